@@ -0,0 +1,133 @@
+use crate::*;
+use crate::tests::*;
+
+fn window(class: &str, name: &str) -> ActiveWindowInfo {
+    ActiveWindowInfo { class: class.to_string(), instance: class.to_lowercase(), name: name.to_string(), ..Default::default() }
+}
+
+/// The callbacks run concurrently, compare the output lines regardless of their order.
+async fn collect_stdout_lines(api: &mut ScriptTestingAPI) -> Vec<String> {
+    let mut lines: Vec<String> = api.collect_stdout().await.lines().map(str::to_string).collect();
+    lines.sort();
+    lines
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn active_window_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/active-window.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(100);
+    sleep(200);
+
+    // no active window
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_A, 1).to_input_ev(),
+        KeyAction::new(*KEY_A, 0).to_input_ev(),
+    ]);
+
+    // window class
+    api.set_active_window(window("firefox", "Mozilla Firefox")).await?;
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    api.set_active_window(window("Thunderbird", "Inbox")).await?;
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_B, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_B, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_C, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_C, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+    assert_eq!(collect_stdout_lines(&mut api).await, vec![
+        "the active window class is: Thunderbird",
+        "the active window class is: firefox",
+        "the active window title is: Inbox",
+        "the active window title is: Mozilla Firefox",
+    ]);
+
+    // the key release is handled by the mapping that handled the key press, even if the window changed
+    api.set_active_window(window("firefox", "Mozilla Firefox")).await?;
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.set_active_window(window("Thunderbird", "Inbox")).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_B, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_B, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+    api.collect_stdout().await;
+
+    // nested selectors
+    api.set_active_window(window("firefox", "GitHub - Mozilla Firefox")).await?;
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    api.set_active_window(window("kitty", "GitHub")).await?;
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_D, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_D, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_A, 1).to_input_ev(),
+        KeyAction::new(*KEY_A, 0).to_input_ev(),
+    ]);
+    api.collect_stdout().await;
+
+    // title changes
+    api.set_active_window(window("firefox", "Mozilla Firefox")).await?;
+    sleep(100);
+    api.collect_stdout().await;
+
+    api.change_active_window_title(window("firefox", "GitHub - Mozilla Firefox")).await?;
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_D, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_D, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+    assert_eq!(api.collect_stdout().await, "the active window title is: GitHub - Mozilla Firefox\n");
+
+    // process selectors
+    api.set_active_window(ActiveWindowInfo {
+        class: "Code".to_string(),
+        name: "main.rs - Visual Studio Code".to_string(),
+        pid: Some(4242),
+        process: Some(ProcessInfo { name: "code".to_string(), ..Default::default() }),
+        ..Default::default()
+    }).await?;
+    sleep(100);
+    api.collect_stdout().await;
+
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![]);
+    assert_eq!(api.collect_stdout().await, "typing in code (pid 4242)\n");
+
+    api.stop().await;
+
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use evdev_rs::enums::EventType;
+use crate::*;
+use crate::tests::*;
+use indoc::indoc;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn collections_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/collections.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(100);
+    sleep(200);
+
+    let expected = indoc! {r#"
+    ["z", "a", "b", "c"]
+    the list has 4 elements, the first one is z
+    ["a", "b", "d"]
+    on my way
+    ["brb", "omw", "ty"]
+    {"rows": [[1, 2], [5, 4]]}
+    0: a
+    1: b
+    2: d
+    "#};
+    assert_eq!(api.collect_stdout().await, expected);
+    assert!(api.collect_errors().await.is_empty());
+
+    // the mapping created from the map entry
+    api.write_action(KeyAction::new(*KEY_B, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_B, 0)).await?;
+    sleep(100);
+
+    let typed: Vec<InputEvent> = api.collect_output_ev().await.into_iter()
+        .filter(|ev| matches!(ev.event_code, EventCode::EV_KEY(_)) && ev.value == 1)
+        .collect();
+    let expected: Vec<InputEvent> = "bravo".chars()
+        .map(|ch| KeyAction::new(Key::from_str(&EventType::EV_KEY, &format!("KEY_{}", ch.to_ascii_uppercase())).unwrap(), 1).to_input_ev())
+        .collect();
+    assert_eq!(typed, expected);
+
+    api.stop().await;
+    Ok(())
+}
@@ -18,6 +18,14 @@ async fn control_statements_test() -> Result<()> {
     i is 1
     i is 2
     i is 4
+    name is x
+    name is y
+    n is 1
+    n is 3
+    counting from 10
+    w is 1
+    w is 2
+    l is 32
     "};
     assert_eq!(&*output, expected);
 
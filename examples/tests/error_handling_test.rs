@@ -0,0 +1,34 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn error_handling_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/error-handling.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(100);
+    sleep(200);
+
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    sleep(100);
+
+    let errors = api.collect_errors().await;
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].to_string(), "line 17, column 16: cannot add number and bool");
+    assert_eq!(api.collect_stdout().await, "a was pressed 1 times\n");
+
+    // the script keeps running after the error
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_B, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_B, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_errors().await.len(), 1);
+    assert_eq!(api.collect_stdout().await, "a was pressed 2 times\nb still works\n");
+
+    api.stop().await;
+    Ok(())
+}
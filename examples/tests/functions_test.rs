@@ -16,6 +16,7 @@ async fn functions_test() -> Result<()> {
     hello world
     hello from my_function
     1 + 2 = 3
+    pushed a
     "};
     assert_eq!(&*output, expected);
 
@@ -0,0 +1,51 @@
+use evdev_rs::enums::EV_ABS;
+
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn gamepad_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/gamepad.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(100);
+    sleep(200);
+
+    // stick thresholds
+    api.write_action(KeyAction::new(abs_key(EV_ABS::ABS_X), -20000)).await?;
+    api.write_action(KeyAction::new(abs_key(EV_ABS::ABS_X), -25000)).await?;
+    api.write_action(KeyAction::new(abs_key(EV_ABS::ABS_X), 1000)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT, 1).to_input_ev(),
+        KeyAction::new(*KEY_LEFT, 0).to_input_ev(),
+    ]);
+
+    // axes without thresholds are forwarded, values within the deadzone are centered
+    api.write_action(KeyAction::new(abs_key(EV_ABS::ABS_RX), 3000)).await?;
+    api.write_action(KeyAction::new(abs_key(EV_ABS::ABS_Y), 3000)).await?;
+    api.write_action(KeyAction::new(abs_key(EV_ABS::ABS_Y), -3000)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(abs_key(EV_ABS::ABS_RX), 3000).to_input_ev(),
+    ]);
+
+    // keys to axes
+    api.write_action(KeyAction::new(*KEY_W, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_W, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(abs_key(EV_ABS::ABS_RY), -32767).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(abs_key(EV_ABS::ABS_RY), 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn hot_reload_test() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("map2-hot-reload-test-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let script_path = dir.join("hot-reload.m2");
+    let script = fs::read_to_string("examples/hot-reload.m2")?;
+    fs::write(&script_path, &script)?;
+
+    let mut params = ScriptTestingParameters::default();
+    let script_path_str = script_path.display().to_string();
+    params.script_path = &script_path_str;
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(100);
+    sleep(200);
+    assert_eq!(api.collect_stdout().await, "script loaded\n");
+
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 1)).await?;
+    sleep(100);
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT_SHIFT, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    // the held key gets released and the new mappings replace the old ones
+    fs::write(&script_path, script.replace("a::b;", "a::c;"))?;
+    api.reload_script().await?;
+    sleep(200);
+    assert_eq!(api.collect_stdout().await, "script loaded\n");
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT_SHIFT, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    sleep(100);
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_C, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_C, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    // a script that can't be parsed leaves the running script alone
+    fs::write(&script_path, "a::d")?;
+    assert!(api.reload_script().await.is_err());
+
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    sleep(100);
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_C, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_C, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    // the combos of the previous script stay active until the global scope of the new script finished running
+    fs::write(&script_path, script.replace("j & k::esc;", "j & k::tab;\nsleep(1000);"))?;
+    api.reload_script().await?;
+    sleep(200);
+    assert_eq!(api.collect_stdout().await, "script loaded\n");
+
+    api.event_delay = None;
+    api.write_action(KeyAction::new(*KEY_J, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_K, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_K, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_J, 0)).await?;
+    sleep(200);
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_ESC, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_ESC, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    sleep(1000);
+    api.write_action(KeyAction::new(*KEY_J, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_K, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_K, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_J, 0)).await?;
+    sleep(200);
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_TAB, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_TAB, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+    api.event_delay = Some(100);
+
+    // a global scope that never finishes doesn't keep the previous script running forever, what it registers after
+    // the new script took over is still added to it
+    fs::write(&script_path, script.replace("a::b;", "a::d;") + "\nsleep(2500);\nb::e;\nloop { sleep(100); print(\"tick\"); }\n")?;
+    api.reload_script().await?;
+    sleep(crate::event_handlers::RELOAD_TIMEOUT.as_millis() as u64 + 200);
+    assert_eq!(api.collect_stdout().await, "script loaded\n");
+
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    sleep(100);
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_D, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_D, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    sleep(500);
+    api.write_action(KeyAction::new(*KEY_B, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_B, 0)).await?;
+    sleep(100);
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_E, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_E, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    // the global scope of the replaced script stops running
+    fs::write(&script_path, &script)?;
+    api.reload_script().await?;
+    sleep(200);
+    assert!(api.collect_stdout().await.ends_with("script loaded\n"));
+    sleep(300);
+    assert_eq!(api.collect_stdout().await, "");
+
+    api.stop().await;
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
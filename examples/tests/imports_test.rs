@@ -0,0 +1,34 @@
+use crate::*;
+use crate::tests::*;
+use indoc::indoc;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn imports_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/imports.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(100);
+    sleep(200);
+
+    let expected = indoc! {"
+    the common layout
+    hello world, greetings from the common layout
+    greeted 1 time(s)
+    "};
+    assert_eq!(api.collect_stdout().await, expected);
+    assert!(api.collect_errors().await.is_empty());
+
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    sleep(100);
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_B, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_B, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    api.stop().await;
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use evdev_rs::enums::EventType;
+
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn layers_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/layers.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(100);
+    sleep(200);
+
+    // momentary layer
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_H, 1).to_input_ev(),
+        KeyAction::new(*KEY_H, 0).to_input_ev(),
+    ]);
+
+    // the key release is handled by the layer that handled the key press
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_H, 1).to_input_ev(),
+        KeyAction::new(*KEY_H, 0).to_input_ev(),
+    ]);
+
+    // release only mappings, the layer activated while the key was held doesn't map it
+    let f10 = Key::from_str(&EventType::EV_KEY, "KEY_F10")?;
+    api.write_action(KeyAction::new(f10, 1)).await?;
+    api.write_action(KeyAction::new(f10, 0)).await?;
+    api.write_action(KeyAction::new(f10, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 1)).await?;
+    api.write_action(KeyAction::new(f10, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(f10, 1).to_input_ev(),
+        KeyAction::new(f10, 1).to_input_ev(),
+    ]);
+    assert_eq!(api.collect_stdout().await, "f10 released\nf10 released\n");
+
+    // one-shot layer
+    api.write_action(KeyAction::new(*KEY_F12, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_F12, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_J, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_J, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_J, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_J, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_1, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_1, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_J, 1).to_input_ev(),
+        KeyAction::new(*KEY_J, 0).to_input_ev(),
+    ]);
+
+    // toggled layer
+    api.write_action(KeyAction::new(*KEY_F11, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_F11, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_K, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_K, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_F11, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_F11, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_K, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_K, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_2, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_2, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_K, 1).to_input_ev(),
+        KeyAction::new(*KEY_K, 0).to_input_ev(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
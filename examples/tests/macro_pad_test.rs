@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn macro_pad_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/macro-pad.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(100);
+    sleep(200);
+
+    let macro_pad = InputDeviceInfo {
+        path: PathBuf::from("/dev/input/event7"),
+        name: "Macro Pad Keyboard".to_string(),
+        vendor: 0x1209,
+        product: 0xbeef,
+    };
+
+    // device scoped mappings
+    api.write_device_action(&macro_pad, KeyAction::new(*KEY_B, 1)).await?;
+    api.write_device_action(&macro_pad, KeyAction::new(*KEY_B, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![]);
+    assert_eq!(api.collect_stdout().await, "macro pad 'b' pressed\n");
+
+    // other devices fall through to the regular mappings
+    api.write_action(KeyAction::new(*KEY_B, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_B, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_B, 1).to_input_ev(),
+        KeyAction::new(*KEY_B, 0).to_input_ev(),
+        KeyAction::new(*KEY_B, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_B, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use evdev_rs::enums::EventType;
+
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn mapping_management_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/mapping-management.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(100);
+    sleep(200);
+
+    let f1 = Key::from_str(&EventType::EV_KEY, "KEY_F1")?;
+    let f2 = Key::from_str(&EventType::EV_KEY, "KEY_F2")?;
+    let f3 = Key::from_str(&EventType::EV_KEY, "KEY_F3")?;
+
+    // list mappings
+    api.write_action(KeyAction::new(f1, 1)).await?;
+    api.write_action(KeyAction::new(f1, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_stdout().await, "6 mappings\n^c\na\nf1\nf2\nf3\n{x down}\n");
+
+    // remove a single mapping
+    api.write_action(KeyAction::new(f2, 1)).await?;
+    api.write_action(KeyAction::new(f2, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_A, 1).to_input_ev(),
+        KeyAction::new(*KEY_A, 0).to_input_ev(),
+        KeyAction::new(*KEY_CAPSLOCK, 1).to_input_ev(),
+        KeyAction::new(*KEY_CAPSLOCK, 0).to_input_ev(),
+    ]);
+
+    // the navigation layer is still active
+    api.write_action(KeyAction::new(*KEY_TAB, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_TAB, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    // remove all mappings
+    api.write_action(KeyAction::new(f3, 1)).await?;
+    api.write_action(KeyAction::new(f3, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_X, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_X, 0)).await?;
+    api.write_action(KeyAction::new(f1, 1)).await?;
+    api.write_action(KeyAction::new(f1, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_TAB, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_TAB, 0)).await?;
+    sleep(100);
+
+    // the release of 'f3' is still handled by the mapping that handled its key press
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_X, 1).to_input_ev(),
+        KeyAction::new(*KEY_X, 0).to_input_ev(),
+        KeyAction::new(f1, 1).to_input_ev(),
+        KeyAction::new(f1, 0).to_input_ev(),
+        KeyAction::new(*KEY_TAB, 1).to_input_ev(),
+        KeyAction::new(*KEY_H, 1).to_input_ev(),
+        KeyAction::new(*KEY_H, 0).to_input_ev(),
+        KeyAction::new(*KEY_TAB, 0).to_input_ev(),
+    ]);
+    assert_eq!(api.collect_stdout().await, "");
+
+    api.stop().await;
+
+    Ok(())
+}
@@ -18,6 +18,12 @@ async fn math_test() -> Result<()> {
     2 times 4 is: 8
     4 divided by 2 is: 2
     result of complicated calculation: 3.8
+    2 + 3 * 4 % 5 is: 4
+    -7 modulo 3 is: -1
+    counter is: 14
+    ababab
+    true
+    check was called 0 times
     "};
     assert_eq!(&*output, expected);
 
@@ -1,4 +1,19 @@
 mod functions_test;
 mod math_test;
 mod hjkl_arrow_keys_test;
-mod control_statements_test;
\ No newline at end of file
+mod control_statements_test;
+mod tap_hold_test;
+mod layers_test;
+mod combos_test;
+mod sequences_test;
+mod sided_modifiers_test;
+mod mouse_test;
+mod gamepad_test;
+mod macro_pad_test;
+mod mapping_management_test;
+mod active_window_test;
+mod error_handling_test;
+mod collections_test;
+mod strings_test;
+mod imports_test;
+mod hot_reload_test;
\ No newline at end of file
@@ -0,0 +1,54 @@
+use evdev_rs::enums::{EV_REL, EventType};
+
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn mouse_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/mouse.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(100);
+    sleep(200);
+
+    let btn_right = Key::from_str(&EventType::EV_KEY, "BTN_RIGHT")?;
+    let volume_up = Key::from_str(&EventType::EV_KEY, "KEY_VOLUMEUP")?;
+
+    // wheel mapping
+    api.write_action(KeyAction::new(btn_right, 1)).await?;
+    api.write_action(KeyAction::new(rel_key(EV_REL::REL_WHEEL_HI_RES), 120)).await?;
+    api.write_action(KeyAction::new(rel_key(EV_REL::REL_WHEEL), 1)).await?;
+    api.write_action(KeyAction::new(btn_right, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(volume_up, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(volume_up, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    // unmapped wheel events are forwarded
+    api.write_action(KeyAction::new(rel_key(EV_REL::REL_WHEEL_HI_RES), -120)).await?;
+    api.write_action(KeyAction::new(rel_key(EV_REL::REL_WHEEL), -1)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(rel_key(EV_REL::REL_WHEEL_HI_RES), -120).to_input_ev(),
+        KeyAction::new(rel_key(EV_REL::REL_WHEEL), -1).to_input_ev(),
+    ]);
+
+    // callback
+    api.write_action(KeyAction::new(rel_key(EV_REL::REL_X), 5)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(rel_key(EV_REL::REL_X), 10).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use crate::*;
+use crate::tests::*;
+use crate::parsing::parser::parse_key_sequence;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn sequences_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/sequences.m2";
+
+    let mut api = test_script(params).await?;
+    sleep(200);
+
+    // full sequence
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_G, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_G, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_S, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_S, 0)).await?;
+    sleep(200);
+
+    assert_eq!(api.collect_stdout().await, "sequence!\n");
+    assert_eq!(api.collect_output_ev().await, vec![]);
+
+    // shorter sequence after the timeout
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_G, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_G, 0)).await?;
+    sleep(700);
+
+    assert_eq!(api.collect_output_ev().await, parse_key_sequence("hello")?.into_iter()
+        .flat_map(|action| vec![action.to_input_ev(), SYN_REPORT.clone()])
+        .collect::<Vec<_>>());
+
+    // mismatch releases the held back keys
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    sleep(200);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_CAPSLOCK, 1).to_input_ev(),
+        KeyAction::new(*KEY_CAPSLOCK, 0).to_input_ev(),
+        KeyAction::new(*KEY_A, 1).to_input_ev(),
+        KeyAction::new(*KEY_A, 0).to_input_ev(),
+    ]);
+
+    // cancel keys
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_ESC, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_ESC, 0)).await?;
+    sleep(700);
+
+    assert_eq!(api.collect_output_ev().await, vec![]);
+
+    api.stop().await;
+
+    Ok(())
+}
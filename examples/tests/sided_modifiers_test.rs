@@ -0,0 +1,68 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn sided_modifiers_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/sided-modifiers.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(100);
+    sleep(200);
+
+    api.write_action(KeyAction::new(*KEY_RIGHT_ALT, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_RIGHT_ALT, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_RIGHT_ALT, 1).to_input_ev(),
+        KeyAction::new(*KEY_RIGHT_ALT, 0).to_input_ev(),
+        KeyAction::new(*KEY_HOME, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_HOME, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_RIGHT_ALT, 1).to_input_ev(),
+        KeyAction::new(*KEY_RIGHT_ALT, 0).to_input_ev(),
+    ]);
+
+    api.write_action(KeyAction::new(*KEY_LEFT_ALT, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_LEFT_ALT, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT_ALT, 1).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_ALT, 0).to_input_ev(),
+        KeyAction::new(*KEY_LEFT, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_LEFT_ALT, 1).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_ALT, 0).to_input_ev(),
+    ]);
+
+    // unsided flags match either side
+    api.write_action(KeyAction::new(*KEY_RIGHT_CTRL, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_H, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_RIGHT_CTRL, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_RIGHT_CTRL, 1).to_input_ev(),
+        KeyAction::new(*KEY_RIGHT_CTRL, 0).to_input_ev(),
+        KeyAction::new(*KEY_BACKSPACE, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_BACKSPACE, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_RIGHT_CTRL, 1).to_input_ev(),
+        KeyAction::new(*KEY_RIGHT_CTRL, 0).to_input_ev(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
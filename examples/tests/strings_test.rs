@@ -0,0 +1,50 @@
+use crate::*;
+use crate::tests::*;
+use indoc::indoc;
+
+fn typed_keys(events: Vec<InputEvent>) -> Vec<String> {
+    events.into_iter()
+        .filter(|ev| ev.value == 1)
+        .filter_map(|ev| match ev.event_code {
+            EventCode::EV_KEY(key) => Some(format!("{:?}", key)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn strings_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/strings.m2";
+
+    let mut api = test_script(params).await?;
+    api.event_delay = Some(100);
+    sleep(200);
+
+    let expected = indoc! {"
+    hello world, 1 + 2 is 3
+    say \"hi\"\tC:\\maps
+    two
+    lines
+    \u{263A} costs $5 rather than 10
+    "};
+    assert_eq!(api.collect_stdout().await, expected);
+    assert!(api.collect_errors().await.is_empty());
+
+    api.write_action(KeyAction::new(*KEY_A, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_A, 0)).await?;
+    sleep(100);
+    assert_eq!(typed_keys(api.collect_output_ev().await), vec![
+        "KEY_LEFTSHIFT", "KEY_LEFTBRACE", "KEY_LEFTSHIFT", "KEY_APOSTROPHE", "KEY_LEFTSHIFT", "KEY_RIGHTBRACE",
+    ]);
+
+    api.write_action(KeyAction::new(*KEY_B, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_B, 0)).await?;
+    sleep(100);
+    assert_eq!(typed_keys(api.collect_output_ev().await), vec![
+        "KEY_LEFTSHIFT", "KEY_LEFTBRACE", "KEY_W", "KEY_O", "KEY_R", "KEY_L", "KEY_D", "KEY_LEFTSHIFT", "KEY_RIGHTBRACE",
+    ]);
+
+    api.stop().await;
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use crate::*;
+use crate::tests::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn tap_hold_test() -> Result<()> {
+    let mut params = ScriptTestingParameters::default();
+    params.script_path = "examples/tap-hold.m2";
+
+    let mut api = test_script(params).await?;
+    sleep(200);
+
+    // tap
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 0)).await?;
+    sleep(300);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_ESC, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_ESC, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    // hold
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 1)).await?;
+    sleep(300);
+    api.write_action(KeyAction::new(*KEY_C, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_C, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_CAPSLOCK, 0)).await?;
+    sleep(100);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT_CTRL, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_C, 1).to_input_ev(),
+        KeyAction::new(*KEY_C, 0).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_CTRL, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    // permissive hold, another key is tapped within the tapping term
+    api.write_action(KeyAction::new(*KEY_TAB, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_C, 1)).await?;
+    api.write_action(KeyAction::new(*KEY_C, 0)).await?;
+    api.write_action(KeyAction::new(*KEY_TAB, 0)).await?;
+    sleep(300);
+
+    assert_eq!(api.collect_output_ev().await, vec![
+        KeyAction::new(*KEY_LEFT_ALT, 1).to_input_ev(),
+        SYN_REPORT.clone(),
+        KeyAction::new(*KEY_C, 1).to_input_ev(),
+        KeyAction::new(*KEY_C, 0).to_input_ev(),
+        KeyAction::new(*KEY_LEFT_ALT, 0).to_input_ev(),
+        SYN_REPORT.clone(),
+    ]);
+
+    api.stop().await;
+
+    Ok(())
+}
@@ -2,7 +2,7 @@ use crate::*;
 
 impl Block {
     pub(crate) fn push_expr(&mut self, expr: Expr) -> &mut Self {
-        self.statements.push(Stmt::Expr(expr));
+        self.statements.push(Stmt::Expr(expr).into());
         self
     }
 }
@@ -10,7 +10,7 @@ impl Block {
 impl Expr {
 
     pub(crate) fn map_key_click_block(from: KeyClickActionWithMods, mut to: Block) -> Self {
-        to.statements.insert(0, Stmt::Expr(Expr::ReleaseRestoreModifiers(from.modifiers.clone(), KeyModifierFlags::new(), TYPE_UP)));
+        to.statements.insert(0, Stmt::Expr(Expr::ReleaseRestoreModifiers(from.modifiers.clone(), KeyModifierFlags::new(), TYPE_UP)).into());
         Expr::KeyMapping(vec![
             KeyMapping { from: KeyActionWithMods::new(from.key, TYPE_DOWN, from.modifiers), to },
             KeyMapping { from: KeyActionWithMods::new(from.key, TYPE_REPEAT, from.modifiers), to: Block::new() }, // stub
@@ -19,7 +19,7 @@ impl Expr {
     }
 
     pub(crate) fn map_key_block(from: KeyActionWithMods, mut to: Block) -> Self {
-        to.statements.insert(0, Stmt::Expr(Expr::ReleaseRestoreModifiers(from.modifiers.clone(), KeyModifierFlags::new(), TYPE_UP)));
+        to.statements.insert(0, Stmt::Expr(Expr::ReleaseRestoreModifiers(from.modifiers.clone(), KeyModifierFlags::new(), TYPE_UP)).into());
 
         Expr::KeyMapping(vec![KeyMapping { from, to }])
     }
@@ -29,18 +29,18 @@ impl Expr {
 
         block.push_expr(Expr::ReleaseRestoreModifiers(from.modifiers.clone(), to.modifiers.clone(), TYPE_UP));
 
-        if !from.modifiers.ctrl && to.modifiers.ctrl { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_CTRL, value: TYPE_DOWN })); }
-        if !from.modifiers.alt && to.modifiers.alt { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_ALT, value: TYPE_DOWN })); }
-        if !from.modifiers.shift && to.modifiers.shift { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_SHIFT, value: TYPE_DOWN })); }
-        if !from.modifiers.meta && to.modifiers.meta { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_META, value: TYPE_DOWN })); }
+        if !from.modifiers.ctrl && to.modifiers.ctrl { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.ctrl_key(), value: TYPE_DOWN })); }
+        if !from.modifiers.alt && to.modifiers.alt { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.alt_key(), value: TYPE_DOWN })); }
+        if !from.modifiers.shift && to.modifiers.shift { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.shift_key(), value: TYPE_DOWN })); }
+        if !from.modifiers.meta && to.modifiers.meta { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.meta_key(), value: TYPE_DOWN })); }
 
         block.push_expr(Expr::KeyAction(KeyAction { key: to.key, value: to.value }));
 
         // revert to original
-        if !from.modifiers.ctrl && to.modifiers.ctrl { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_CTRL, value: TYPE_UP })); }
-        if !from.modifiers.alt && to.modifiers.alt { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_ALT, value: TYPE_UP })); }
-        if !from.modifiers.shift && to.modifiers.shift { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_SHIFT, value: TYPE_UP })); }
-        if !from.modifiers.meta && to.modifiers.meta { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_META, value: TYPE_UP })); }
+        if !from.modifiers.ctrl && to.modifiers.ctrl { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.ctrl_key(), value: TYPE_UP })); }
+        if !from.modifiers.alt && to.modifiers.alt { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.alt_key(), value: TYPE_UP })); }
+        if !from.modifiers.shift && to.modifiers.shift { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.shift_key(), value: TYPE_UP })); }
+        if !from.modifiers.meta && to.modifiers.meta { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.meta_key(), value: TYPE_UP })); }
 
         block.push_expr(Expr::ReleaseRestoreModifiers(from.modifiers.clone(), to.modifiers.clone(), TYPE_DOWN));
 
@@ -53,18 +53,18 @@ impl Expr {
 
         block.push_expr(Expr::ReleaseRestoreModifiers(from.modifiers.clone(), to.modifiers.clone(), TYPE_UP));
 
-        if !from.modifiers.ctrl && to.modifiers.ctrl { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_CTRL, value: TYPE_DOWN })); }
-        if !from.modifiers.alt && to.modifiers.alt { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_ALT, value: TYPE_DOWN })); }
-        if !from.modifiers.shift && to.modifiers.shift { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_SHIFT, value: TYPE_DOWN })); }
-        if !from.modifiers.meta && to.modifiers.meta { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_META, value: TYPE_DOWN })); }
+        if !from.modifiers.ctrl && to.modifiers.ctrl { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.ctrl_key(), value: TYPE_DOWN })); }
+        if !from.modifiers.alt && to.modifiers.alt { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.alt_key(), value: TYPE_DOWN })); }
+        if !from.modifiers.shift && to.modifiers.shift { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.shift_key(), value: TYPE_DOWN })); }
+        if !from.modifiers.meta && to.modifiers.meta { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.meta_key(), value: TYPE_DOWN })); }
 
         block.push_expr(Expr::KeyAction(KeyAction { key: to.key, value: to.value }));
 
         // revert to original
-        if !from.modifiers.ctrl && to.modifiers.ctrl { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_CTRL, value: TYPE_UP })); }
-        if !from.modifiers.alt && to.modifiers.alt { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_ALT, value: TYPE_UP })); }
-        if !from.modifiers.shift && to.modifiers.shift { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_SHIFT, value: TYPE_UP })); }
-        if !from.modifiers.meta && to.modifiers.meta { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_META, value: TYPE_UP })); }
+        if !from.modifiers.ctrl && to.modifiers.ctrl { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.ctrl_key(), value: TYPE_UP })); }
+        if !from.modifiers.alt && to.modifiers.alt { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.alt_key(), value: TYPE_UP })); }
+        if !from.modifiers.shift && to.modifiers.shift { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.shift_key(), value: TYPE_UP })); }
+        if !from.modifiers.meta && to.modifiers.meta { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.meta_key(), value: TYPE_UP })); }
 
         block.push_expr(Expr::ReleaseRestoreModifiers(from.modifiers.clone(), to.modifiers.clone(), TYPE_DOWN));
 
@@ -80,19 +80,19 @@ impl Expr {
 
         block.push_expr(Expr::ReleaseRestoreModifiers(from.modifiers.clone(), to.modifiers.clone(), TYPE_UP));
 
-        if !from.modifiers.ctrl && to.modifiers.ctrl { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_CTRL, value: TYPE_DOWN })); }
-        if !from.modifiers.alt && to.modifiers.alt { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_ALT, value: TYPE_DOWN })); }
-        if !from.modifiers.shift && to.modifiers.shift { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_SHIFT, value: TYPE_DOWN })); }
-        if !from.modifiers.meta && to.modifiers.meta { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_META, value: TYPE_DOWN })); }
+        if !from.modifiers.ctrl && to.modifiers.ctrl { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.ctrl_key(), value: TYPE_DOWN })); }
+        if !from.modifiers.alt && to.modifiers.alt { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.alt_key(), value: TYPE_DOWN })); }
+        if !from.modifiers.shift && to.modifiers.shift { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.shift_key(), value: TYPE_DOWN })); }
+        if !from.modifiers.meta && to.modifiers.meta { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.meta_key(), value: TYPE_DOWN })); }
 
         block.push_expr(Expr::KeyAction(KeyAction { key: to.key, value: TYPE_DOWN }));
         block.push_expr(Expr::KeyAction(KeyAction { key: to.key, value: TYPE_UP }));
 
         // revert to original
-        if !from.modifiers.ctrl && to.modifiers.ctrl { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_CTRL, value: TYPE_UP })); }
-        if !from.modifiers.alt && to.modifiers.alt { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_ALT, value: TYPE_UP })); }
-        if !from.modifiers.shift && to.modifiers.shift { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_SHIFT, value: TYPE_UP })); }
-        if !from.modifiers.meta && to.modifiers.meta { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_META, value: TYPE_UP })); }
+        if !from.modifiers.ctrl && to.modifiers.ctrl { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.ctrl_key(), value: TYPE_UP })); }
+        if !from.modifiers.alt && to.modifiers.alt { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.alt_key(), value: TYPE_UP })); }
+        if !from.modifiers.shift && to.modifiers.shift { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.shift_key(), value: TYPE_UP })); }
+        if !from.modifiers.meta && to.modifiers.meta { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.meta_key(), value: TYPE_UP })); }
 
         block.push_expr(Expr::ReleaseRestoreModifiers(from.modifiers.clone(), to.modifiers.clone(), TYPE_DOWN));
 
@@ -105,10 +105,10 @@ impl Expr {
             let mut block = Block::new();
             block.push_expr(Expr::ReleaseRestoreModifiers(from.modifiers.clone(), to.modifiers.clone(), TYPE_UP));
 
-            if to.modifiers.ctrl && !from.modifiers.ctrl { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_CTRL, value: TYPE_DOWN })); }
-            if to.modifiers.alt && !from.modifiers.alt { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_ALT, value: TYPE_DOWN })); }
-            if to.modifiers.shift && !from.modifiers.shift { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_SHIFT, value: TYPE_DOWN })); }
-            if to.modifiers.meta && !from.modifiers.meta { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_META, value: TYPE_DOWN })); }
+            if to.modifiers.ctrl && !from.modifiers.ctrl { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.ctrl_key(), value: TYPE_DOWN })); }
+            if to.modifiers.alt && !from.modifiers.alt { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.alt_key(), value: TYPE_DOWN })); }
+            if to.modifiers.shift && !from.modifiers.shift { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.shift_key(), value: TYPE_DOWN })); }
+            if to.modifiers.meta && !from.modifiers.meta { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.meta_key(), value: TYPE_DOWN })); }
 
             block.push_expr(Expr::KeyAction(KeyAction { key: to.key, value: TYPE_DOWN }));
 
@@ -119,10 +119,10 @@ impl Expr {
             let mut block = Block::new();
             block.push_expr(Expr::KeyAction(KeyAction { key: to.key, value: TYPE_UP }));
 
-            if to.modifiers.ctrl && !from.modifiers.ctrl { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_CTRL, value: TYPE_UP })); }
-            if to.modifiers.alt && !from.modifiers.alt { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_ALT, value: TYPE_UP })); }
-            if to.modifiers.shift && !from.modifiers.shift { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_SHIFT, value: TYPE_UP })); }
-            if to.modifiers.meta && !from.modifiers.meta { block.push_expr(Expr::KeyAction(KeyAction { key: *KEY_LEFT_META, value: TYPE_UP })); }
+            if to.modifiers.ctrl && !from.modifiers.ctrl { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.ctrl_key(), value: TYPE_UP })); }
+            if to.modifiers.alt && !from.modifiers.alt { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.alt_key(), value: TYPE_UP })); }
+            if to.modifiers.shift && !from.modifiers.shift { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.shift_key(), value: TYPE_UP })); }
+            if to.modifiers.meta && !from.modifiers.meta { block.push_expr(Expr::KeyAction(KeyAction { key: to.modifiers.meta_key(), value: TYPE_UP })); }
 
             block.push_expr(Expr::ReleaseRestoreModifiers(from.modifiers.clone(), to.modifiers.clone(), TYPE_DOWN));
 
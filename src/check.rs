@@ -0,0 +1,388 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use crate::*;
+use crate::parsing::key_sequence::unknown_key_groups;
+use crate::parsing::parser::parse_key_sequence;
+use crate::runtime::builtin_functions::builtin_arity;
+use crate::script::{self, ScriptFile};
+
+/// A problem found in a script without running it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    fn new(message: impl Into<String>, span: Span) -> Self {
+        Diagnostic { message: message.into(), span }
+    }
+
+    /// Renders the diagnostic together with the file and code it points to.
+    pub fn render(&self, files: &[ScriptFile]) -> String {
+        match files.get(self.span.file) {
+            Some(file) if self.span.is_known() && self.span.end <= file.source.len() =>
+                self.span.render(&file.source, Some(&file.path.display().to_string()), &self.message),
+            _ => format!("error: {}", self.message),
+        }
+    }
+}
+
+/// Loads the scripts and reports syntax errors and the problems found by [`check_script`] on the standard error output.
+/// Returns `true` if no problems were found.
+pub fn check_scripts(paths: &[PathBuf]) -> bool {
+    let mut ok = true;
+    for path in paths {
+        let (block, files) = match script::load_script(path) {
+            Ok(script) => script,
+            Err(err) => {
+                eprintln!("{}\n", err);
+                ok = false;
+                continue;
+            }
+        };
+
+        for diagnostic in check_script(&block) {
+            eprintln!("{}\n", diagnostic.render(&files));
+            ok = false;
+        }
+    }
+    ok
+}
+
+/// Looks for mistakes that would only show up once the script runs, i.e. variables that are never declared, calls to
+/// built-in functions with the wrong number of arguments, invalid key sequences, mappings that replace each other
+/// and unreachable statements. Imported scripts are checked as well.
+pub fn check_script(block: &Block) -> Vec<Diagnostic> {
+    let mut checker = Checker::default();
+    checker.check_module(block);
+
+    let mut diagnostics = checker.diagnostics;
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.span.file, diagnostic.span.start));
+    diagnostics
+}
+
+struct Scope {
+    /// the variables that were declared so far
+    declared: HashSet<String>,
+    /// all variables declared in the block, code that runs later on (i.e. lambdas) can see them
+    hoisted: HashSet<String>,
+    /// the scope belongs to code that runs later on
+    deferred: bool,
+}
+
+#[derive(Default)]
+struct Checker {
+    diagnostics: Vec<Diagnostic>,
+    scopes: Vec<Scope>,
+    /// imported scripts that were checked already
+    modules: HashSet<*const Module>,
+}
+
+impl Checker {
+    fn check_module(&mut self, block: &Block) {
+        let scopes = std::mem::take(&mut self.scopes);
+        self.check_block(block, false, vec![], Span::default());
+        self.scopes = scopes;
+    }
+
+    /// Checks the statements of a block in a scope of its own, `params` are declared in the new scope.
+    fn check_block(&mut self, block: &Block, deferred: bool, params: Vec<String>, span: Span) {
+        let hoisted = block.statements.iter().flat_map(|stmt| declared_names(&stmt.node)).collect();
+        self.scopes.push(Scope { declared: params.into_iter().collect(), hoisted, deferred });
+
+        let mut mappings: HashMap<KeyActionWithMods, Span> = HashMap::new();
+        let mut unreachable_reported = false;
+        for (idx, stmt) in block.statements.iter().enumerate() {
+            let stmt_span = if stmt.span.is_known() { stmt.span } else { span };
+
+            if !unreachable_reported && idx > 0 && is_exit(&block.statements[idx - 1].node) {
+                self.diagnostics.push(Diagnostic::new("unreachable statement", stmt_span));
+                unreachable_reported = true;
+            }
+
+            // later mappings for the same trigger replace the earlier ones
+            if let Stmt::Expr(Expr::KeyMapping(key_mappings)) = &stmt.node {
+                if let Some((from, previous)) = key_mappings.iter().find_map(|mapping| mappings.get(&mapping.from).map(|span| (mapping.from, *span))) {
+                    self.diagnostics.push(Diagnostic::new(
+                        format!("the mapping for '{}' replaces the mapping in line {}", from, previous.line), stmt_span));
+                }
+                for mapping in key_mappings { mappings.insert(mapping.from, stmt_span); }
+            }
+
+            self.check_stmt(&stmt.node, stmt_span);
+        }
+
+        self.scopes.pop();
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt, span: Span) {
+        match stmt {
+            Stmt::Expr(expr) | Stmt::Export(expr) => self.check_expr(expr, span),
+            Stmt::Block(block) => self.check_block(block, false, vec![], span),
+            Stmt::If(branches, else_block) => {
+                for (condition, block) in branches {
+                    self.check_spanned(condition, span);
+                    self.check_block(block, false, vec![], span);
+                }
+                if let Some(block) = else_block { self.check_block(block, false, vec![], span); }
+            }
+            Stmt::For(init, condition, advance, block) => {
+                self.check_spanned(init, span);
+                self.check_spanned(condition, span);
+                self.check_block(block, false, vec![], span);
+                self.check_spanned(advance, span);
+            }
+            Stmt::ForIn(name, iterable, block) => {
+                self.check_spanned(iterable, span);
+                self.check_block(block, false, vec![name.clone()], span);
+            }
+            Stmt::While(condition, block) => {
+                self.check_spanned(condition, span);
+                self.check_block(block, false, vec![], span);
+            }
+            Stmt::Loop(block) => self.check_block(block, false, vec![], span),
+            Stmt::Return(expr) => self.check_spanned(expr, span),
+            Stmt::Continue | Stmt::Break => {}
+            Stmt::Import(import) => {
+                let (module, namespace) = match (&import.module, &import.namespace) {
+                    (Some(module), Some(namespace)) => (module, namespace),
+                    _ => {
+                        self.diagnostics.push(Diagnostic::new("imports are only allowed at the top level", span));
+                        return;
+                    }
+                };
+
+                if self.modules.insert(Arc::as_ptr(module)) { self.check_module(&module.block); }
+                for name in &module.exports { self.declare(format!("{}.{}", namespace, name)); }
+            }
+        }
+    }
+
+    fn check_spanned(&mut self, expr: &Spanned<Expr>, span: Span) {
+        self.check_expr(&expr.node, if expr.span.is_known() { expr.span } else { span });
+    }
+
+    fn check_expr(&mut self, expr: &Expr, span: Span) {
+        match expr {
+            Expr::Eq(left, right) | Expr::Neq(left, right) | Expr::LT(left, right) | Expr::GT(left, right) |
+            Expr::LE(left, right) | Expr::GE(left, right) | Expr::Add(left, right) | Expr::Sub(left, right) |
+            Expr::Div(left, right) | Expr::Mul(left, right) | Expr::Mod(left, right) | Expr::And(left, right) |
+            Expr::Or(left, right) | Expr::Index(left, right) | Expr::Range(left, right, _) => {
+                self.check_spanned(left, span);
+                self.check_spanned(right, span);
+            }
+            Expr::Neg(value) | Expr::Minus(value) => self.check_spanned(value, span),
+            Expr::Init(name, value) => {
+                self.check_spanned(value, span);
+                self.declare(name.clone());
+            }
+            Expr::Assign(name, value) | Expr::AddAssign(name, value) | Expr::SubAssign(name, value) => {
+                self.check_spanned(value, span);
+                self.check_name(name, span);
+            }
+            Expr::Inc(name) | Expr::Name(name) => self.check_name(name, span),
+            Expr::IndexAssign(container, index, value) => {
+                self.check_spanned(container, span);
+                self.check_spanned(index, span);
+                self.check_spanned(value, span);
+            }
+            Expr::KeyMapping(mappings) => {
+                for mapping in mappings { self.check_block(&mapping.to, true, vec![], span); }
+            }
+            Expr::Combo(_, block) | Expr::SequenceMapping(_, block) => self.check_block(block, true, vec![], span),
+            Expr::Lambda(params, block) => self.check_block(block, true, params.clone(), span),
+            Expr::List(values) | Expr::Interpolation(values) => {
+                for value in values { self.check_spanned(value, span); }
+            }
+            Expr::Map(entries) => {
+                for (_, value) in entries { self.check_spanned(value, span); }
+            }
+            Expr::FunctionCall(name, args) => {
+                for arg in args { self.check_spanned(arg, span); }
+                self.check_call(name, args, span);
+            }
+            Expr::TapHold(_) | Expr::Value(_) | Expr::KeyAction(_) | Expr::SleepAction(_) | Expr::ReleaseRestoreModifiers(..) => {}
+        }
+    }
+
+    fn check_call(&mut self, name: &str, args: &[Spanned<Expr>], span: Span) {
+        // functions declared by the script take precedence over the built-in functions of the same name
+        if self.is_declared(name) { return; }
+
+        let (min, max) = match builtin_arity(name) {
+            Some(arity) => arity,
+            None => {
+                if !self.is_declared(name) {
+                    self.diagnostics.push(Diagnostic::new(format!("function '{}' not found in this scope", name), span));
+                }
+                return;
+            }
+        };
+
+        if args.len() < min || matches!(max, Some(max) if args.len() > max) {
+            let expected = match max {
+                Some(max) if max == min => format!("{} argument{}", min, if min == 1 { "" } else { "s" }),
+                Some(max) => format!("{} to {} arguments", min, max),
+                None => format!("at least {} argument{}", min, if min == 1 { "" } else { "s" }),
+            };
+            self.diagnostics.push(Diagnostic::new(format!("function '{}' takes {}, got {}", name, expected, args.len()), span));
+            return;
+        }
+
+        // key sequences that are known before the script runs
+        if let ("send" | "send_modifier" | "set_sequence_cancel_keys", Some(arg)) = (name, args.first()) {
+            if let Expr::Value(ValueType::String(sequence)) = &arg.node {
+                let span = if arg.span.is_known() { arg.span } else { span };
+                if let Err(err) = parse_key_sequence(sequence) {
+                    self.diagnostics.push(Diagnostic::new(err.to_string(), span));
+                }
+                for name in unknown_key_groups(sequence) {
+                    self.diagnostics.push(Diagnostic::new(format!("unknown key '{}', it would be typed as text", name), span));
+                }
+            }
+        }
+    }
+
+    fn declare(&mut self, name: String) {
+        if let Some(scope) = self.scopes.last_mut() { scope.declared.insert(name); }
+    }
+
+    fn is_declared(&self, name: &str) -> bool {
+        let mut deferred = false;
+        for scope in self.scopes.iter().rev() {
+            if scope.declared.contains(name) || (deferred && scope.hoisted.contains(name)) { return true; }
+            deferred |= scope.deferred;
+        }
+        false
+    }
+
+    fn check_name(&mut self, name: &str, span: Span) {
+        if !self.is_declared(name) {
+            self.diagnostics.push(Diagnostic::new(format!("variable '{}' does not exist", name), span));
+        }
+    }
+}
+
+/// The variables a statement declares in the scope it runs in.
+fn declared_names(stmt: &Stmt) -> Vec<String> {
+    match stmt {
+        Stmt::Expr(Expr::Init(name, _)) | Stmt::Export(Expr::Init(name, _)) => vec![name.clone()],
+        Stmt::For(init, ..) => match &init.node {
+            Expr::Init(name, _) => vec![name.clone()],
+            _ => vec![],
+        },
+        Stmt::Import(Import { module: Some(module), namespace: Some(namespace), .. }) =>
+            module.exports.iter().map(|name| format!("{}.{}", namespace, name)).collect(),
+        _ => vec![],
+    }
+}
+
+/// Whether the statements following the statement in the same block are never run.
+fn is_exit(stmt: &Stmt) -> bool {
+    matches!(stmt, Stmt::Return(_) | Stmt::Break | Stmt::Continue)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::parsing::parser::parse_script;
+
+    use super::*;
+
+    fn check(source: &str) -> Vec<String> {
+        let file = ScriptFile { path: "test.m2".into(), source: source.to_string() };
+        let block = parse_script(&file, 0).unwrap();
+        check_script(&block).into_iter()
+            .map(|diagnostic| format!("{}: {}", diagnostic.span, diagnostic.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_undefined_variables() {
+        assert_eq!(check(indoc! {"
+            let a = 1;
+            print(a + b);
+            let add = |x|{ return x + a + later; };
+            let later = 2;
+            if (true) { let inner = 1; }
+            inner = 2;
+            for (let i = 0; i < 2; i++) { print(i); }
+            print(i);
+            for key in [1] { print(key); }
+            missing(1);
+            add(1);
+        "}), vec![
+            "line 2, column 11: variable 'b' does not exist",
+            "line 6, column 1: variable 'inner' does not exist",
+            "line 10, column 1: function 'missing' not found in this scope",
+        ]);
+
+        // used before the declaration
+        assert_eq!(check("print(a);\nlet a = 1;\n"), vec!["line 1, column 7: variable 'a' does not exist"]);
+    }
+
+    #[test]
+    fn test_builtin_calls() {
+        assert_eq!(check(indoc! {"
+            print(1, 2);
+            mouse_scroll();
+            execute(\"date\", \"-u\");
+            send(\"hello{enter}\");
+            send(\"{shift down}{bogus}\");
+            let seq = \"{bogus}\";
+            send(seq);
+            let push = |list, a, b|{ print(a + b); };
+            push([], 1, 2);
+        "}), vec![
+            "line 1, column 1: function 'print' takes 1 argument, got 2",
+            "line 2, column 1: function 'mouse_scroll' takes 1 to 2 arguments, got 0",
+            "line 5, column 6: unknown key '{bogus}', it would be typed as text",
+        ]);
+    }
+
+    #[test]
+    fn test_mappings() {
+        assert_eq!(check(indoc! {"
+            a::b;
+            {a down}::c;
+            b::c;
+            window(\"class=firefox\", ||{ a::d; });
+            a::{ print(later); };
+            let later = 1;
+        "}), vec![
+            "line 2, column 1: the mapping for '{a down}' replaces the mapping in line 1",
+            "line 5, column 1: the mapping for '{a down}' replaces the mapping in line 2",
+        ]);
+    }
+
+    #[test]
+    fn test_unreachable_statements() {
+        assert_eq!(check(indoc! {"
+            let f = ||{
+              return 1;
+              print(2);
+              print(3);
+            };
+            loop { break; print(1); }
+        "}), vec![
+            "line 3, column 3: unreachable statement",
+            "line 6, column 15: unreachable statement",
+        ]);
+    }
+
+    #[test]
+    fn test_examples() {
+        for entry in fs::read_dir("examples").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("m2") { continue; }
+
+            let (block, files) = script::load_script(&path).unwrap();
+            let diagnostics: Vec<String> = check_script(&block).iter().map(|diagnostic| diagnostic.render(&files)).collect();
+            assert!(diagnostics.is_empty(), "{}", diagnostics.join("\n"));
+        }
+    }
+}
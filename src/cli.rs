@@ -4,16 +4,29 @@ use std::io::{BufRead, BufReader};
 use std::path::PathBuf;
 
 use anyhow::{anyhow, Result};
-use clap::{App, Arg};
+use clap::{App, AppSettings, Arg, SubCommand};
 use xdg::BaseDirectories;
 
+use crate::runtime::runtime_error::ErrorPolicy;
+
 pub struct Configuration {
-    pub script_file: fs::File,
+    pub script_path: PathBuf,
     pub verbosity: i32,
     pub devices: Vec<String>,
+    pub error_policy: ErrorPolicy,
+}
+
+/// What map2 was asked to do.
+pub enum Command {
+    /// runs the script
+    Run(Configuration),
+    /// checks the scripts for mistakes without running them
+    Check(Vec<PathBuf>),
+    /// formats the scripts, or only reports the unformatted ones if `check` is set
+    Fmt { paths: Vec<PathBuf>, check: bool },
 }
 
-pub fn parse_cli() -> Result<Configuration> {
+pub fn parse_cli() -> Result<Command> {
     let matches = App::new("map2")
         .version("1.0")
         .author("shiro <shiro@usagi.io>")
@@ -29,20 +42,50 @@ pub fn parse_cli() -> Result<Configuration> {
             .long("--devices")
             .takes_value(true)
         )
+        .arg(Arg::with_name("on error")
+            .help("Sets what happens after a runtime error, 'continue' (default) or 'abort'")
+            .long("--on-error")
+            .takes_value(true)
+            .possible_values(&["continue", "abort"])
+        )
         .arg(Arg::with_name("script file")
             .help("Executes the given script file")
             .index(1)
             .required(true))
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(SubCommand::with_name("check")
+            .about("Checks scripts for mistakes without running them")
+            .arg(Arg::with_name("script files")
+                .help("The scripts to check")
+                .multiple(true)
+                .required(true)))
+        .subcommand(SubCommand::with_name("fmt")
+            .about("Formats scripts")
+            .arg(Arg::with_name("check")
+                .help("Reports the scripts that aren't formatted instead of formatting them")
+                .long("--check"))
+            .arg(Arg::with_name("script files")
+                .help("The scripts to format")
+                .multiple(true)
+                .required(true)))
         .get_matches();
 
+    if let Some(matches) = matches.subcommand_matches("check") {
+        return Ok(Command::Check(matches.values_of("script files").unwrap().map(PathBuf::from).collect()));
+    }
+    if let Some(matches) = matches.subcommand_matches("fmt") {
+        return Ok(Command::Fmt {
+            paths: matches.values_of("script files").unwrap().map(PathBuf::from).collect(),
+            check: matches.is_present("check"),
+        });
+    }
+
     let device_list_config_name = "devices.list";
 
     let xdg_dirs = BaseDirectories::with_prefix("map2")
         .map_err(|_| anyhow!("failed to initialize XDG directory configuration"))?;
 
-    let script_path = matches.value_of("script file").unwrap().to_string();
-    let script_file = fs::File::open(&script_path)
-        .map_err(|err| anyhow!("failed to read script file '{}': {}", &script_path, &err))?;
+    let script_path = PathBuf::from(matches.value_of("script file").unwrap());
 
 
     let device_list_path = matches.value_of("devices")
@@ -68,12 +111,14 @@ pub fn parse_cli() -> Result<Configuration> {
     };
 
     let verbosity = matches.occurrences_of("verbosity") as i32;
+    let error_policy = matches.value_of("on error").map(str::parse).transpose()?.unwrap_or_default();
 
     let config = Configuration {
-        script_file,
+        script_path,
         verbosity,
         devices: device_list,
+        error_policy,
     };
 
-    Ok(config)
+    Ok(Command::Run(config))
 }
\ No newline at end of file
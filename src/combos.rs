@@ -0,0 +1,134 @@
+use std::collections::HashSet;
+
+use crate::*;
+
+pub const DEFAULT_COMBO_WINDOW: time::Duration = time::Duration::from_millis(50);
+
+/// Keys that trigger a block when pressed at the same time.
+#[derive(Debug, Clone)]
+pub struct ComboMapping {
+    pub keys: HashSet<Key>,
+    pub target: Arc<(Block, GuardedVarMap)>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ComboStep {
+    /// pass the event on to the regular mapping stage
+    Process(InputEvent),
+    /// run the block of a combo
+    Trigger(Arc<(Block, GuardedVarMap)>),
+    /// notify the combo stage after the duration passed
+    StartTimer(usize, time::Duration),
+}
+
+#[derive(Debug)]
+struct PendingCombo {
+    pressed: HashSet<Key>,
+    timer_id: usize,
+    buffer: Vec<InputEvent>,
+}
+
+/// Holds back key presses that might be part of a combo until the combo resolves.
+#[derive(Debug)]
+pub struct ComboState {
+    pub combos: Vec<ComboMapping>,
+    pub window: time::Duration,
+    pending: Option<PendingCombo>,
+    /// keys of triggered combos, their remaining events are swallowed
+    consumed: HashSet<Key>,
+    timer_id: usize,
+}
+
+impl Default for ComboState {
+    fn default() -> Self {
+        ComboState {
+            combos: vec![],
+            window: DEFAULT_COMBO_WINDOW,
+            pending: None,
+            consumed: Default::default(),
+            timer_id: 0,
+        }
+    }
+}
+
+impl ComboState {
+    pub fn new() -> Self { Default::default() }
+
+    /// Drops the held back events of a partially pressed combo and forgets the keys of triggered combos.
+    pub fn reset(&mut self) {
+        self.pending = None;
+        self.consumed.clear();
+    }
+
+    pub fn add_combo(&mut self, combo: ComboMapping) {
+        self.combos.retain(|existing| existing.keys != combo.keys);
+        self.combos.push(combo);
+    }
+
+    /// Feeds a key event into the combo stage, returns `None` if the event is not affected by it.
+    pub fn handle_event(&mut self, ev: &InputEvent) -> Option<Vec<ComboStep>> {
+        let action = KeyAction::from_input_ev(ev);
+
+        if self.consumed.contains(&action.key) {
+            if action.value == TYPE_UP { self.consumed.remove(&action.key); }
+            return Some(vec![]);
+        }
+
+        if let Some(pending) = &mut self.pending {
+            if action.value == TYPE_DOWN && !pending.pressed.contains(&action.key) {
+                let pressed: HashSet<Key> = pending.pressed.iter().cloned().chain(std::iter::once(action.key)).collect();
+
+                if let Some(combo) = self.combos.iter().find(|combo| combo.keys == pressed) {
+                    let target = combo.target.clone();
+                    self.pending = None;
+                    self.consumed.extend(pressed);
+                    return Some(vec![ComboStep::Trigger(target)]);
+                }
+
+                if self.combos.iter().any(|combo| combo.keys.is_superset(&pressed)) {
+                    pending.pressed = pressed;
+                    pending.buffer.push(ev.clone());
+                    return Some(vec![]);
+                }
+            } else if action.value == TYPE_REPEAT && pending.pressed.contains(&action.key) {
+                pending.buffer.push(ev.clone());
+                return Some(vec![]);
+            }
+
+            // the event can't be part of the pending combo, release the held back keys
+            let mut steps = self.flush();
+            match self.handle_event(ev) {
+                Some(next) => steps.extend(next),
+                None => steps.push(ComboStep::Process(ev.clone())),
+            }
+            return Some(steps);
+        }
+
+        if action.value == TYPE_DOWN && self.combos.iter().any(|combo| combo.keys.contains(&action.key)) {
+            self.timer_id += 1;
+            self.pending = Some(PendingCombo {
+                pressed: std::iter::once(action.key).collect(),
+                timer_id: self.timer_id,
+                buffer: vec![ev.clone()],
+            });
+            return Some(vec![ComboStep::StartTimer(self.timer_id, self.window)]);
+        }
+
+        None
+    }
+
+    /// Called once the combo window of a pending combo closed, the held back keys are released unchanged.
+    pub fn handle_timeout(&mut self, timer_id: usize) -> Vec<ComboStep> {
+        match &self.pending {
+            Some(pending) if pending.timer_id == timer_id => self.flush(),
+            _ => vec![],
+        }
+    }
+
+    fn flush(&mut self) -> Vec<ComboStep> {
+        match self.pending.take() {
+            Some(pending) => pending.buffer.into_iter().map(ComboStep::Process).collect(),
+            None => vec![],
+        }
+    }
+}
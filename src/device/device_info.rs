@@ -0,0 +1,103 @@
+use std::path::PathBuf;
+
+use evdev_rs::{Device, DeviceWrapper};
+use regex::Regex;
+
+use crate::*;
+
+/// Identifies the input device an event originated from.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InputDeviceInfo {
+    pub path: PathBuf,
+    pub name: String,
+    pub vendor: u16,
+    pub product: u16,
+}
+
+impl InputDeviceInfo {
+    pub fn from_device(path: PathBuf, device: &Device) -> Self {
+        InputDeviceInfo {
+            path,
+            name: device.name().unwrap_or("").to_string(),
+            vendor: device.vendor_id(),
+            product: device.product_id(),
+        }
+    }
+}
+
+/// Selects input devices by their path, name or vendor and product id.
+#[derive(Debug, Clone)]
+pub enum DeviceSelector {
+    Path(Regex),
+    Name(Regex),
+    Id { vendor: u16, product: Option<u16> },
+}
+
+impl DeviceSelector {
+    /// Parses a selector in the form of `path:<regex>`, `name:<regex>` or `id:<vendor>[:<product>]` with
+    /// hexadecimal ids, selectors without a prefix match the device name.
+    pub fn parse(raw: &str) -> Result<Self> {
+        let parse_regex = |pattern: &str| Regex::new(pattern)
+            .map_err(|err| anyhow!("invalid device selector '{}': {}", raw, err));
+        let parse_id = |id: &str| u16::from_str_radix(id, 16)
+            .map_err(|_| anyhow!("invalid device selector '{}': '{}' is not a hexadecimal id", raw, id));
+
+        if let Some(pattern) = raw.strip_prefix("path:") { return Ok(DeviceSelector::Path(parse_regex(pattern)?)); }
+        if let Some(pattern) = raw.strip_prefix("name:") { return Ok(DeviceSelector::Name(parse_regex(pattern)?)); }
+        if let Some(id) = raw.strip_prefix("id:") {
+            let mut parts = id.splitn(2, ':');
+            let vendor = parse_id(parts.next().unwrap())?;
+            let product = parts.next().map(parse_id).transpose()?;
+            return Ok(DeviceSelector::Id { vendor, product });
+        }
+        Ok(DeviceSelector::Name(parse_regex(raw)?))
+    }
+
+    pub fn matches(&self, device: &InputDeviceInfo) -> bool {
+        match self {
+            DeviceSelector::Path(regex) => regex.is_match(&device.path.to_string_lossy()),
+            DeviceSelector::Name(regex) => regex.is_match(&device.name),
+            DeviceSelector::Id { vendor, product } =>
+                *vendor == device.vendor && product.map_or(true, |product| product == device.product),
+        }
+    }
+}
+
+impl PartialEq for DeviceSelector {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (DeviceSelector::Path(a), DeviceSelector::Path(b)) => a.as_str() == b.as_str(),
+            (DeviceSelector::Name(a), DeviceSelector::Name(b)) => a.as_str() == b.as_str(),
+            (DeviceSelector::Id { vendor: a_vendor, product: a_product }, DeviceSelector::Id { vendor: b_vendor, product: b_product }) =>
+                a_vendor == b_vendor && a_product == b_product,
+            _ => false,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn macro_pad() -> InputDeviceInfo {
+        InputDeviceInfo {
+            path: PathBuf::from("/dev/input/by-id/usb-Macro_Pad-event-kbd"),
+            name: "Macro Pad Keyboard".to_string(),
+            vendor: 0x1209,
+            product: 0xbeef,
+        }
+    }
+
+    #[test]
+    fn test_device_selector() {
+        assert!(DeviceSelector::parse("path:Macro_Pad").unwrap().matches(&macro_pad()));
+        assert!(DeviceSelector::parse("name:^Macro Pad").unwrap().matches(&macro_pad()));
+        assert!(DeviceSelector::parse("Macro").unwrap().matches(&macro_pad()));
+        assert!(DeviceSelector::parse("id:1209").unwrap().matches(&macro_pad()));
+        assert!(DeviceSelector::parse("id:1209:BEEF").unwrap().matches(&macro_pad()));
+        assert!(!DeviceSelector::parse("id:1209:0001").unwrap().matches(&macro_pad()));
+        assert!(!DeviceSelector::parse("name:Laptop").unwrap().matches(&macro_pad()));
+        assert!(DeviceSelector::parse("id:xyz").is_err());
+    }
+}
@@ -1,4 +1,5 @@
 pub mod virtual_input_device;
 mod virt_device;
 pub mod device_logging;
+pub mod device_info;
 mod virtual_output_device;
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use anyhow::Result;
 use evdev_rs::*;
 use evdev_rs::Device;
@@ -17,7 +19,17 @@ fn set_code_bits(dev: &Device, ev_code: &EventCode, max: &EventCode) -> Result<(
     Ok(())
 }
 
-fn set_bits(dev: &Device) -> Result<()> {
+fn set_abs_bits(dev: &Device, abs_info: &HashMap<EV_ABS, AbsInfo>) -> Result<()> {
+    for (axis, info) in abs_info {
+        let code = EventCode::EV_ABS(*axis);
+        dev.enable_event_code(&code, Some(info))
+            .map_err(|err| anyhow!("failed to enable code bit: {}", err))?;
+        dev.set_abs_info(&code, info);
+    }
+    Ok(())
+}
+
+fn set_bits(dev: &Device, abs_info: &HashMap<EV_ABS, AbsInfo>) -> Result<()> {
     for ev_type in EventType::EV_SYN.iter() {
         match ev_type {
             EventType::EV_KEY => set_code_bits(
@@ -30,11 +42,7 @@ fn set_bits(dev: &Device) -> Result<()> {
                 &EventCode::EV_REL(EV_REL::REL_X),
                 &EventCode::EV_REL(EV_REL::REL_MAX),
             )?,
-            // EventType::EV_ABS => clone_code_bits(
-            //     dev,
-            //     &EventCode::EV_ABS(EV_ABS::ABS_X),
-            //     &EventCode::EV_ABS(EV_ABS::ABS_MAX),
-            // )?,
+            EventType::EV_ABS => set_abs_bits(dev, abs_info)?,
             // EventType::EV_LED => {}
                 // clone_code_bits(
                 // dev,
@@ -47,9 +55,11 @@ fn set_bits(dev: &Device) -> Result<()> {
     Ok(())
 }
 
-pub(crate) fn init_virtual_device(dev: &Device) -> Result<()> {
+/// Initializes the virtual device, absolute axes are only enabled if their axis info is given since the kernel
+/// requires a range for them.
+pub(crate) fn init_virtual_device(dev: &Device, abs_info: &HashMap<EV_ABS, AbsInfo>) -> Result<()> {
     dev.set_name("Virtual Device");
-    set_bits(dev)?;
+    set_bits(dev, abs_info)?;
 
     Ok(())
 }
@@ -2,9 +2,11 @@ use std::{fs, io, thread, time};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
 use evdev_rs::*;
+use evdev_rs::enums::{EV_ABS, EventCode, EventType};
 use notify::{DebouncedEvent, Watcher};
 use regex::Regex;
 use tokio::sync::{mpsc, oneshot};
@@ -12,6 +14,8 @@ use tokio::task;
 use walkdir::WalkDir;
 
 use super::*;
+use super::device_info::InputDeviceInfo;
+use crate::state::HeldKeys;
 
 fn get_fd_list(patterns: &Vec<Regex>) -> Vec<PathBuf> {
     let mut list = vec![];
@@ -28,6 +32,25 @@ fn get_fd_list(patterns: &Vec<Regex>) -> Vec<PathBuf> {
     list
 }
 
+/// Collects the absolute axes of the given devices, the first device that reports an axis defines its range.
+fn get_abs_info(fd_paths: &[PathBuf]) -> HashMap<EV_ABS, AbsInfo> {
+    let mut abs_info = HashMap::new();
+    for fd_path in fd_paths {
+        let device = match fs::File::open(&fd_path).and_then(Device::new_from_file) {
+            Ok(device) => device,
+            Err(_) => continue,
+        };
+        if !device.has(&EventType::EV_ABS) { continue; }
+
+        for code in EventCode::EV_ABS(EV_ABS::ABS_X).iter() {
+            if code == EventCode::EV_ABS(EV_ABS::ABS_MAX) { break; }
+            if let (EventCode::EV_ABS(axis), Some(info)) = (&code, device.abs_info(&code)) {
+                abs_info.entry(*axis).or_insert(info);
+            }
+        }
+    }
+    abs_info
+}
 
 pub fn read_from_device_input_fd_thread_handler(
     device: Device,
@@ -80,7 +103,7 @@ pub fn read_from_device_input_fd_thread_handler(
 
 
 async fn runner_it(fd_path: &Path,
-                   writer: mpsc::Sender<InputEvent>)
+                   writer: mpsc::Sender<(Arc<InputDeviceInfo>, InputEvent)>)
                    -> Result<oneshot::Sender<()>> {
     let fd_file = fs::OpenOptions::new()
         .read(true)
@@ -91,6 +114,7 @@ async fn runner_it(fd_path: &Path,
     let mut device = Device::new_from_file(fd_file_nb).expect(&*format!("failed to open fd '{}'", fd_path.to_str().unwrap_or("...")));
     device.grab(GrabMode::Grab)
         .map_err(|err| anyhow!("failed to grab device '{}': {}", fd_path.to_string_lossy(), err))?;
+    let device_info = Arc::new(InputDeviceInfo::from_device(fd_path.to_path_buf(), &device));
 
     // spawn tasks for reading devices
     let (abort_tx, abort_rx) = oneshot::channel();
@@ -99,7 +123,7 @@ async fn runner_it(fd_path: &Path,
             device,
             |ev| {
                 let _ = futures::executor::block_on(
-                    writer.send(ev)
+                    writer.send((device_info.clone(), ev))
                 );
             },
             abort_rx,
@@ -112,7 +136,8 @@ async fn runner_it(fd_path: &Path,
 async fn runner
 (device_fd_path_pattens: Vec<Regex>,
  reader_init: oneshot::Sender<mpsc::Sender<InputEvent>>,
- writer: mpsc::Sender<InputEvent>,
+ writer: mpsc::Sender<(Arc<InputDeviceInfo>, InputEvent)>,
+ held_keys: HeldKeys,
 ) -> Result<()> {
     task::spawn(async move {
         let (fs_reader_tx, reader_rx) = mpsc::channel(128);
@@ -120,7 +145,9 @@ async fn runner
         // send the reader to the client
         reader_init.send(fs_reader_tx.clone()).unwrap();
 
-        virtual_output_device::init_virtual_output_device(reader_rx).await
+        // absolute axes can't be added to the output device later on, only devices present at startup are considered
+        let abs_info = get_abs_info(&get_fd_list(&device_fd_path_pattens));
+        virtual_output_device::init_virtual_output_device(reader_rx, &abs_info, held_keys).await
             .map_err(|err| anyhow!("uinput error: {}", err))
             .unwrap();
 
@@ -197,14 +224,16 @@ async fn runner
 }
 
 
-pub async fn bind_udev_inputs(fd_patterns: &[impl AsRef<str>], reader_init_tx: oneshot::Sender<mpsc::Sender<InputEvent>>, writer_tx: mpsc::Sender<InputEvent>) -> Result<()> {
+/// Grabs the matching devices and creates the output device, keys that are held down on it are recorded in `held_keys`.
+pub async fn bind_udev_inputs(fd_patterns: &[impl AsRef<str>], reader_init_tx: oneshot::Sender<mpsc::Sender<InputEvent>>,
+                              writer_tx: mpsc::Sender<(Arc<InputDeviceInfo>, InputEvent)>, held_keys: HeldKeys) -> Result<()> {
     let fd_patterns_regex = fd_patterns.into_iter()
         .map(|v| Regex::new(v.as_ref()))
         .collect::<std::result::Result<_, _>>()
         .map_err(|err| anyhow!("failed to parse regex: {}", err))?;
 
     task::spawn(async move {
-        runner(fd_patterns_regex, reader_init_tx, writer_tx).await.unwrap();
+        runner(fd_patterns_regex, reader_init_tx, writer_tx, held_keys).await.unwrap();
         Ok::<(), anyhow::Error>(())
     });
 
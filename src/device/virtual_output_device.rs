@@ -1,15 +1,21 @@
-use evdev_rs::{UInputDevice, UninitDevice};
+use std::collections::HashMap;
+
+use evdev_rs::{AbsInfo, UInputDevice, UninitDevice};
+use evdev_rs::enums::EV_ABS;
+
 use crate::*;
 use super::*;
 
 pub async fn init_virtual_output_device(
     mut reader_rx: mpsc::Receiver<InputEvent>,
+    abs_info: &HashMap<EV_ABS, AbsInfo>,
+    held_keys: HeldKeys,
 ) -> Result<()> {
     let mut new_device = UninitDevice::new()
         .ok_or(anyhow!("failed to instantiate udev device: libevdev didn't return a device"))?
         .unstable_force_init();
 
-    virt_device::init_virtual_device(&mut new_device)
+    virt_device::init_virtual_device(&mut new_device, abs_info)
         .map_err(|err| anyhow!("failed to instantiate udev device: {}", err))?;
 
     let input_device = UInputDevice::create_from_device(&new_device);
@@ -31,6 +37,7 @@ pub async fn init_virtual_output_device(
             };
             input_device.write_event(&ev)
                 .map_err(|err| anyhow!("failed to write event into uinput device: {}", err))?;
+            track_held_key(&held_keys, &ev);
         }
         #[allow(unreachable_code)]
             Ok(())
@@ -1,6 +1,15 @@
+use std::collections::VecDeque;
+use std::mem;
+
+use evdev_rs::enums::{EV_ABS, EV_REL};
+
 use crate::*;
 use messaging::*;
 use crate::cli::Configuration;
+use crate::script::{self, ScriptFile};
+
+/// How long the global scope of a reloaded script may run before the reloaded script replaces the running one anyway.
+pub const RELOAD_TIMEOUT: time::Duration = time::Duration::from_secs(2);
 
 pub(crate) fn update_modifiers(state: &mut State, action: &KeyAction) {
     // let ignore_list = &mut state.ignore_list;
@@ -38,7 +47,8 @@ pub(crate) fn update_modifiers(state: &mut State, action: &KeyAction) {
 }
 
 pub async fn handle_stdin_ev(
-    mut state: &mut State,
+    state: &mut State,
+    device: Arc<InputDeviceInfo>,
     ev: InputEvent,
     mappings: &mut CompiledKeyMappings,
     ev_writer: &mut mpsc::Sender<InputEvent>,
@@ -47,43 +57,265 @@ pub async fn handle_stdin_ev(
     configuration: &Configuration,
 ) -> Result<()> {
     if configuration.verbosity >= 3 {
-        logging::print_debug(format!("input event: {}", logging::print_input_event(&ev)));
+        logging::print_debug(format!("input event ({}): {}", device.name, logging::print_input_event(&ev)));
     }
 
     match ev.event_code {
-        EventCode::EV_KEY(_) => {}
+        EventCode::EV_KEY(_) => {
+            // events might be held back by the following stages, remember where they came from
+            state.key_devices.insert(Key { event_code: ev.event_code }, device);
+        }
+        EventCode::EV_REL(axis) => {
+            return handle_rel_ev(state, &device, ev, axis, mappings, ev_writer, message_tx, window_cycle_token).await;
+        }
+        EventCode::EV_ABS(axis) => {
+            return handle_abs_ev(state, ev, axis, mappings, ev_writer, message_tx, window_cycle_token).await;
+        }
         _ => {
             ev_writer.send(ev).await.unwrap();
             return Ok(());
         }
     }
 
-    let mut from_modifiers = KeyModifierFlags::new();
-    from_modifiers.ctrl = state.modifiers.is_ctrl();
-    from_modifiers.alt = state.modifiers.is_alt();
-    from_modifiers.shift = state.modifiers.is_shift();
-    from_modifiers.meta = state.modifiers.is_meta();
+    handle_tap_hold_steps(state, vec![TapHoldStep::Process(ev)], mappings, ev_writer, message_tx, window_cycle_token).await
+}
 
-    let from_key_action = KeyActionWithMods {
-        key: Key { event_code: ev.event_code },
-        value: ev.value,
-        modifiers: from_modifiers,
-    };
+async fn handle_rel_ev(
+    state: &mut State,
+    device: &InputDeviceInfo,
+    ev: InputEvent,
+    axis: EV_REL,
+    mappings: &mut CompiledKeyMappings,
+    ev_writer: &mut mpsc::Sender<InputEvent>,
+    message_tx: &mut ExecutionMessageSender,
+    window_cycle_token: usize,
+) -> Result<()> {
+    if state.mouse.is_hi_res_replaced(axis) { return Ok(()); }
 
-    if let Some(block) = mappings.0.get(&from_key_action) {
-        let block = block.clone();
+    if let Some((params, block, var_map)) = state.mouse.handlers.get(&axis) {
+        let (params, block, var_map) = (params.clone(), block.clone(), var_map.clone());
         let mut message_tx = message_tx.clone();
         let ev_writer = ev_writer.clone();
         let modifier_state = state.modifiers.clone();
         task::spawn(async move {
-            let (block, var_map) = block.deref();
-            let mut amb = Ambient { ev_writer_tx: ev_writer, message_tx: Some(&mut message_tx), window_cycle_token, modifier_state: &modifier_state };
+            let mut amb = Ambient { ev_writer_tx: ev_writer, message_tx: Some(&mut message_tx), window_cycle_token, modifier_state: &modifier_state, layer: None, condition: Default::default() };
+            let ret = call_lambda(&params, &block, &var_map, vec![ValueType::Number(ev.value as f64)], &mut amb).await;
+            report_runtime_error(&message_tx, ret).await;
+        });
+        return Ok(());
+    }
+
+    if state.mouse.mapped_wheels.contains(&axis) {
+        let key = rel_key(axis);
+        let from_key_actions: Vec<KeyActionWithMods> = state.modifiers.to_flag_candidates().into_iter()
+            .map(|modifiers| KeyActionWithMods { key, value: ev.value.signum(), modifiers })
+            .collect();
+
+        if let Some(block) = mappings.get(&state.layers.lookup_order(&key), &from_key_actions, Some(device), state.active_window.as_ref()).cloned() {
+            // the mapping runs once per wheel tick
+            for _ in 0..ev.value.abs() {
+                spawn_mapping_block(block.clone(), state, ev_writer, message_tx, window_cycle_token);
+            }
+            return Ok(());
+        }
+
+        // the high resolution event got dropped, replace it
+        if let Some(hi_res_axis) = wheel_hi_res_axis(axis) {
+            ev_writer.send(InputEvent { event_code: EventCode::EV_REL(hi_res_axis), value: ev.value * WHEEL_HI_RES_TICK, time: ev.time.clone() }).await.unwrap();
+        }
+    }
+
+    ev_writer.send(ev).await.unwrap();
+    Ok(())
+}
 
-            eval_block(&block, &var_map, &mut amb).await;
+async fn handle_abs_ev(
+    state: &mut State,
+    mut ev: InputEvent,
+    axis: EV_ABS,
+    mappings: &mut CompiledKeyMappings,
+    ev_writer: &mut mpsc::Sender<InputEvent>,
+    message_tx: &mut ExecutionMessageSender,
+    window_cycle_token: usize,
+) -> Result<()> {
+    ev.value = match state.gamepad.filter_value(axis, ev.value) {
+        Some(value) => value,
+        None => return Ok(()),
+    };
+
+    if let Some((params, block, var_map)) = state.gamepad.handlers.get(&axis) {
+        let (params, block, var_map) = (params.clone(), block.clone(), var_map.clone());
+        let mut message_tx = message_tx.clone();
+        let ev_writer = ev_writer.clone();
+        let modifier_state = state.modifiers.clone();
+        task::spawn(async move {
+            let mut amb = Ambient { ev_writer_tx: ev_writer, message_tx: Some(&mut message_tx), window_cycle_token, modifier_state: &modifier_state, layer: None, condition: Default::default() };
+            let ret = call_lambda(&params, &block, &var_map, vec![ValueType::Number(ev.value as f64)], &mut amb).await;
+            report_runtime_error(&message_tx, ret).await;
         });
         return Ok(());
     }
 
+    if let Some(actions) = state.gamepad.threshold_actions(axis, ev.value) {
+        // the key events go through the regular mapping stages, the sync event of the device follows
+        let steps = actions.into_iter()
+            .map(|action| TapHoldStep::Process(action.to_input_ev()))
+            .collect();
+        return handle_tap_hold_steps(state, steps, mappings, ev_writer, message_tx, window_cycle_token).await;
+    }
+
+    ev_writer.send(ev).await.unwrap();
+    Ok(())
+}
+
+async fn handle_tap_hold_steps(
+    state: &mut State,
+    steps: Vec<TapHoldStep>,
+    mappings: &mut CompiledKeyMappings,
+    ev_writer: &mut mpsc::Sender<InputEvent>,
+    message_tx: &mut ExecutionMessageSender,
+    window_cycle_token: usize,
+) -> Result<()> {
+    let mut queue = VecDeque::from(steps);
+
+    while let Some(step) = queue.pop_front() {
+        match step {
+            TapHoldStep::Emit(action) => {
+                update_modifiers(state, &action);
+                ev_writer.send(action.to_input_ev()).await.unwrap();
+                ev_writer.send(SYN_REPORT.clone()).await.unwrap();
+            }
+            TapHoldStep::Process(ev) => {
+                // events released by the tap-hold stage might start another dual-role key
+                match state.tap_hold.handle_event(&ev) {
+                    Some(steps) => for step in steps.into_iter().rev() { queue.push_front(step); },
+                    None => handle_key_ev(state, ev, mappings, ev_writer, message_tx, window_cycle_token).await?,
+                }
+            }
+            TapHoldStep::StartTimer(timer_id, duration) => {
+                start_timer(message_tx, duration, ExecutionMessage::TapHoldTimeout(timer_id));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_key_ev(
+    state: &mut State,
+    ev: InputEvent,
+    mappings: &mut CompiledKeyMappings,
+    ev_writer: &mut mpsc::Sender<InputEvent>,
+    message_tx: &mut ExecutionMessageSender,
+    window_cycle_token: usize,
+) -> Result<()> {
+    match state.combos.handle_event(&ev) {
+        Some(steps) => handle_combo_steps(state, steps, mappings, ev_writer, message_tx, window_cycle_token).await,
+        None => handle_sequence_ev(state, ev, mappings, ev_writer, message_tx, window_cycle_token).await,
+    }
+}
+
+async fn handle_combo_steps(
+    state: &mut State,
+    steps: Vec<ComboStep>,
+    mappings: &mut CompiledKeyMappings,
+    ev_writer: &mut mpsc::Sender<InputEvent>,
+    message_tx: &mut ExecutionMessageSender,
+    window_cycle_token: usize,
+) -> Result<()> {
+    for step in steps {
+        match step {
+            ComboStep::Process(ev) => handle_sequence_ev(state, ev, mappings, ev_writer, message_tx, window_cycle_token).await?,
+            ComboStep::Trigger(block) => spawn_mapping_block(block, state, ev_writer, message_tx, window_cycle_token),
+            ComboStep::StartTimer(timer_id, duration) => {
+                start_timer(message_tx, duration, ExecutionMessage::ComboTimeout(timer_id));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_sequence_ev(
+    state: &mut State,
+    ev: InputEvent,
+    mappings: &mut CompiledKeyMappings,
+    ev_writer: &mut mpsc::Sender<InputEvent>,
+    message_tx: &mut ExecutionMessageSender,
+    window_cycle_token: usize,
+) -> Result<()> {
+    let modifiers = current_modifiers(state);
+    match state.sequences.handle_event(&ev, modifiers, &mappings.sequences) {
+        Some(steps) => handle_sequence_steps(state, steps, mappings, ev_writer, message_tx, window_cycle_token).await,
+        None => handle_mapping_ev(state, ev, mappings, ev_writer, message_tx, window_cycle_token).await,
+    }
+}
+
+async fn handle_sequence_steps(
+    state: &mut State,
+    steps: Vec<SequenceStep>,
+    mappings: &mut CompiledKeyMappings,
+    ev_writer: &mut mpsc::Sender<InputEvent>,
+    message_tx: &mut ExecutionMessageSender,
+    window_cycle_token: usize,
+) -> Result<()> {
+    for step in steps {
+        match step {
+            SequenceStep::Process(ev) => handle_mapping_ev(state, ev, mappings, ev_writer, message_tx, window_cycle_token).await?,
+            SequenceStep::Trigger(block) => spawn_mapping_block(block, state, ev_writer, message_tx, window_cycle_token),
+            SequenceStep::StartTimer(timer_id, duration) => {
+                start_timer(message_tx, duration, ExecutionMessage::SequenceTimeout(timer_id));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_mapping_ev(
+    mut state: &mut State,
+    ev: InputEvent,
+    mappings: &mut CompiledKeyMappings,
+    ev_writer: &mut mpsc::Sender<InputEvent>,
+    message_tx: &mut ExecutionMessageSender,
+    window_cycle_token: usize,
+) -> Result<()> {
+    let action = KeyAction::from_input_ev(&ev);
+    if state.layers.handle_key_action(&action) { return Ok(()); }
+
+    // mappings for a specific modifier side take precedence
+    let from_key_actions: Vec<KeyActionWithMods> = state.modifiers.to_flag_candidates().into_iter()
+        .map(|modifiers| KeyActionWithMods {
+            key: Key { event_code: ev.event_code },
+            value: ev.value,
+            modifiers,
+        })
+        .collect();
+
+    let device = state.key_devices.get(&action.key).map(Deref::deref);
+    let mapping = if action.value == TYPE_DOWN {
+        let (mapping, pressed) = mappings.get_pressed(&state.layers.lookup_order(&action.key), &from_key_actions, device, state.active_window.as_ref());
+        state.pressed_mappings.insert(action.key, pressed);
+        mapping
+    } else {
+        // the mapping that handled the key press handles its other events
+        let pressed = if action.value == TYPE_UP {
+            state.pressed_mappings.remove(&action.key)
+        } else {
+            state.pressed_mappings.get(&action.key).cloned()
+        };
+        match pressed {
+            Some(pressed) => if action.value == TYPE_UP { pressed.up } else { pressed.repeat },
+            None => mappings.get(&state.layers.lookup_order(&action.key), &from_key_actions, device, state.active_window.as_ref()).cloned(),
+        }
+    };
+    state.layers.release_latched(&action);
+
+    if let Some(block) = mapping {
+        spawn_mapping_block(block, state, ev_writer, message_tx, window_cycle_token);
+        return Ok(());
+    }
+
     update_modifiers(&mut state, &KeyAction::from_input_ev(&ev));
 
     ev_writer.send(ev).await.unwrap();
@@ -91,29 +323,300 @@ pub async fn handle_stdin_ev(
     Ok(())
 }
 
+fn current_modifiers(state: &State) -> KeyModifierFlags {
+    let mut modifiers = KeyModifierFlags::new();
+    modifiers.ctrl = state.modifiers.is_ctrl();
+    modifiers.alt = state.modifiers.is_alt();
+    modifiers.shift = state.modifiers.is_shift();
+    modifiers.meta = state.modifiers.is_meta();
+    modifiers
+}
+
+fn spawn_mapping_block(
+    block: Arc<(Block, GuardedVarMap)>,
+    state: &State,
+    ev_writer: &mpsc::Sender<InputEvent>,
+    message_tx: &ExecutionMessageSender,
+    window_cycle_token: usize,
+) {
+    let mut message_tx = message_tx.clone();
+    let ev_writer = ev_writer.clone();
+    let modifier_state = state.modifiers.clone();
+    task::spawn(async move {
+        let (block, var_map) = block.deref();
+        let mut amb = Ambient { ev_writer_tx: ev_writer, message_tx: Some(&mut message_tx), window_cycle_token, modifier_state: &modifier_state, layer: None, condition: Default::default() };
+
+        let ret = eval_block(&block, &var_map, &mut amb).await;
+        report_runtime_error(&message_tx, ret).await;
+    });
+}
+
+/// Sends the message back to the main loop once the duration passed.
+fn start_timer(message_tx: &ExecutionMessageSender, duration: time::Duration, message: ExecutionMessage) {
+    let message_tx = message_tx.clone();
+    task::spawn(async move {
+        tokio::time::sleep(duration).await;
+        let _ = message_tx.send(message).await;
+    });
+}
+
 
+/// Handles a message of the running script, messages of a script that is being reloaded change the tables of the reloaded
+/// script instead.
 pub async fn handle_execution_message(
+    out: &mut impl Write,
+    window_cycle_token: &mut usize,
+    msg: ExecutionMessage,
+    state: &mut State,
+    mappings: &mut CompiledKeyMappings,
+    window_change_handlers: &mut WindowChangeHandlers,
+    ev_writer: &mut mpsc::Sender<InputEvent>,
+    message_tx: &mut ExecutionMessageSender,
+) {
+    let mut reload = match state.pending_reload.take() {
+        Some(reload) => reload,
+        None => return apply_execution_message(out, *window_cycle_token, msg, state, mappings, window_change_handlers, ev_writer, message_tx).await,
+    };
+
+    match msg {
+        // the global scope of a script that replaced the running one on timeout finished after all
+        ExecutionMessage::ScriptEvaluated(token) if token == reload.token && reload.replaced => {}
+        ExecutionMessage::ScriptEvaluated(token) | ExecutionMessage::ReloadTimeout(token) if token == reload.token => {
+            let timed_out = matches!(msg, ExecutionMessage::ReloadTimeout(_));
+            if timed_out {
+                eprintln!("the global scope of the reloaded script didn't finish within {} seconds, replacing the running \
+                           script anyway", RELOAD_TIMEOUT.as_secs());
+            }
+
+            release_held_keys(state, ev_writer).await;
+            reload.swap(state, mappings, window_change_handlers);
+
+            // events that were held back or handled by the previous script are not resolved by the new one
+            state.tap_hold.reset();
+            state.combos.reset();
+            state.sequences.reset();
+            state.pressed_mappings.clear();
+
+            if let Some(task) = mem::replace(&mut state.script_task, reload.task.take()) {
+                task.abort();
+            }
+
+            // messages sent by the previous script from now on are outdated
+            *window_cycle_token += 1;
+
+            // the window specific mappings of the new script
+            if state.active_window.is_some() {
+                handle_active_window_change(ev_writer, message_tx, *window_cycle_token, &mut window_change_handlers.focus);
+                handle_active_window_change(ev_writer, message_tx, *window_cycle_token, &mut window_change_handlers.title);
+            }
+
+            // the global scope keeps running, what it registers from now on is added to the running script
+            if timed_out {
+                reload.replaced = true;
+                state.pending_reload = Some(reload);
+            }
+        }
+        msg if msg.token() == Some(reload.token) => {
+            if reload.replaced {
+                apply_execution_message(out, reload.token, msg, state, mappings, window_change_handlers, ev_writer, message_tx).await;
+            } else {
+                reload.swap(state, mappings, window_change_handlers);
+                apply_execution_message(out, reload.token, msg, state, mappings, window_change_handlers, ev_writer, message_tx).await;
+                reload.swap(state, mappings, window_change_handlers);
+            }
+            state.pending_reload = Some(reload);
+        }
+        msg => {
+            apply_execution_message(out, *window_cycle_token, msg, state, mappings, window_change_handlers, ev_writer, message_tx).await;
+            state.pending_reload = Some(reload);
+        }
+    }
+}
+
+/// Releases the keys that are held down on the output device.
+async fn release_held_keys(state: &mut State, ev_writer: &mut mpsc::Sender<InputEvent>) {
+    let held_keys: Vec<Key> = state.held_keys.lock().unwrap().iter().cloned().collect();
+    for key in held_keys {
+        let action = KeyAction::new(key, TYPE_UP);
+        update_modifiers(state, &action);
+        ev_writer.send(action.to_input_ev()).await.unwrap();
+        ev_writer.send(SYN_REPORT.clone()).await.unwrap();
+    }
+}
+
+/// Replaces the running script with a newly loaded version, the grabbed devices are kept.
+///
+/// The global scope of the new script is evaluated into tables of its own, the mappings of the previous script stay
+/// active until the evaluation finished. If it takes longer than [`RELOAD_TIMEOUT`], the new script replaces the running
+/// one anyway and whatever its global scope registers afterwards is added to it. Keys that are held down on the output
+/// device get released once the new script takes over, the global scope of the previous script is stopped.
+pub async fn reload_script(
+    state: &mut State,
+    script_ast: Block,
+    script_files: Vec<ScriptFile>,
+    window_cycle_token: &mut usize,
+    ev_writer: &mut mpsc::Sender<InputEvent>,
+    message_tx: &mut ExecutionMessageSender,
+) {
+    // a reload that didn't finish yet is superseded
+    if let Some(PendingReload { task: Some(task), .. }) = state.pending_reload.take() {
+        task.abort();
+    }
+
+    // the new script gets a token that the running script never uses, even if the active window changes meanwhile
+    let token = *window_cycle_token + 1;
+    *window_cycle_token += 2;
+    state.script_files = script_files;
+    start_timer(message_tx, RELOAD_TIMEOUT, ExecutionMessage::ReloadTimeout(token));
+
+    let mut reload = PendingReload::new(token);
+    let message_tx = message_tx.clone();
+    let ev_writer = ev_writer.clone();
+    reload.task = Some(task::spawn(async move {
+        script::evaluate_script(script_ast, message_tx, ev_writer, token).await;
+    }));
+    state.pending_reload = Some(reload);
+}
+
+async fn apply_execution_message(
     out: &mut impl Write,
     current_token: usize,
     msg: ExecutionMessage,
     state: &mut State,
     mappings: &mut CompiledKeyMappings,
-    window_change_handlers: &mut Vec<(Block, GuardedVarMap)>,
+    window_change_handlers: &mut WindowChangeHandlers,
+    ev_writer: &mut mpsc::Sender<InputEvent>,
+    message_tx: &mut ExecutionMessageSender,
 ) {
     match msg {
         // ExecutionMessage::EatEv(action) => {
         //     state.ignore_list.ignore(&action);
         // }
-        ExecutionMessage::AddMapping(token, from, to, var_map) => {
+        ExecutionMessage::AddMapping(token, layer, condition, from, to, var_map) => {
+            if token == current_token {
+                if let EventCode::EV_REL(axis) = from.key.event_code { state.mouse.mapped_wheels.insert(axis); }
+                mappings.insert(layer, condition, from, Arc::new((to, var_map)));
+            }
+        }
+        ExecutionMessage::RemoveMapping(token, layer, condition, from) => {
+            if token == current_token {
+                // tap-hold keys can't be scoped, they are only removed by unscoped calls
+                if layer.is_none() && condition.is_unconditional() && from.modifiers == KeyModifierFlags::new() {
+                    state.tap_hold.mappings.remove(&from.key);
+                }
+                if mappings.remove(layer, &condition, &from) {
+                    state.mouse.update_mapped_wheels(mappings);
+                }
+            }
+        }
+        ExecutionMessage::ClearMappings(token) => {
+            if token == current_token {
+                mappings.clear();
+                state.tap_hold.mappings.clear();
+                state.combos.combos.clear();
+                state.layers.momentary.clear();
+                state.layers.deactivate_all();
+                state.mouse.handlers.clear();
+                state.gamepad.handlers.clear();
+                state.gamepad.thresholds.clear();
+                state.mouse.update_mapped_wheels(mappings);
+            }
+        }
+        ExecutionMessage::GetMappings(layer, tx) => {
+            tx.send(mappings.triggers(layer.as_ref())).await.unwrap();
+        }
+        ExecutionMessage::AddTapHold(token, mapping) => {
+            if token == current_token {
+                state.tap_hold.mappings.insert(mapping.trigger, mapping);
+            }
+        }
+        ExecutionMessage::TapHoldTimeout(timer_id) => {
+            let steps = state.tap_hold.handle_timeout(timer_id);
+            // the sync events of held back events were already forwarded
+            let needs_sync = steps.iter().any(|step| matches!(step, TapHoldStep::Process(_)));
+            handle_tap_hold_steps(state, steps, mappings, ev_writer, message_tx, current_token).await.unwrap();
+            if needs_sync { ev_writer.send(SYN_REPORT.clone()).await.unwrap(); }
+        }
+        ExecutionMessage::AddCombo(token, combo) => {
+            if token == current_token {
+                state.combos.add_combo(combo);
+            }
+        }
+        ExecutionMessage::ComboTimeout(timer_id) => {
+            let steps = state.combos.handle_timeout(timer_id);
+            let needs_sync = !steps.is_empty();
+            handle_combo_steps(state, steps, mappings, ev_writer, message_tx, current_token).await.unwrap();
+            if needs_sync { ev_writer.send(SYN_REPORT.clone()).await.unwrap(); }
+        }
+        ExecutionMessage::SetComboWindow(token, window) => {
+            if token == current_token {
+                state.combos.window = window;
+            }
+        }
+        ExecutionMessage::AddSequence(token, sequence, block, var_map) => {
+            if token == current_token {
+                mappings.sequences.insert(&sequence, Arc::new((block, var_map)));
+            }
+        }
+        ExecutionMessage::SequenceTimeout(timer_id) => {
+            let steps = state.sequences.handle_timeout(timer_id, &mappings.sequences);
+            let needs_sync = steps.iter().any(|step| matches!(step, SequenceStep::Process(_)));
+            handle_sequence_steps(state, steps, mappings, ev_writer, message_tx, current_token).await.unwrap();
+            if needs_sync { ev_writer.send(SYN_REPORT.clone()).await.unwrap(); }
+        }
+        ExecutionMessage::SetSequenceTimeout(token, timeout) => {
+            if token == current_token {
+                state.sequences.timeout = timeout;
+            }
+        }
+        ExecutionMessage::SetSequenceCancelKeys(token, keys) => {
+            if token == current_token {
+                state.sequences.cancel_keys = keys;
+            }
+        }
+        ExecutionMessage::AddRelHandler(token, axis, params, block, var_map) => {
             if token == current_token {
-                mappings.0.insert(from, Arc::new((to, var_map)));
+                if wheel_hi_res_axis(axis).is_some() { state.mouse.mapped_wheels.insert(axis); }
+                state.mouse.handlers.insert(axis, (params, block, var_map));
+            }
+        }
+        ExecutionMessage::AddAbsHandler(token, axis, params, block, var_map) => {
+            if token == current_token {
+                state.gamepad.handlers.insert(axis, (params, block, var_map));
+            }
+        }
+        ExecutionMessage::AddAbsThreshold(token, axis, threshold) => {
+            if token == current_token {
+                state.gamepad.add_threshold(axis, threshold);
+            }
+        }
+        ExecutionMessage::SetAbsDeadzone(token, axis, deadzone) => {
+            if token == current_token {
+                state.gamepad.deadzones.insert(axis, deadzone);
+            }
+        }
+        ExecutionMessage::AddMomentaryLayer(token, key, layer) => {
+            if token == current_token {
+                state.layers.momentary.insert(key, layer);
+            }
+        }
+        ExecutionMessage::LayerCommand(token, command) => {
+            if token == current_token {
+                state.layers.handle_command(command);
             }
         }
         ExecutionMessage::GetFocusedWindowInfo(tx) => {
             tx.send(state.active_window.clone()).await.unwrap();
         }
-        ExecutionMessage::RegisterWindowChangeCallback(block, var_map) => {
-            window_change_handlers.push((block, var_map));
+        ExecutionMessage::RegisterWindowChangeCallback(token, block, var_map) => {
+            if token == current_token {
+                window_change_handlers.focus.push((block, var_map));
+            }
+        }
+        ExecutionMessage::RegisterWindowTitleChangeCallback(token, block, var_map) => {
+            if token == current_token {
+                window_change_handlers.title.push((block, var_map));
+            }
         }
         ExecutionMessage::Write(message) => {
             out.write(message.as_ref()).unwrap();
@@ -122,14 +625,41 @@ pub async fn handle_execution_message(
             event_handlers::update_modifiers(state, &action);
         }
         ExecutionMessage::Exit(exit_code) => { std::process::exit(exit_code) }
-        ExecutionMessage::FatalError(err, exit_code) => {
-            eprintln!("error: {}", err);
-            std::process::exit(exit_code)
+        ExecutionMessage::RuntimeError(err) => {
+            eprintln!("{}", err.render(&state.script_files));
+            if state.error_policy == ErrorPolicy::Abort { std::process::exit(1) }
         }
+        // only relevant while a script is being reloaded
+        ExecutionMessage::ScriptEvaluated(_) | ExecutionMessage::ReloadTimeout(_) => {}
     }
 }
 
 
+pub fn handle_window_event(state: &mut State, event: WindowEvent, window_cycle_token: &mut usize,
+                           window_change_handlers: &mut WindowChangeHandlers,
+                           ev_writer_tx: &mut mpsc::Sender<InputEvent>, message_tx: &mut ExecutionMessageSender) {
+    let previous_title = state.active_window.as_ref().map(|window| window.name.clone());
+
+    match event {
+        WindowEvent::Focus(window) => {
+            let title_changed = previous_title.as_ref() != Some(&window.name);
+            state.active_window = Some(window);
+            *window_cycle_token = *window_cycle_token + 1;
+
+            handle_active_window_change(ev_writer_tx, message_tx, *window_cycle_token, &mut window_change_handlers.focus);
+            if title_changed {
+                handle_active_window_change(ev_writer_tx, message_tx, *window_cycle_token, &mut window_change_handlers.title);
+            }
+        }
+        WindowEvent::TitleChange(window) => {
+            if previous_title.as_ref() == Some(&window.name) { return; }
+            state.active_window = Some(window);
+
+            handle_active_window_change(ev_writer_tx, message_tx, *window_cycle_token, &mut window_change_handlers.title);
+        }
+    }
+}
+
 pub fn handle_active_window_change(ev_writer_tx: &mut mpsc::Sender<InputEvent>, message_tx: &mut ExecutionMessageSender,
                                    window_cycle_token: usize, window_change_handlers: &mut Vec<(Block, GuardedVarMap)>) {
     for (handler, var_map) in window_change_handlers {
@@ -139,15 +669,18 @@ pub fn handle_active_window_change(ev_writer_tx: &mut mpsc::Sender<InputEvent>,
         let mut var_map = var_map.clone();
 
         task::spawn(async move {
-            eval_block(&handler,
-                       &mut var_map,
-                       &mut Ambient {
-                           ev_writer_tx,
-                           message_tx: Some(&mut message_tx),
-                           window_cycle_token,
-                           modifier_state: &KeyModifierState::new(),
-                       },
+            let ret = eval_block(&handler,
+                                 &mut var_map,
+                                 &mut Ambient {
+                                     ev_writer_tx,
+                                     message_tx: Some(&mut message_tx),
+                                     window_cycle_token,
+                                     modifier_state: &KeyModifierState::new(),
+                                     layer: None,
+                                     condition: Default::default(),
+                                 },
             ).await;
+            report_runtime_error(&message_tx, ret).await;
         });
     }
 }
\ No newline at end of file
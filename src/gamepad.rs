@@ -0,0 +1,130 @@
+use std::str::FromStr;
+
+use evdev_rs::enums::EV_ABS;
+
+use crate::*;
+
+pub fn abs_key(axis: EV_ABS) -> Key { Key { event_code: EventCode::EV_ABS(axis) } }
+
+/// Parses the name of an absolute axis, either a short name such as "x" or "hat0x" or the full event code name.
+pub fn parse_abs_axis(name: &str) -> Result<EV_ABS> {
+    let full_name = match name {
+        "x" | "y" | "z" | "rx" | "ry" | "rz" | "hat0x" | "hat0y" | "gas" | "brake" => format!("ABS_{}", name.to_uppercase()),
+        name => name.to_uppercase(),
+    };
+    EV_ABS::from_str(&full_name).map_err(|_| anyhow!("unknown absolute axis '{}'", name))
+}
+
+/// Values within `radius` of `center` are reported as `center`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AbsDeadzone {
+    pub center: i32,
+    pub radius: i32,
+}
+
+/// Presses a key while the axis value is beyond the threshold, negative thresholds trigger below the value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AbsThreshold {
+    pub threshold: i32,
+    pub key: Key,
+    pressed: bool,
+}
+
+impl AbsThreshold {
+    pub fn new(threshold: i32, key: Key) -> Self {
+        AbsThreshold { threshold, key, pressed: false }
+    }
+
+    fn is_reached(&self, value: i32) -> bool {
+        if self.threshold < 0 { value <= self.threshold } else { value >= self.threshold }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GamepadState {
+    /// callbacks that receive all events of an absolute axis instead of the output device
+    pub handlers: HashMap<EV_ABS, (Vec<String>, Block, GuardedVarMap)>,
+    /// axes that are turned into key events, their events are not forwarded
+    pub thresholds: HashMap<EV_ABS, Vec<AbsThreshold>>,
+    pub deadzones: HashMap<EV_ABS, AbsDeadzone>,
+    /// the last value of each axis after applying the deadzone
+    values: HashMap<EV_ABS, i32>,
+}
+
+impl GamepadState {
+    pub fn new() -> Self { Default::default() }
+
+    pub fn add_threshold(&mut self, axis: EV_ABS, threshold: AbsThreshold) {
+        let thresholds = self.thresholds.entry(axis).or_default();
+        thresholds.retain(|existing| existing.threshold != threshold.threshold);
+        thresholds.push(threshold);
+    }
+
+    /// Applies the deadzone of the axis, returns `None` if the resulting value didn't change.
+    pub fn filter_value(&mut self, axis: EV_ABS, value: i32) -> Option<i32> {
+        let value = match self.deadzones.get(&axis) {
+            Some(deadzone) if (value - deadzone.center).abs() <= deadzone.radius => deadzone.center,
+            _ => value,
+        };
+
+        if self.values.insert(axis, value) == Some(value) { return None; }
+        Some(value)
+    }
+
+    /// Returns the key actions of thresholds that were crossed by the new value, or `None` if the axis has no
+    /// thresholds.
+    pub fn threshold_actions(&mut self, axis: EV_ABS, value: i32) -> Option<Vec<KeyAction>> {
+        let thresholds = self.thresholds.get_mut(&axis)?;
+
+        let mut actions = vec![];
+        for threshold in thresholds.iter_mut() {
+            let reached = threshold.is_reached(value);
+            if reached == threshold.pressed { continue; }
+
+            threshold.pressed = reached;
+            actions.push(KeyAction::new(threshold.key, if reached { TYPE_DOWN } else { TYPE_UP }));
+        }
+        Some(actions)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_abs_axis() {
+        assert_eq!(parse_abs_axis("x").unwrap(), EV_ABS::ABS_X);
+        assert_eq!(parse_abs_axis("hat0y").unwrap(), EV_ABS::ABS_HAT0Y);
+        assert_eq!(parse_abs_axis("ABS_RZ").unwrap(), EV_ABS::ABS_RZ);
+        assert!(parse_abs_axis("foo").is_err());
+    }
+
+    #[test]
+    fn test_deadzone() {
+        let mut gamepad = GamepadState::new();
+        gamepad.deadzones.insert(EV_ABS::ABS_X, AbsDeadzone { center: 0, radius: 100 });
+
+        assert_eq!(gamepad.filter_value(EV_ABS::ABS_X, 50), Some(0));
+        assert_eq!(gamepad.filter_value(EV_ABS::ABS_X, -80), None);
+        assert_eq!(gamepad.filter_value(EV_ABS::ABS_X, 200), Some(200));
+        assert_eq!(gamepad.filter_value(EV_ABS::ABS_Y, 50), Some(50));
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let mut gamepad = GamepadState::new();
+        gamepad.add_threshold(EV_ABS::ABS_X, AbsThreshold::new(-100, *KEY_A));
+        gamepad.add_threshold(EV_ABS::ABS_X, AbsThreshold::new(100, *KEY_B));
+
+        assert_eq!(gamepad.threshold_actions(EV_ABS::ABS_X, -150).unwrap(), vec![KeyAction::new(*KEY_A, TYPE_DOWN)]);
+        assert_eq!(gamepad.threshold_actions(EV_ABS::ABS_X, -120).unwrap(), vec![]);
+        assert_eq!(gamepad.threshold_actions(EV_ABS::ABS_X, 150).unwrap(), vec![
+            KeyAction::new(*KEY_A, TYPE_UP),
+            KeyAction::new(*KEY_B, TYPE_DOWN),
+        ]);
+        assert_eq!(gamepad.threshold_actions(EV_ABS::ABS_X, 0).unwrap(), vec![KeyAction::new(*KEY_B, TYPE_UP)]);
+        assert!(gamepad.threshold_actions(EV_ABS::ABS_Y, 0).is_none());
+    }
+}
@@ -28,8 +28,15 @@ pub static ref KEY_LEFT: Key = Key::from_str(&EventType::EV_KEY, "KEY_LEFT").unw
 pub static ref KEY_RIGHT: Key = Key::from_str(&EventType::EV_KEY, "KEY_RIGHT").unwrap();
 pub static ref KEY_UP: Key = Key::from_str(&EventType::EV_KEY, "KEY_UP").unwrap();
 pub static ref KEY_DOWN: Key = Key::from_str(&EventType::EV_KEY, "KEY_DOWN").unwrap();
+pub static ref KEY_HOME: Key = Key::from_str(&EventType::EV_KEY, "KEY_HOME").unwrap();
+pub static ref KEY_BACKSPACE: Key = Key::from_str(&EventType::EV_KEY, "KEY_BACKSPACE").unwrap();
 pub static ref KEY_F4: Key = Key::from_str(&EventType::EV_KEY, "KEY_F4").unwrap();
 pub static ref KEY_F5: Key = Key::from_str(&EventType::EV_KEY, "KEY_F5").unwrap();
+pub static ref KEY_F11: Key = Key::from_str(&EventType::EV_KEY, "KEY_F11").unwrap();
+pub static ref KEY_F12: Key = Key::from_str(&EventType::EV_KEY, "KEY_F12").unwrap();
+pub static ref KEY_1: Key = Key::from_str(&EventType::EV_KEY, "KEY_1").unwrap();
+pub static ref KEY_2: Key = Key::from_str(&EventType::EV_KEY, "KEY_2").unwrap();
+pub static ref KEY_3: Key = Key::from_str(&EventType::EV_KEY, "KEY_3").unwrap();
 pub static ref KEY_A: Key = Key::from_str(&EventType::EV_KEY, "KEY_A").unwrap();
 pub static ref KEY_B: Key = Key::from_str(&EventType::EV_KEY, "KEY_B").unwrap();
 pub static ref KEY_C: Key = Key::from_str(&EventType::EV_KEY, "KEY_C").unwrap();
@@ -67,6 +74,11 @@ pub static ref KEY_KPD7: Key = Key::from_str(&EventType::EV_KEY, "KEY_KP7").unwr
 }
 
 
+pub fn is_modifier_key(key: &Key) -> bool {
+    [*KEY_LEFT_CTRL, *KEY_RIGHT_CTRL, *KEY_LEFT_ALT, *KEY_RIGHT_ALT, *KEY_LEFT_SHIFT, *KEY_RIGHT_SHIFT, *KEY_LEFT_META, *KEY_RIGHT_META]
+        .contains(key)
+}
+
 lazy_static! {
     pub(crate) static ref KEY_ALIAS_TABLE: HashMap<&'static str, (Key, KeyModifierFlags)> = {
         let mut m = HashMap::new();
@@ -91,8 +103,10 @@ lazy_static! {
         m.insert("?", (Key::from_str(&EventType::EV_KEY, "KEY_SLASH").unwrap(), KeyModifierFlags::new()));
         m.insert("@", (Key::from_str(&EventType::EV_KEY, "KEY_1").unwrap(), KeyModifierFlags::new().tap_mut(|f|{f.shift();})));
         m.insert("[", (Key::from_str(&EventType::EV_KEY, "KEY_LEFTBRACE").unwrap(), KeyModifierFlags::new()));
-        // TODO implement escaped chars so this doesn't break sequence parsing
-        // m.insert("\"", (Key::from_str(&EventType::EV_KEY, "KEY_APOSTROPHE").unwrap(), KeyModifierFlags::new()));
+        m.insert("\"", (Key::from_str(&EventType::EV_KEY, "KEY_APOSTROPHE").unwrap(), KeyModifierFlags::new().tap_mut(|f|{f.shift();})));
+        m.insert("\\", (Key::from_str(&EventType::EV_KEY, "KEY_BACKSLASH").unwrap(), KeyModifierFlags::new()));
+        m.insert("\n", (Key::from_str(&EventType::EV_KEY, "KEY_ENTER").unwrap(), KeyModifierFlags::new()));
+        m.insert("\t", (Key::from_str(&EventType::EV_KEY, "KEY_TAB").unwrap(), KeyModifierFlags::new()));
         m.insert("]", (Key::from_str(&EventType::EV_KEY, "KEY_RIGHTBRACE").unwrap(), KeyModifierFlags::new()));
         m.insert("^", (Key::from_str(&EventType::EV_KEY, "KEY_6").unwrap(), KeyModifierFlags::new().tap_mut(|f|{f.shift();})));
         m.insert("{", (Key::from_str(&EventType::EV_KEY, "KEY_LEFTBRACE").unwrap(), KeyModifierFlags::new().tap_mut(|f|{f.shift();})));
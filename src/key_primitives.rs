@@ -1,4 +1,7 @@
+use std::fmt;
+
 use evdev_rs::enums::{EventCode, EventType};
+use tap::Tap;
 
 use crate::*;
 
@@ -16,6 +19,14 @@ impl Key {
     }
 }
 
+/// Formats the key the way it is written in scripts, i.e. `a` for `KEY_A`.
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.event_code.to_string();
+        write!(f, "{}", name.strip_prefix("KEY_").unwrap_or(&name).to_lowercase())
+    }
+}
+
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 #[allow(unused)]
@@ -32,16 +43,35 @@ impl KeyValue {
     }
 }
 
+/// Restricts a modifier flag to the modifier key on one side of the keyboard.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum KeyModifierSide { Any, Left, Right }
+
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub struct KeyModifierFlags {
     pub ctrl: bool,
     pub shift: bool,
     pub alt: bool,
     pub meta: bool,
+    pub ctrl_side: KeyModifierSide,
+    pub shift_side: KeyModifierSide,
+    pub alt_side: KeyModifierSide,
+    pub meta_side: KeyModifierSide,
 }
 
 impl KeyModifierFlags {
-    pub fn new() -> Self { KeyModifierFlags { ctrl: false, shift: false, alt: false, meta: false } }
+    pub fn new() -> Self {
+        KeyModifierFlags {
+            ctrl: false,
+            shift: false,
+            alt: false,
+            meta: false,
+            ctrl_side: KeyModifierSide::Any,
+            shift_side: KeyModifierSide::Any,
+            alt_side: KeyModifierSide::Any,
+            meta_side: KeyModifierSide::Any,
+        }
+    }
     pub fn ctrl(&mut self) { self.ctrl = true; }
     pub fn alt(&mut self) { self.alt = true; }
     pub fn shift(&mut self) { self.shift = true; }
@@ -49,10 +79,33 @@ impl KeyModifierFlags {
         self.meta = true;
     }
     pub fn apply_from(&mut self, other: &KeyModifierFlags) {
-        if other.ctrl { self.ctrl(); }
-        if other.alt { self.alt(); }
-        if other.shift { self.shift(); }
-        if other.meta { self.meta(); }
+        if other.ctrl { self.ctrl(); self.ctrl_side = other.ctrl_side; }
+        if other.alt { self.alt(); self.alt_side = other.alt_side; }
+        if other.shift { self.shift(); self.shift_side = other.shift_side; }
+        if other.meta { self.meta(); self.meta_side = other.meta_side; }
+    }
+
+    // the modifier keys used when emitting the flags, left unless the right side is requested
+    pub fn ctrl_key(&self) -> Key { if self.ctrl_side == KeyModifierSide::Right { *KEY_RIGHT_CTRL } else { *KEY_LEFT_CTRL } }
+    pub fn alt_key(&self) -> Key { if self.alt_side == KeyModifierSide::Right { *KEY_RIGHT_ALT } else { *KEY_LEFT_ALT } }
+    pub fn shift_key(&self) -> Key { if self.shift_side == KeyModifierSide::Right { *KEY_RIGHT_SHIFT } else { *KEY_LEFT_SHIFT } }
+    pub fn meta_key(&self) -> Key { if self.meta_side == KeyModifierSide::Right { *KEY_RIGHT_META } else { *KEY_LEFT_META } }
+}
+
+/// Formats the flags the way they are written in mapping triggers, i.e. `<^!`.
+impl fmt::Display for KeyModifierFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags = [(self.ctrl, self.ctrl_side, '^'), (self.shift, self.shift_side, '+'), (self.alt, self.alt_side, '!'), (self.meta, self.meta_side, '#')];
+        for (is_set, side, flag) in flags.iter() {
+            if !is_set { continue; }
+            match side {
+                KeyModifierSide::Left => write!(f, "<")?,
+                KeyModifierSide::Right => write!(f, ">")?,
+                KeyModifierSide::Any => {}
+            }
+            write!(f, "{}", flag)?;
+        }
+        Ok(())
     }
 }
 
@@ -85,6 +138,31 @@ impl KeyModifierState {
     pub fn is_alt(&self) -> bool { self.left_alt || self.right_alt }
     pub fn is_shift(&self) -> bool { self.left_shift || self.right_shift }
     pub fn is_meta(&self) -> bool { self.left_meta || self.right_meta }
+
+    /// Returns all modifier flags that match the current state, more specific (sided) flags first.
+    pub fn to_flag_candidates(&self) -> Vec<KeyModifierFlags> {
+        let modifiers: [(bool, bool, fn(&mut KeyModifierFlags, KeyModifierSide)); 4] = [
+            (self.left_ctrl, self.right_ctrl, |f, side| { f.ctrl = true; f.ctrl_side = side; }),
+            (self.left_alt, self.right_alt, |f, side| { f.alt = true; f.alt_side = side; }),
+            (self.left_shift, self.right_shift, |f, side| { f.shift = true; f.shift_side = side; }),
+            (self.left_meta, self.right_meta, |f, side| { f.meta = true; f.meta_side = side; }),
+        ];
+
+        let mut candidates = vec![KeyModifierFlags::new()];
+        for (left, right, set) in modifiers.iter() {
+            let sides = match (left, right) {
+                (true, false) => vec![KeyModifierSide::Left, KeyModifierSide::Any],
+                (false, true) => vec![KeyModifierSide::Right, KeyModifierSide::Any],
+                (true, true) => vec![KeyModifierSide::Any],
+                (false, false) => continue,
+            };
+
+            candidates = candidates.into_iter()
+                .flat_map(|flags| sides.iter().map(move |side| flags.tap_mut(|f| set(f, *side))))
+                .collect();
+        }
+        candidates
+    }
 }
 
 
@@ -113,6 +191,21 @@ impl KeyActionWithMods {
     pub fn new(key: Key, value: i32, modifiers: KeyModifierFlags) -> Self { KeyActionWithMods { key, value, modifiers } }
 }
 
+impl fmt::Display for KeyActionWithMods {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = wheel_trigger_name(self.key, self.value) {
+            return write!(f, "{}{}", self.modifiers, name);
+        }
+
+        let state = match self.value {
+            0 => "up",
+            1 => "down",
+            _ => "repeat",
+        };
+        write!(f, "{}{{{} {}}}", self.modifiers, self.key, state)
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct KeyClickActionWithMods {
     pub key: Key,
@@ -125,6 +218,12 @@ impl KeyClickActionWithMods {
     pub fn to_key_action(self, value: i32) -> KeyActionWithMods { KeyActionWithMods::new(self.key, value, self.modifiers) }
 }
 
+impl fmt::Display for KeyClickActionWithMods {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.modifiers, self.key)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct KeyMapping {
     pub(crate) from: KeyActionWithMods,
@@ -0,0 +1,120 @@
+use crate::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayerCommand {
+    Push(String),
+    /// pops the given layer or the topmost one if no name is given
+    Pop(Option<String>),
+    Toggle(String),
+    OneShot(String),
+}
+
+/// Keeps track of the active layers, the topmost layer takes precedence when looking up mappings.
+#[derive(Debug, Default)]
+pub struct LayerState {
+    active: Vec<String>,
+    oneshot: Option<String>,
+    /// keys that activate a layer while held down
+    pub momentary: HashMap<Key, String>,
+    /// keys that consumed a one-shot layer, the layer stays active for them until they are released
+    latched: HashMap<Key, String>,
+}
+
+impl LayerState {
+    pub fn new() -> Self { Default::default() }
+
+    pub fn handle_command(&mut self, command: LayerCommand) {
+        match command {
+            LayerCommand::Push(name) => self.active.push(name),
+            LayerCommand::Pop(Some(name)) => {
+                if let Some(idx) = self.active.iter().rposition(|v| *v == name) { self.active.remove(idx); }
+            }
+            LayerCommand::Pop(None) => { self.active.pop(); }
+            LayerCommand::Toggle(name) => {
+                match self.active.iter().rposition(|v| *v == name) {
+                    Some(idx) => { self.active.remove(idx); }
+                    None => self.active.push(name),
+                }
+            }
+            LayerCommand::OneShot(name) => self.oneshot = Some(name),
+        }
+    }
+
+    /// Deactivates all layers, including pending and latched one-shot layers.
+    pub fn deactivate_all(&mut self) {
+        self.active.clear();
+        self.oneshot = None;
+        self.latched.clear();
+    }
+
+    /// Updates momentary and one-shot layers, returns `true` if the event was consumed.
+    pub fn handle_key_action(&mut self, action: &KeyAction) -> bool {
+        if let Some(name) = self.momentary.get(&action.key) {
+            let name = name.clone();
+            if action.value == TYPE_DOWN {
+                self.active.push(name);
+            } else if action.value == TYPE_UP {
+                self.handle_command(LayerCommand::Pop(Some(name)));
+            }
+            return true;
+        }
+
+        if action.value == TYPE_DOWN && !is_modifier_key(&action.key) {
+            if let Some(name) = self.oneshot.take() {
+                self.latched.insert(action.key, name);
+            }
+        }
+        false
+    }
+
+    /// Releases the layer latched by a one-shot key, needs to be called after the key event was processed.
+    pub fn release_latched(&mut self, action: &KeyAction) {
+        if action.value == TYPE_UP { self.latched.remove(&action.key); }
+    }
+
+    /// Returns the layers relevant for the given key, in lookup order.
+    pub fn lookup_order(&self, key: &Key) -> Vec<&String> {
+        self.latched.get(key).into_iter()
+            .chain(self.active.iter().rev())
+            .collect()
+    }
+
+    pub fn active_layers(&self) -> &Vec<String> { &self.active }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layer_stack() {
+        let mut layers = LayerState::new();
+        layers.handle_command(LayerCommand::Push("a".to_string()));
+        layers.handle_command(LayerCommand::Push("b".to_string()));
+        layers.handle_command(LayerCommand::Toggle("c".to_string()));
+        assert_eq!(layers.lookup_order(&*KEY_A), vec!["c", "b", "a"]);
+
+        layers.handle_command(LayerCommand::Toggle("c".to_string()));
+        layers.handle_command(LayerCommand::Pop(Some("a".to_string())));
+        assert_eq!(layers.lookup_order(&*KEY_A), vec!["b"]);
+
+        layers.handle_command(LayerCommand::Pop(None));
+        assert!(layers.lookup_order(&*KEY_A).is_empty());
+    }
+
+    #[test]
+    fn test_oneshot_layer() {
+        let mut layers = LayerState::new();
+        layers.handle_command(LayerCommand::OneShot("a".to_string()));
+
+        // modifiers don't consume the layer
+        assert!(!layers.handle_key_action(&KeyAction::new(*KEY_LEFT_SHIFT, TYPE_DOWN)));
+        assert!(!layers.handle_key_action(&KeyAction::new(*KEY_B, TYPE_DOWN)));
+        assert_eq!(layers.lookup_order(&*KEY_B), vec!["a"]);
+        assert!(layers.lookup_order(&*KEY_C).is_empty());
+
+        layers.release_latched(&KeyAction::new(*KEY_B, TYPE_UP));
+        assert!(layers.lookup_order(&*KEY_B).is_empty());
+    }
+}
@@ -20,19 +20,28 @@ pub use tokio::task;
 
 pub use crate::cli::parse_cli;
 pub use crate::device::virtual_input_device::bind_udev_inputs;
+pub use crate::device::device_info::*;
 pub use crate::key_defs::*;
 pub use crate::key_primitives::*;
 pub use crate::runtime::*;
 pub use crate::runtime::evaluation::*;
+pub use crate::runtime::runtime_error::*;
+pub use crate::parsing::span::{Span, Spanned};
 pub use crate::state::*;
-pub use crate::x11::{x11_initialize, get_window_info_x11};
-pub use crate::x11::ActiveWindowInfo;
+pub use crate::tap_hold::*;
+pub use crate::layers::*;
+pub use crate::combos::*;
+pub use crate::sequences::*;
+pub use crate::mouse::*;
+pub use crate::gamepad::*;
+pub use crate::window::{ActiveWindowInfo, initialize_window_backend, watch_window_events, WindowEvent, ProcessInfo, WindowGeometry, WindowInfoBackend, WindowSelector};
 
-pub mod x11;
+pub mod window;
 pub mod key_defs;
 pub mod state;
 pub mod runtime;
 pub mod script;
+pub mod check;
 pub mod block_ext;
 pub mod key_primitives;
 pub mod parsing;
@@ -42,6 +51,12 @@ pub mod ignore_list;
 pub mod messaging;
 pub mod event_handlers;
 pub mod logging;
+pub mod tap_hold;
+pub mod layers;
+pub mod combos;
+pub mod sequences;
+pub mod mouse;
+pub mod gamepad;
 
 #[cfg(test)]
 pub mod tests;
@@ -6,36 +6,33 @@ mod event_handlers;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let mut configuration = parse_cli()?;
+    let configuration = match parse_cli()? {
+        cli::Command::Run(configuration) => configuration,
+        cli::Command::Check(paths) => std::process::exit(if check::check_scripts(&paths) { 0 } else { 1 }),
+        cli::Command::Fmt { paths, check } =>
+            std::process::exit(if parsing::formatter::format_scripts(&paths, check) { 0 } else { 1 }),
+    };
 
-    // create X11 communication channels
+    // create window info communication channels
     let (window_ev_tx, mut window_ev_rx) = mpsc::channel(128);
     let (mut execution_message_tx, mut message_rx) = mpsc::channel(128);
 
-    // spawn X11 thread
-    tokio::spawn(async move {
-        let x11_state = Arc::new(x11_initialize().unwrap());
-
-        loop {
-            let x11_state_clone = x11_state.clone();
-            let res = task::spawn_blocking(move || {
-                get_window_info_x11(&x11_state_clone)
-            }).await.unwrap();
-
-            if let Ok(Some(val)) = res {
-                window_ev_tx.send(val).await.unwrap_or_else(|_| panic!());
-            }
-        }
-    });
+    // spawn window info task
+    task::spawn(watch_window_events(window_ev_tx));
 
     // initialize global state
     let mut stdout = io::stdout();
     let mut state = State::new();
+    state.error_policy = configuration.error_policy;
     let mut window_cycle_token: usize = 0;
     let mut mappings = CompiledKeyMappings::new();
-    let mut window_change_handlers = vec![];
+    let mut window_change_handlers = WindowChangeHandlers::default();
 
-    let script_ast = script::parse_script(&mut configuration.script_file);
+    let (script_ast, script_files) = script::load_script(&configuration.script_path).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    state.script_files = script_files;
 
     // add a small delay if run from TTY so we don't miss 'enter up' which is often released when the device is grabbed
     if atty::is(atty::Stream::Stdout) {
@@ -47,30 +44,32 @@ async fn main() -> Result<()> {
     let (ev_writer_tx, mut ev_writer_rx) = mpsc::channel(128);
 
     // send one end of the communication channels to the readers/writer
-    bind_udev_inputs(&configuration.devices, ev_reader_init_tx, ev_writer_tx).await?;
+    bind_udev_inputs(&configuration.devices, ev_reader_init_tx, ev_writer_tx, state.held_keys.clone()).await?;
     let mut ev_reader_tx = ev_reader_init_rx.await?;
 
     // initial evaluation pass on global scope
     {
         let execution_message_tx = execution_message_tx.clone();
         let ev_reader_tx = ev_reader_tx.clone();
-        task::spawn(async move {
+        state.script_task = Some(task::spawn(async move {
             script::evaluate_script(script_ast, execution_message_tx, ev_reader_tx, window_cycle_token).await;
-        });
+        }));
     }
 
+    // reload the script when it or one of its imports changes
+    let (script_change_tx, mut script_change_rx) = mpsc::channel(1);
+    let mut _script_watcher = watch_script_files(&state.script_files, &script_change_tx);
+
     // main processing loop
     loop {
         tokio::select! {
-            Some(window) = window_ev_rx.recv() => {
-                state.active_window = Some(window);
-                window_cycle_token = window_cycle_token + 1;
-                event_handlers::handle_active_window_change(&mut ev_reader_tx,
-                    &mut execution_message_tx, window_cycle_token, &mut window_change_handlers);
+            Some(window_event) = window_ev_rx.recv() => {
+                event_handlers::handle_window_event(&mut state, window_event, &mut window_cycle_token,
+                    &mut window_change_handlers, &mut ev_reader_tx, &mut execution_message_tx);
             }
-            Some(ev) = ev_writer_rx.recv() => {
+            Some((device, ev)) = ev_writer_rx.recv() => {
                 event_handlers::handle_stdin_ev(
-                    &mut state, ev,
+                    &mut state, device, ev,
                     &mut mappings,
                     &mut ev_reader_tx,
                     &mut execution_message_tx,
@@ -79,9 +78,27 @@ async fn main() -> Result<()> {
                 ).await.unwrap();
             }
             Some(msg) = message_rx.recv() => {
-                event_handlers::handle_execution_message(&mut stdout, window_cycle_token, msg, &mut state,
-                    &mut mappings, &mut window_change_handlers).await;
+                event_handlers::handle_execution_message(&mut stdout, &mut window_cycle_token, msg, &mut state,
+                    &mut mappings, &mut window_change_handlers, &mut ev_reader_tx, &mut execution_message_tx).await;
+            }
+            Some(()) = script_change_rx.recv() => {
+                match script::load_script(&configuration.script_path) {
+                    Ok((script_ast, script_files)) => {
+                        // imports might have been added or removed
+                        _script_watcher = watch_script_files(&script_files, &script_change_tx);
+                        event_handlers::reload_script(&mut state, script_ast, script_files, &mut window_cycle_token,
+                            &mut ev_reader_tx, &mut execution_message_tx).await;
+                    }
+                    // the previous version of the script keeps running
+                    Err(err) => eprintln!("{}", err),
+                }
             }
         }
     }
 }
+
+fn watch_script_files(script_files: &[script::ScriptFile], script_change_tx: &mpsc::Sender<()>) -> Option<notify::RecommendedWatcher> {
+    script::watch_script_files(script_files, script_change_tx.clone())
+        .map_err(|err| eprintln!("failed to watch the script for changes: {}", err))
+        .ok()
+}
@@ -14,6 +14,10 @@ fn main() {
             .short("-d")
             .long("--devices")
         )
+        .flag(Flag::new()
+            .help("Sets what happens after a runtime error, 'continue' (default) or 'abort'")
+            .long("--on-error")
+        )
         .example(Example::new()
             .text("run a script")
             .command("map2 example.m2")
@@ -29,6 +33,16 @@ fn main() {
             .command("map2 -vvv example.m2")
             .output("Runs the script example.m2 and outputs all debug information.")
         )
+        .example(Example::new()
+            .text("check scripts for mistakes")
+            .command("map2 check example.m2 common.m2")
+            .output("Reports syntax errors, undefined variables and other mistakes without running the scripts.")
+        )
+        .example(Example::new()
+            .text("check that scripts are formatted")
+            .command("map2 fmt --check example.m2")
+            .output("Lists the scripts that aren't formatted, `map2 fmt example.m2` formats them in place.")
+        )
         .custom(
             Section::new("devices")
                 .paragraph(&*vec![
@@ -1,17 +1,56 @@
-use anyhow::Error;
+use evdev_rs::enums::{EV_ABS, EV_REL};
 
 use crate::*;
 
 #[derive(Debug)]
 pub enum ExecutionMessage {
     // EatEv(KeyAction),
-    AddMapping(usize, KeyActionWithMods, Block, GuardedVarMap),
+    AddMapping(usize, Option<String>, KeyActionCondition, KeyActionWithMods, Block, GuardedVarMap),
+    RemoveMapping(usize, Option<String>, KeyActionCondition, KeyActionWithMods),
+    ClearMappings(usize),
+    GetMappings(Option<String>, mpsc::Sender<Vec<String>>),
+    AddTapHold(usize, TapHoldMapping),
+    TapHoldTimeout(usize),
+    AddCombo(usize, ComboMapping),
+    ComboTimeout(usize),
+    SetComboWindow(usize, time::Duration),
+    AddSequence(usize, Vec<KeyClickActionWithMods>, Block, GuardedVarMap),
+    SequenceTimeout(usize),
+    SetSequenceTimeout(usize, time::Duration),
+    SetSequenceCancelKeys(usize, Vec<Key>),
+    AddMomentaryLayer(usize, Key, String),
+    AddRelHandler(usize, EV_REL, Vec<String>, Block, GuardedVarMap),
+    AddAbsHandler(usize, EV_ABS, Vec<String>, Block, GuardedVarMap),
+    AddAbsThreshold(usize, EV_ABS, AbsThreshold),
+    SetAbsDeadzone(usize, EV_ABS, AbsDeadzone),
+    LayerCommand(usize, LayerCommand),
     GetFocusedWindowInfo(mpsc::Sender<Option<ActiveWindowInfo>>),
-    RegisterWindowChangeCallback(Block, GuardedVarMap),
+    RegisterWindowChangeCallback(usize, Block, GuardedVarMap),
+    RegisterWindowTitleChangeCallback(usize, Block, GuardedVarMap),
     Write(String),
     UpdateModifiers(KeyAction),
     Exit(i32),
-    FatalError(Error, i32),
+    RuntimeError(RuntimeError),
+    /// the global scope of a script finished evaluating
+    ScriptEvaluated(usize),
+    /// the global scope of a reloaded script took too long to evaluate
+    ReloadTimeout(usize),
+}
+
+impl ExecutionMessage {
+    /// The window cycle token of the evaluation that sent the message, if the message carries one.
+    pub fn token(&self) -> Option<usize> {
+        use ExecutionMessage::*;
+        match self {
+            AddMapping(token, ..) | RemoveMapping(token, ..) | ClearMappings(token) | AddTapHold(token, _) |
+            AddCombo(token, _) | SetComboWindow(token, _) | AddSequence(token, ..) | SetSequenceTimeout(token, _) |
+            SetSequenceCancelKeys(token, _) | AddMomentaryLayer(token, ..) | AddRelHandler(token, ..) |
+            AddAbsHandler(token, ..) | AddAbsThreshold(token, ..) | SetAbsDeadzone(token, ..) | LayerCommand(token, _) |
+            RegisterWindowChangeCallback(token, ..) | RegisterWindowTitleChangeCallback(token, ..) |
+            ScriptEvaluated(token) => Some(*token),
+            _ => None,
+        }
+    }
 }
 
 pub type ExecutionMessageSender = tokio::sync::mpsc::Sender<ExecutionMessage>;
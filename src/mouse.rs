@@ -0,0 +1,78 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use evdev_rs::enums::EV_REL;
+
+use crate::*;
+
+/// The value of a single wheel tick on high resolution wheel axes.
+pub const WHEEL_HI_RES_TICK: i32 = 120;
+
+pub fn rel_key(axis: EV_REL) -> Key { Key { event_code: EventCode::EV_REL(axis) } }
+
+/// Parses the name of a relative axis, either a short name such as "x" or "wheel" or the full event code name.
+pub fn parse_rel_axis(name: &str) -> Result<EV_REL> {
+    match name {
+        "x" => Ok(EV_REL::REL_X),
+        "y" => Ok(EV_REL::REL_Y),
+        "wheel" => Ok(EV_REL::REL_WHEEL),
+        "hwheel" => Ok(EV_REL::REL_HWHEEL),
+        name => EV_REL::from_str(&name.to_uppercase()).map_err(|_| anyhow!("unknown relative axis '{}'", name)),
+    }
+}
+
+/// Returns the trigger key and value of a wheel direction, as used in mapping triggers (i.e. `wheel_up::a;`).
+pub fn wheel_trigger(name: &str) -> Option<(Key, i32)> {
+    match name {
+        "wheel_up" => Some((rel_key(EV_REL::REL_WHEEL), 1)),
+        "wheel_down" => Some((rel_key(EV_REL::REL_WHEEL), -1)),
+        "wheel_right" => Some((rel_key(EV_REL::REL_HWHEEL), 1)),
+        "wheel_left" => Some((rel_key(EV_REL::REL_HWHEEL), -1)),
+        _ => None,
+    }
+}
+
+/// Returns the name of a wheel direction for the trigger key and value, the reverse of `wheel_trigger`.
+pub fn wheel_trigger_name(key: Key, value: i32) -> Option<&'static str> {
+    match (key.event_code, value.signum()) {
+        (EventCode::EV_REL(EV_REL::REL_WHEEL), 1) => Some("wheel_up"),
+        (EventCode::EV_REL(EV_REL::REL_WHEEL), -1) => Some("wheel_down"),
+        (EventCode::EV_REL(EV_REL::REL_HWHEEL), 1) => Some("wheel_right"),
+        (EventCode::EV_REL(EV_REL::REL_HWHEEL), -1) => Some("wheel_left"),
+        _ => None,
+    }
+}
+
+/// Returns the high resolution counterpart of a wheel axis.
+pub fn wheel_hi_res_axis(axis: EV_REL) -> Option<EV_REL> {
+    match axis {
+        EV_REL::REL_WHEEL => Some(EV_REL::REL_WHEEL_HI_RES),
+        EV_REL::REL_HWHEEL => Some(EV_REL::REL_HWHEEL_HI_RES),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MouseState {
+    /// callbacks that receive all events of a relative axis instead of the output device
+    pub handlers: HashMap<EV_REL, (Vec<String>, Block, GuardedVarMap)>,
+    /// wheel axes that are handled by the script, their high resolution events are replaced
+    pub mapped_wheels: HashSet<EV_REL>,
+}
+
+impl MouseState {
+    pub fn new() -> Self { Default::default() }
+
+    /// Recomputes the mapped wheels after mappings were removed.
+    pub fn update_mapped_wheels(&mut self, mappings: &CompiledKeyMappings) {
+        let handlers = &self.handlers;
+        self.mapped_wheels = [EV_REL::REL_WHEEL, EV_REL::REL_HWHEEL].iter()
+            .filter(|axis| mappings.is_mapped(&rel_key(**axis)) || handlers.contains_key(axis))
+            .cloned()
+            .collect();
+    }
+
+    pub fn is_hi_res_replaced(&self, axis: EV_REL) -> bool {
+        self.mapped_wheels.iter().any(|wheel| wheel_hi_res_axis(*wheel) == Some(axis))
+    }
+}
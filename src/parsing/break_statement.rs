@@ -0,0 +1,9 @@
+use super::*;
+
+pub(super) fn break_statement(input: &str) -> ResNew<&str, Stmt> {
+    let (input, _) = tag_custom("break")(input)?;
+    let (input, _) = ws0(input)?;
+    let (input, _) = tag_custom(";")(input)?;
+
+    Ok((input, (Stmt::Break, None)))
+}
@@ -0,0 +1,88 @@
+use super::*;
+
+/// Parses comma separated items, a trailing comma is allowed.
+fn comma_separated<'a, O>(item: impl Fn(&'a str) -> ResNew<&'a str, O> + Copy) -> impl Fn(&'a str) -> ResNew<&'a str, Vec<O>> {
+    move |input: &'a str| {
+        tuple((
+            opt(tuple((
+                item,
+                ws0,
+                many0(tuple((
+                    tag_custom(","),
+                    ws0,
+                    item,
+                    ws0,
+                ))),
+                opt(tag_custom(",")),
+            ))),
+            ws0,
+        ))(input).map(|(next, (items, _))| {
+            let items = match items {
+                Some((first, _, rest, _)) => {
+                    let mut items: Vec<O> = rest.into_iter().map(|v| v.2.0).collect();
+                    items.insert(0, first.0);
+                    items
+                }
+                None => vec![],
+            };
+            (next, (items, None))
+        })
+    }
+}
+
+pub(super) fn list_literal(input: &str) -> ResNew<&str, Expr> {
+    let (input, _) = tag_custom("[")(input)
+        .map_err(|_: NomErr<CustomError<_>>| make_generic_nom_err_options(input, vec!["list".to_string()]))?;
+
+    tuple((ws0, comma_separated(expr), tag_custom("]")))(input)
+        .map(|(next, (_, (values, _), _))| (next, (Expr::List(values), None)))
+}
+
+fn map_entry(input: &str) -> ResNew<&str, (String, Spanned<Expr>)> {
+    tuple((string_literal, ws0, tag_custom(":"), ws0, expr))(input)
+        .map(|(next, ((key, _), _, _, _, (value, last_err)))| (next, ((key, value), last_err)))
+}
+
+pub(super) fn map_literal(input: &str) -> ResNew<&str, Expr> {
+    let (input, _) = tag_custom("{")(input)
+        .map_err(|_: NomErr<CustomError<_>>| make_generic_nom_err_options(input, vec!["map".to_string()]))?;
+
+    tuple((ws0, comma_separated(map_entry), tag_custom("}")))(input)
+        .map(|(next, (_, (entries, _), _))| (next, (Expr::Map(entries), None)))
+}
+
+/// The `[index]` following an expression.
+pub(super) fn index(input: &str) -> ResNew<&str, Spanned<Expr>> {
+    tuple((tag_custom("["), ws0, expr, ws0, tag_custom("]")))(input)
+        .map(|(next, (_, _, (index, _), _, _))| (next, (index, None)))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(value: f64) -> Spanned<Expr> { Expr::Value(ValueType::Number(value)).into() }
+
+    #[test]
+    fn test_list() {
+        assert_eq!(list_literal("[]"), nom_ok(Expr::List(vec![])));
+        assert_eq!(list_literal("[1, 2,3 ,]"), nom_ok(Expr::List(vec![number(1.0), number(2.0), number(3.0)])));
+        assert_eq!(nom_eval(list_literal("[[1], a]")), Expr::List(vec![
+            Expr::List(vec![number(1.0)]).into(),
+            Expr::Name("a".to_string()).into(),
+        ]));
+        assert!(list_literal("[1 2]").is_err());
+        assert!(list_literal("[::a").is_err());
+    }
+
+    #[test]
+    fn test_map() {
+        assert_eq!(map_literal("{}"), nom_ok(Expr::Map(vec![])));
+        assert_eq!(map_literal("{\"a\": 1, \"b\" : 2}"), nom_ok(Expr::Map(vec![
+            ("a".to_string(), number(1.0)),
+            ("b".to_string(), number(2.0)),
+        ])));
+        assert!(map_literal("{ a::b; }").is_err());
+    }
+}
@@ -5,7 +5,7 @@ use nom::{Compare, CompareResult, Err, InputLength, InputTake, Offset, Parser, S
 use nom::branch::alt;
 use nom::bytes::complete::{is_not, tag, take_until};
 use nom::character::complete::{multispace0, multispace1};
-use nom::combinator::{value};
+use nom::combinator::{opt, value};
 use nom::error::{ErrorKind, ParseError};
 use nom::IResult;
 use nom::multi::many0;
@@ -18,7 +18,7 @@ fn line_comment<'a, E>(input: &'a str) -> IResult<&str, (), E>
 {
     value((), tuple((
         tag("//"),
-        is_not("\r\n")
+        opt(is_not("\r\n")),
     )))(input)
 }
 
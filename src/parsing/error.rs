@@ -62,78 +62,51 @@ impl<Input> FromTagError<Input> for CustomError<Input> {
 }
 
 
-pub(super) fn convert_custom_error<I: core::ops::Deref<Target=str>>(
-    input: I,
-    err: &CustomError<I>,
-) -> String {
-    use std::fmt::Write;
-    use nom::Offset;
-
-    let mut result = String::new();
-
-    let expected;
-    if err.expected.is_empty() {
-        expected = "valid token (no suggestion)".to_string();
-    } else {
-        let mut options = err.expected.clone();
-        options.sort();
-        options.dedup();
-
-        expected = format!("[ {} ]", options.join(", "));
+/// Describes what the parser expected at the position of the error, i.e. "expected ';' or '::'".
+pub(super) fn expected_message<I: InputLength>(err: &CustomError<I>) -> String {
+    let mut options = err.expected.clone();
+    options.sort();
+    options.dedup();
+
+    let found = if err.input.input_len() == 0 { ", found end of input" } else { "" };
+    match options.split_last() {
+        None => format!("unexpected input{}", found),
+        Some((option, [])) => format!("expected {}{}", option, found),
+        Some((last, options)) => format!("expected {} or {}{}", options.join(", "), last, found),
     }
+}
+
+/// Renders the error together with the line it occurred in.
+pub(super) fn convert_custom_error(source: &str, path: Option<&str>, err: &CustomError<&str>) -> String {
+    // inputs that don't point into the source (i.e. empty slices created by a parser) belong to the end of the input
+    let start = (err.input.as_ptr() as usize).checked_sub(source.as_ptr() as usize)
+        .filter(|offset| *offset <= source.len())
+        .unwrap_or(source.len());
+    let end = start + err.input.chars().next().map(char::len_utf8).unwrap_or(0);
+    Span::at(source, start, end).render(source, path, &expected_message(err))
+}
+
 
-    let substring = &err.input;
-
-    let offset = input.offset(&substring);
-
-    if input.is_empty() {
-        // TODO handle EOF
-        // match kind {
-        //     VerboseErrorKind::Char(c) => {
-        //         write!(&mut result, "{}: expected '{}', got empty input\n\n", i, c)
-        //     }
-        //     VerboseErrorKind::Context(s) => write!(&mut result, "{}: in {}, got empty input\n\n", i, s),
-        //     VerboseErrorKind::Nom(e) => write!(&mut result, "{}: in {:?}, got empty input\n\n", i, e),
-        // }
-    } else {
-        let prefix = &input.as_bytes()[..offset];
-
-        // Count the number of newlines in the first `offset` bytes of input
-        let line_number = prefix.iter().filter(|&&b| b == b'\n').count() + 1;
-
-        // Find the line that includes the subslice:
-        // Find the *last* newline before the substring starts
-        let line_begin = prefix
-            .iter()
-            .rev()
-            .position(|&b| b == b'\n')
-            .map(|pos| offset - pos)
-            .unwrap_or(0);
-
-        // Find the full line after that newline
-        let line = input[line_begin..]
-            .lines()
-            .next()
-            .unwrap_or(&input[line_begin..])
-            .trim_end();
-
-        // The (1-indexed) column number is the offset of our substring into that line
-        let column_number: usize = line.offset(&substring) + 1;
-
-        write!(
-            &mut result,
-            "err: at line {line_number}:\n\
-               {line}\n\
-               {caret:>column$}\n\
-               expected {expected}\n\n",
-            // i = i,
-            line_number = line_number,
-            line = line,
-            caret = '^',
-            column = column_number,
-            expected = expected,
-        ).unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expected_message() {
+        let err = |input, expected: &[&str]| CustomError { input, expected: expected.iter().map(|v| v.to_string()).collect() };
+        assert_eq!(expected_message(&err("a", &[])), "unexpected input");
+        assert_eq!(expected_message(&err("a", &["';'"])), "expected ';'");
+        assert_eq!(expected_message(&err("a", &["'::'", "';'", "'::'", "block"])), "expected '::', ';' or block");
+        assert_eq!(expected_message(&err("", &["'}'"])), "expected '}', found end of input");
     }
 
-    result
+    #[test]
+    fn test_convert_custom_error() {
+        let source = "a::b;\nc::d";
+        let err = CustomError { input: &source[6..], expected: vec!["';'".to_string()] };
+        assert!(convert_custom_error(source, None, &err).starts_with("error: expected ';'\n --> line 2, column 1"));
+
+        let err = CustomError { input: "", expected: vec!["';'".to_string()] };
+        assert!(convert_custom_error(source, None, &err).starts_with("error: expected ';', found end of input\n --> line 2, column 5"));
+    }
 }
@@ -2,54 +2,86 @@ use nom::combinator::not;
 
 use super::*;
 
-pub(super) fn expr_4(input: &str) -> ResNew<&str, Expr> {
+pub(super) fn expr_5(input: &str) -> ResNew<&str, Spanned<Expr>> {
     alt((
         map(tuple((tag_custom("("), expr, tag_custom(")"))), |(_, v, _)| v),
-        boolean,
-        string,
-        number,
-        lambda,
-        variable_initialization,
-        variable_assignment,
-        function_call,
-        key_mapping,
-        key_mapping_inline,
-        variable,
+        spanned(alt((
+            boolean,
+            key_mapping_sequence,
+            string,
+            number,
+            list_literal,
+            map_literal,
+            lambda,
+            variable_initialization,
+            variable_assignment,
+            index_assignment,
+            compound_assignment,
+            function_call,
+            key_mapping_wheel,
+            key_mapping_combo,
+            key_mapping_tap_hold,
+            key_mapping,
+            key_mapping_inline,
+            variable,
+        ))),
     ))(input)
 }
 
-pub(super) fn expr_3(input: &str) -> ResNew<&str, Expr> {
+pub(super) fn expr_4(i: &str) -> ResNew<&str, Spanned<Expr>> {
+    let (input, init) = expr_5(i)?;
+    let expr = fold_many0_once_err(
+        |input: &str| spanned(index)(input),
+        init.0,
+        |acc, (index, _)| {
+            let span = acc.span.to(&index.span);
+            Spanned { node: Expr::Index(Box::new(acc), Box::new(index.node)), span }
+        },
+    )(input);
+
+    match expr {
+        Err(v) => Err(v),
+        Ok((next, (expr, last_err))) => Ok((next, (expr, Some(last_err)))),
+    }
+}
+
+pub(super) fn expr_3(input: &str) -> ResNew<&str, Spanned<Expr>> {
     // TODO fold this
     let (input, expr) = alt((
         expr_4,
-        map(
+        spanned(map(
             tuple((tag_custom("!"), not(tag("{")), expr_3)),
             |(_, _, (expr, last_err))| (Expr::Neg(Box::new(expr)), last_err),
-        ),
+        )),
+        spanned(map(
+            tuple((tag_custom("-"), ws0, expr_3)),
+            |(_, _, (expr, last_err))| (Expr::Minus(Box::new(expr)), last_err),
+        )),
     ))(input)?;
 
     Ok((input, expr))
 }
 
-pub(super) fn expr_2(i: &str) -> ResNew<&str, Expr> {
-    let (input, init) = expr_3(i)?;
+type BinaryOp = fn(Box<Spanned<Expr>>, Box<Spanned<Expr>>) -> Expr;
+
+/// Combines two operands, the new node spans from the start of the left to the end of the right operand.
+fn binary_expr(left: Spanned<Expr>, right: Spanned<Expr>, op: BinaryOp) -> Spanned<Expr> {
+    let span = left.span.to(&right.span);
+    Spanned { node: op(Box::new(left), Box::new(right)), span }
+}
+
+/// Parses a left associative chain of operators that bind equally strong.
+fn binary_chain<'a>(
+    i: &'a str,
+    operand: fn(&'a str) -> ResNew<&'a str, Spanned<Expr>>,
+    operator: fn(&'a str) -> IResult<&'a str, &'a str, CustomError<&'a str>>,
+    to_expr: fn(&str) -> BinaryOp,
+) -> ResNew<&'a str, Spanned<Expr>> {
+    let (input, init) = operand(i)?;
     let expr = fold_many0_once_err(
-        |input: &str| {
-            tuple((
-                ws0,
-                alt((tag_custom("*"), tag_custom("/"))),
-                ws0,
-                expr_3
-            ))(input)
-        },
+        |input: &'a str| tuple((ws0, operator, ws0, operand))(input),
         init.0,
-        |acc, (_, op, _, (val, _))| {
-            match op {
-                "*" => Expr::Mul(Box::new(acc), Box::new(val)),
-                "/" => Expr::Div(Box::new(acc), Box::new(val)),
-                _ => unreachable!()
-            }
-        },
+        |acc, (_, op, _, (val, _))| binary_expr(acc, val, to_expr(op)),
     )(input);
 
     match expr {
@@ -58,67 +90,94 @@ pub(super) fn expr_2(i: &str) -> ResNew<&str, Expr> {
     }
 }
 
-pub(super) fn expr_1(i: &str) -> ResNew<&str, Expr> {
-    let (input, init) = expr_2(i)?;
-    let expr = fold_many0_once_err(
-        |input: &str| {
-            tuple((
-                ws0,
-                alt((tag_custom("+"), tag_custom("-"))),
-                ws0,
-                expr_2,
-            ))(input)
+pub(super) fn expr_2(i: &str) -> ResNew<&str, Spanned<Expr>> {
+    binary_chain(
+        i, expr_3,
+        |input| alt((tag_custom("*"), tag_custom("/"), tag_custom("%")))(input),
+        |op| match op {
+            "*" => Expr::Mul,
+            "/" => Expr::Div,
+            "%" => Expr::Mod,
+            _ => unreachable!()
         },
-        init.0,
-        |acc, (_, op, _, (val, _))| {
-            match op {
-                "+" => Expr::Add(Box::new(acc), Box::new(val)),
-                "-" => Expr::Sub(Box::new(acc), Box::new(val)),
-                _ => unreachable!()
-            }
+    )
+}
+
+pub(super) fn expr_1(i: &str) -> ResNew<&str, Spanned<Expr>> {
+    binary_chain(
+        i, expr_2,
+        // `a+=1` and `a++` are assignments
+        |input| alt((
+            terminated(tag_custom("+"), not(alt((tag("+"), tag("="))))),
+            terminated(tag_custom("-"), not(tag("="))),
+        ))(input),
+        |op| match op {
+            "+" => Expr::Add,
+            "-" => Expr::Sub,
+            _ => unreachable!()
         },
-    )(input);
+    )
+}
 
-    match expr {
-        Err(v) => Err(v),
-        Ok((next, (expr, last_err))) => Ok((next, (expr, Some(last_err)))),
+/// `start..end` and `start..=end`, ranges evaluate to lists of numbers.
+pub(super) fn range(i: &str) -> ResNew<&str, Spanned<Expr>> {
+    let (input, (start, last_err)) = expr_1(i)?;
+
+    match tuple((ws0, tag_custom(".."), opt(tag_custom("=")), ws0, expr_1))(input) {
+        Ok((next, (_, _, inclusive, _, (end, last_err)))) => {
+            let span = start.span.to(&end.span);
+            Ok((next, (Spanned { node: Expr::Range(Box::new(start), Box::new(end), inclusive.is_some()), span }, last_err)))
+        }
+        Err(NomErr::Error(_)) => Ok((input, (start, last_err))),
+        Err(err) => Err(err),
     }
 }
 
-pub(super) fn expr(input: &str) -> ResNew<&str, Expr> {
-    let (input, init) = expr_1(input)?;
-
-    let expr = fold_many0_once_err(
-        |i: &str| {
-            tuple((
-                ws0,
-                alt((
-                    tag_custom("=="),
-                    tag_custom("!="),
-                    tag_custom("&&"),
-                    tag_custom("||"),
-                    tag_custom("<"),
-                    tag_custom(">"))),
-                ws0, expr_1))(i)
+fn comparison(i: &str) -> ResNew<&str, Spanned<Expr>> {
+    binary_chain(
+        i, range,
+        |input| alt((tag_custom("<="), tag_custom(">="), tag_custom("<"), tag_custom(">")))(input),
+        |op| match op {
+            "<=" => Expr::LE,
+            ">=" => Expr::GE,
+            "<" => Expr::LT,
+            ">" => Expr::GT,
+            _ => unreachable!()
         },
-        init.0,
-        |acc, (_, op, _, (val, _))| {
-            match op {
-                "==" => Expr::Eq(Box::new(acc), Box::new(val)),
-                "!=" => Expr::Neq(Box::new(acc), Box::new(val)),
-                "&&" => Expr::And(Box::new(acc), Box::new(val)),
-                "||" => Expr::Or(Box::new(acc), Box::new(val)),
-                ">" => Expr::GT(Box::new(acc), Box::new(val)),
-                "<" => Expr::LT(Box::new(acc), Box::new(val)),
-                _ => unreachable!()
-            }
+    )
+}
+
+fn equality(i: &str) -> ResNew<&str, Spanned<Expr>> {
+    binary_chain(
+        i, comparison,
+        |input| alt((tag_custom("=="), tag_custom("!=")))(input),
+        |op| match op {
+            "==" => Expr::Eq,
+            "!=" => Expr::Neq,
+            _ => unreachable!()
         },
-    )(input);
+    )
+}
 
-    match expr {
-        Err(v) => Err(v),
-        Ok((next, (expr, last_err))) => Ok((next, (expr, Some(last_err)))),
-    }
+fn logic_and(i: &str) -> ResNew<&str, Spanned<Expr>> {
+    binary_chain(i, equality, |input| tag_custom("&&")(input), |_| Expr::And)
+}
+
+/// Operators from the weakest to the strongest binding:
+///
+/// | operators          | parser        |
+/// |--------------------|---------------|
+/// | `\|\|`             | `expr`        |
+/// | `&&`               | `logic_and`   |
+/// | `==` `!=`          | `equality`    |
+/// | `<` `<=` `>` `>=`  | `comparison`  |
+/// | `..` `..=`         | `range`       |
+/// | `+` `-`            | `expr_1`      |
+/// | `*` `/` `%`        | `expr_2`      |
+/// | `!` `-` (unary)    | `expr_3`      |
+/// | `[index]`          | `expr_4`      |
+pub(super) fn expr(i: &str) -> ResNew<&str, Spanned<Expr>> {
+    binary_chain(i, logic_and, |input| tag_custom("||")(input), |_| Expr::Or)
 }
 
 
@@ -129,34 +188,82 @@ mod tests {
     #[test]
     fn test_operator_equal() {
         assert_eq!(nom_no_last_err(expr("true == true")),
-                   nom_ok(Expr::Eq(
-                       Box::new(Expr::Value(ValueType::Bool(true))),
-                       Box::new(Expr::Value(ValueType::Bool(true))),
-                   )));
+                   nom_ok(Spanned::from(Expr::Eq(
+                       Box::new(Expr::Value(ValueType::Bool(true)).into()),
+                       Box::new(Expr::Value(ValueType::Bool(true)).into()),
+                   ))));
         assert_eq!(nom_no_last_err(expr("\"hello world\" == \"hello world\"")),
-                   nom_ok(Expr::Eq(
-                       Box::new(Expr::Value(ValueType::String("hello world".to_string()))),
-                       Box::new(Expr::Value(ValueType::String("hello world".to_string()))),
-                   )));
+                   nom_ok(Spanned::from(Expr::Eq(
+                       Box::new(Expr::Value(ValueType::String("hello world".to_string())).into()),
+                       Box::new(Expr::Value(ValueType::String("hello world".to_string())).into()),
+                   ))));
         assert_eq!(nom_no_last_err(expr("\"22hello\" == true")),
-                   nom_ok(Expr::Eq(
-                       Box::new(Expr::Value(ValueType::String("22hello".to_string()))),
-                       Box::new(Expr::Value(ValueType::Bool(true))),
-                   )));
+                   nom_ok(Spanned::from(Expr::Eq(
+                       Box::new(Expr::Value(ValueType::String("22hello".to_string())).into()),
+                       Box::new(Expr::Value(ValueType::Bool(true)).into()),
+                   ))));
     }
 
     #[test]
     fn test_add_sub() {
         assert_eq!(nom_no_last_err(expr("33 + 33")),
-                   nom_ok(Expr::Add(
-                       Box::new(Expr::Value(ValueType::Number(33.0))),
-                       Box::new(Expr::Value(ValueType::Number(33.0))),
-                   )));
+                   nom_ok(Spanned::from(Expr::Add(
+                       Box::new(Expr::Value(ValueType::Number(33.0)).into()),
+                       Box::new(Expr::Value(ValueType::Number(33.0)).into()),
+                   ))));
 
         assert_eq!(nom_no_last_err(expr("33 - 33")),
-                   nom_ok(Expr::Sub(
-                       Box::new(Expr::Value(ValueType::Number(33.0))),
-                       Box::new(Expr::Value(ValueType::Number(33.0))),
-                   )));
+                   nom_ok(Spanned::from(Expr::Sub(
+                       Box::new(Expr::Value(ValueType::Number(33.0)).into()),
+                       Box::new(Expr::Value(ValueType::Number(33.0)).into()),
+                   ))));
+    }
+
+    #[test]
+    fn test_precedence() {
+        let num = |value: f64| Box::new(Spanned::from(Expr::Value(ValueType::Number(value))));
+        let name = |name: &str| Box::new(Spanned::from(Expr::Name(name.to_string())));
+
+        assert_eq!(nom_no_last_err(expr("1 + 2 * 3 % 4")),
+                   nom_ok(Spanned::from(Expr::Add(
+                       num(1.0),
+                       Box::new(Expr::Mod(Box::new(Expr::Mul(num(2.0), num(3.0)).into()), num(4.0)).into()),
+                   ))));
+
+        assert_eq!(nom_no_last_err(expr("a || b && c == d")),
+                   nom_ok(Spanned::from(Expr::Or(
+                       name("a"),
+                       Box::new(Expr::And(name("b"), Box::new(Expr::Eq(name("c"), name("d")).into())).into()),
+                   ))));
+
+        assert_eq!(nom_no_last_err(expr("a <= 1 != b >= 2")),
+                   nom_ok(Spanned::from(Expr::Neq(
+                       Box::new(Expr::LE(name("a"), num(1.0)).into()),
+                       Box::new(Expr::GE(name("b"), num(2.0)).into()),
+                   ))));
+
+        assert_eq!(nom_no_last_err(expr("-a * 2")),
+                   nom_ok(Spanned::from(Expr::Mul(Box::new(Expr::Minus(name("a")).into()), num(2.0)))));
+        assert_eq!(nom_no_last_err(expr("1 - -2")),
+                   nom_ok(Spanned::from(Expr::Sub(num(1.0), num(-2.0)))));
+    }
+
+    #[test]
+    fn test_range() {
+        assert_eq!(nom_no_last_err(expr("0..a + 1")),
+                   nom_ok(Spanned::from(Expr::Range(
+                       Box::new(Expr::Value(ValueType::Number(0.0)).into()),
+                       Box::new(Expr::Add(
+                           Box::new(Expr::Name("a".to_string()).into()),
+                           Box::new(Expr::Value(ValueType::Number(1.0)).into()),
+                       ).into()),
+                       false,
+                   ))));
+        assert_eq!(nom_no_last_err(expr("1..=3")),
+                   nom_ok(Spanned::from(Expr::Range(
+                       Box::new(Expr::Value(ValueType::Number(1.0)).into()),
+                       Box::new(Expr::Value(ValueType::Number(3.0)).into()),
+                       true,
+                   ))));
     }
 }
@@ -18,6 +18,21 @@ pub(super) fn for_loop(input: &str) -> ResNew<&str, Stmt> {
         })
 }
 
+/// `for x in list {}`, iterates over lists, the keys of maps and the characters of strings.
+pub(super) fn for_in_loop(input: &str) -> ResNew<&str, Stmt> {
+    tuple((
+        tag_custom("for"), ws1,
+        ident, ws1,
+        tag_custom("in"), ws1,
+        expr, ws0,
+        block,
+    ))(input)
+        .map(|(next, v)| {
+            let stmt = Stmt::ForIn(v.2.0, v.6.0, v.8.0);
+            (next, (stmt, None))
+        })
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -28,11 +43,28 @@ mod tests {
         assert_eq!(
             for_loop("for(let i=0; i<20; i=i+1){}"),
             nom_ok( Stmt::For(
-                Expr::Init("i".to_string(), Box::new(Expr::Value(ValueType::Number(0.0)))),
-                Expr::LT(Box::new(Expr::Name("i".to_string())), Box::new(Expr::Value(ValueType::Number(20.0)))),
+                Expr::Init("i".to_string(), Box::new(Expr::Value(ValueType::Number(0.0)).into())).into(),
+                Expr::LT(Box::new(Expr::Name("i".to_string()).into()), Box::new(Expr::Value(ValueType::Number(20.0)).into())).into(),
                 nom_eval(expr("i=i+1")),
                 Block::new(),
             ))
         );
     }
+
+    #[test]
+    fn test_for_in_loop() {
+        assert_eq!(
+            for_in_loop("for key in keys(map) { print(key); }"),
+            nom_ok(Stmt::ForIn(
+                "key".to_string(),
+                nom_eval(expr("keys(map)")),
+                nom_eval(block("{ print(key); }")),
+            ))
+        );
+        assert_eq!(
+            nom_no_last_err(for_in_loop("for i in 0..3 {}")),
+            nom_ok(Stmt::ForIn("i".to_string(), nom_eval(expr("0..3")), Block::new()))
+        );
+        assert!(for_in_loop("for(let i=0; i<20; i=i+1){}").is_err());
+    }
 }
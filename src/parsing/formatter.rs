@@ -0,0 +1,653 @@
+use std::path::PathBuf;
+
+use nom::combinator::recognize;
+
+use crate::script::ScriptFile;
+
+use super::*;
+
+/// Formats the scripts in place, or only reports the ones that aren't formatted if `check` is set. Problems are
+/// reported on the standard error output.
+/// Returns `true` if all scripts could be formatted, or are formatted already in check mode.
+pub fn format_scripts(paths: &[PathBuf], check: bool) -> bool {
+    let mut ok = true;
+    for path in paths {
+        let res = fs::read_to_string(path)
+            .map_err(|err| anyhow!("failed to read script '{}': {}", path.display(), err))
+            .and_then(|source| {
+                let formatted = format_script(&ScriptFile { path: path.clone(), source: source.clone() })?;
+                Ok((source, formatted))
+            });
+
+        let (source, formatted) = match res {
+            Ok(v) => v,
+            Err(err) => {
+                eprintln!("{}\n", err);
+                ok = false;
+                continue;
+            }
+        };
+
+        if source == formatted { continue; }
+        if check {
+            eprintln!("{} is not formatted", path.display());
+            ok = false;
+        } else if let Err(err) = fs::write(path, formatted) {
+            eprintln!("failed to write script '{}': {}\n", path.display(), err);
+            ok = false;
+        }
+    }
+    ok
+}
+
+/// Formats the script, comments are kept where they are and empty lines between statements are collapsed into one.
+///
+/// Statements that contain comments the formatter can't place, i.e. inside of a list, are kept as they are.
+pub fn format_script(file: &ScriptFile) -> Result<String> {
+    let block = parser::parse_script(file, 0)?;
+    let source = &*file.source;
+
+    let formatted = span::with_source(source, 0, || {
+        let mut formatter = Formatter::new(source);
+        let mut out = String::new();
+        formatter.body(&mut out, &block, 0, source.len(), 0);
+        out
+    });
+
+    // the formatter must never change what the script does or lose comments, the scripts are compared without their
+    // spans since formatting moves the code around
+    let without_spans = |source| match global_block(source) {
+        Ok(("", (block, _))) => Some(block),
+        _ => None,
+    };
+    let reparsed = without_spans(&formatted);
+    if reparsed.is_none() || reparsed != without_spans(source) || comment_texts(&formatted) != comment_texts(source) {
+        return Err(anyhow!("failed to format '{}': the formatted script differs from the original", file.path.display()));
+    }
+    Ok(formatted)
+}
+
+/// Finds the byte ranges of the comments in the source, strings and their `${expr}` parts are skipped.
+fn comment_ranges(source: &str) -> Vec<(usize, usize)> {
+    let bytes = source.as_bytes();
+    let mut comments = vec![];
+    // the number of open braces of each `${expr}` part the scan is in
+    let mut interpolations: Vec<usize> = vec![];
+    let mut in_string = false;
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        let next = bytes.get(idx + 1).copied();
+        if in_string {
+            match (bytes[idx], next) {
+                (b'\\', _) => idx += 1,
+                (b'"', _) => in_string = false,
+                (b'$', Some(b'{')) => {
+                    interpolations.push(0);
+                    in_string = false;
+                    idx += 1;
+                }
+                _ => {}
+            }
+            idx += 1;
+            continue;
+        }
+
+        match (bytes[idx], next) {
+            (b'"', _) => in_string = true,
+            (b'/', Some(b'/')) => {
+                let end = source[idx..].find(['\n', '\r']).map_or(source.len(), |end| idx + end);
+                comments.push((idx, end));
+                idx = end;
+                continue;
+            }
+            (b'/', Some(b'*')) => {
+                let end = source[idx + 2..].find("*/").map_or(source.len(), |end| idx + 2 + end + 2);
+                comments.push((idx, end));
+                idx = end;
+                continue;
+            }
+            (b'{', _) => if let Some(depth) = interpolations.last_mut() { *depth += 1; },
+            (b'}', _) => match interpolations.last_mut() {
+                Some(0) => {
+                    interpolations.pop();
+                    in_string = true;
+                }
+                Some(depth) => *depth -= 1,
+                None => {}
+            },
+            _ => {}
+        }
+        idx += 1;
+    }
+    comments
+}
+
+fn comment_texts(source: &str) -> Vec<&str> {
+    comment_ranges(source).into_iter().map(|(start, end)| source[start..end].trim_end()).collect()
+}
+
+fn escape_string(value: &str) -> String {
+    let mut escaped = String::from("\"");
+    let mut chars = value.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '$' if chars.peek() == Some(&'{') => escaped.push_str("\\$"),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// How strongly an expression binds, operands that bind weaker than their operator need parentheses.
+fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Or(_, _) => 1,
+        Expr::And(_, _) => 2,
+        Expr::Eq(_, _) | Expr::Neq(_, _) => 3,
+        Expr::LT(_, _) | Expr::GT(_, _) | Expr::LE(_, _) | Expr::GE(_, _) => 4,
+        Expr::Range(_, _, _) => 5,
+        Expr::Add(_, _) | Expr::Sub(_, _) => 6,
+        Expr::Mul(_, _) | Expr::Div(_, _) | Expr::Mod(_, _) => 7,
+        Expr::Neg(_) | Expr::Minus(_) => 8,
+        Expr::Index(_, _) => 9,
+        Expr::Name(_) | Expr::Value(_) | Expr::List(_) | Expr::Map(_) | Expr::Interpolation(_) |
+        Expr::FunctionCall(_, _) | Expr::Inc(_) => 10,
+        // assignments, lambdas and mappings
+        _ => 0,
+    }
+}
+
+fn indentation(indent: usize) -> String { "  ".repeat(indent) }
+
+/// Recognizes the trigger of a mapping, the part in front of `::`.
+type TriggerParser = fn(&str) -> IResult<&str, &str, CustomError<&str>>;
+
+fn sequence_trigger(input: &str) -> IResult<&str, &str, CustomError<&str>> {
+    recognize(key_sequence)(input)
+}
+
+fn combo_trigger(input: &str) -> IResult<&str, &str, CustomError<&str>> {
+    recognize(tuple((key, many1(tuple((ws0, tag_custom("&"), ws0, key))))))(input)
+}
+
+fn wheel_trigger(input: &str) -> IResult<&str, &str, CustomError<&str>> {
+    recognize(tuple((
+        key_flags,
+        alt((tag("wheel_up"), tag("wheel_down"), tag("wheel_left"), tag("wheel_right"))),
+    )))(input)
+}
+
+fn key_trigger(input: &str) -> IResult<&str, &str, CustomError<&str>> {
+    recognize(key_action_with_flags)(input)
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    comments: Vec<(usize, usize)>,
+    /// set once a comment made it into the output
+    emitted: Vec<bool>,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(source: &'a str) -> Self {
+        let comments = comment_ranges(source);
+        let emitted = vec![false; comments.len()];
+        Formatter { source, comments, emitted }
+    }
+
+    fn text(&self, span: Span) -> Option<&'a str> {
+        if !span.is_known() { return None; }
+        self.source.get(span.start..span.end)
+    }
+
+    fn comment_text(&self, idx: usize) -> &'a str {
+        let (start, end) = self.comments[idx];
+        self.source[start..end].trim_end()
+    }
+
+    /// Marks the comments in the range as emitted since the range is copied to the output as it is.
+    fn keep(&mut self, start: usize, end: usize) {
+        for (idx, (comment_start, comment_end)) in self.comments.iter().enumerate() {
+            if *comment_start >= start && *comment_end <= end { self.emitted[idx] = true; }
+        }
+    }
+
+    fn all_emitted(&self, start: usize, end: usize) -> bool {
+        self.comments.iter().zip(self.emitted.iter())
+            .all(|((comment_start, comment_end), emitted)| *emitted || *comment_start < start || *comment_end > end)
+    }
+
+    /// Finds the `{` that starts a block, skipping comments and keywords such as `else`.
+    fn next_brace(&self, from: usize) -> Option<usize> {
+        let mut idx = from;
+        while idx < self.source.len() {
+            if let Some((_, end)) = self.comments.iter().find(|(start, _)| *start == idx) {
+                idx = *end;
+                continue;
+            }
+            if self.source.as_bytes()[idx] == b'{' { return Some(idx); }
+            idx += 1;
+        }
+        None
+    }
+
+    /// Writes the comments between two parts of the source.
+    /// Returns `true` if the parts are separated by an empty line.
+    fn gap(&mut self, out: &mut String, from: usize, to: usize, indent: usize, first: &mut bool) -> bool {
+        let mut pos = from;
+        for idx in 0..self.comments.len() {
+            let (start, end) = self.comments[idx];
+            if self.emitted[idx] || start < from || end > to { continue; }
+
+            let newlines = self.source[pos..start].matches('\n').count();
+            if newlines == 0 && out.ends_with('\n') {
+                // a comment at the end of a line stays there
+                out.pop();
+                out.push(' ');
+            } else {
+                if newlines >= 2 && !*first { out.push('\n'); }
+                out.push_str(&indentation(indent));
+                *first = false;
+            }
+            out.push_str(self.comment_text(idx));
+            out.push('\n');
+            self.emitted[idx] = true;
+            pos = end;
+        }
+        self.source[pos..to].matches('\n').count() >= 2
+    }
+
+    /// Writes the statements of the block, `start` and `end` enclose the statements without the braces.
+    fn body(&mut self, out: &mut String, block: &Block, start: usize, end: usize, indent: usize) {
+        let mut pos = start;
+        let mut first = true;
+        for stmt in &block.statements {
+            let empty_line = self.gap(out, pos, stmt.span.start, indent, &mut first);
+            if empty_line && !first { out.push('\n'); }
+            first = false;
+
+            let formatted = self.stmt(stmt, indent);
+            out.push_str(&indentation(indent));
+            out.push_str(&formatted);
+            out.push('\n');
+            pos = stmt.span.end;
+        }
+        self.gap(out, pos, end, indent, &mut first);
+    }
+
+    /// Formats the block that starts at the given offset.
+    /// Returns the formatted block, the offset following it and the block itself.
+    fn block_at(&mut self, open: usize, indent: usize) -> Option<(String, usize, Block)> {
+        let (rest, (block, _)) = block(&self.source[open..]).ok()?;
+        let end = self.source.len() - rest.len();
+
+        let mut out = String::from("{\n");
+        self.body(&mut out, &block, open + 1, end - 1, indent + 1);
+        if out == "{\n" { return Some(("{}".to_string(), end, block)); }
+        out.push_str(&indentation(indent));
+        out.push('}');
+        Some((out, end, block))
+    }
+
+    /// Formats the block that starts at the given offset if it is the expected one.
+    fn expected_block_at(&mut self, open: usize, expected: &Block, indent: usize) -> Option<(String, usize)> {
+        let (formatted, end, block) = self.block_at(open, indent)?;
+        if block != *expected { return None; }
+        Some((formatted, end))
+    }
+
+    fn stmt(&mut self, stmt: &Spanned<Stmt>, indent: usize) -> String {
+        let span = stmt.span;
+        match self.format_stmt(stmt, indent) {
+            Some(formatted) if self.all_emitted(span.start, span.end) => formatted,
+            // comments in places that can't be formatted, such as inside of a list, keep the statement as it is
+            _ => {
+                self.keep(span.start, span.end);
+                self.source[span.start..span.end].to_string()
+            }
+        }
+    }
+
+    fn format_stmt(&mut self, stmt: &Spanned<Stmt>, indent: usize) -> Option<String> {
+        let span = stmt.span;
+        self.text(span)?;
+
+        let formatted = match &stmt.node {
+            // the statement ends with `;`
+            Stmt::Expr(expr) => format!("{};", self.expr_node(expr, Span { end: span.end - 1, ..span }, indent)?),
+            Stmt::Block(block) => self.expected_block_at(span.start, block, indent)?.0,
+            Stmt::If(pairs, else_block) => {
+                let mut formatted = String::new();
+                let mut end = span.start;
+                for (idx, (condition, block)) in pairs.iter().enumerate() {
+                    let condition_text = self.expr(condition, indent)?;
+                    let (block_text, block_end) = self.expected_block_at(self.next_brace(condition.span.end)?, block, indent)?;
+                    formatted.push_str(if idx == 0 { "if (" } else { " else if (" });
+                    formatted.push_str(&format!("{}) {}", condition_text, block_text));
+                    end = block_end;
+                }
+                if let Some(block) = else_block {
+                    let (block_text, _) = self.expected_block_at(self.next_brace(end)?, block, indent)?;
+                    formatted.push_str(&format!(" else {}", block_text));
+                }
+                formatted
+            }
+            Stmt::For(init, condition, advance, block) => {
+                let (init, condition, advance_text) = (self.expr(init, indent)?, self.expr(condition, indent)?, self.expr(advance, indent)?);
+                let (block_text, _) = self.expected_block_at(self.next_brace(advance.span.end)?, block, indent)?;
+                format!("for ({}; {}; {}) {}", init, condition, advance_text, block_text)
+            }
+            Stmt::ForIn(name, iterable, block) => {
+                let iterable_text = self.expr(iterable, indent)?;
+                let (block_text, _) = self.expected_block_at(self.next_brace(iterable.span.end)?, block, indent)?;
+                format!("for {} in {} {}", name, iterable_text, block_text)
+            }
+            Stmt::While(condition, block) => {
+                let condition_text = self.expr(condition, indent)?;
+                let (block_text, _) = self.expected_block_at(self.next_brace(condition.span.end)?, block, indent)?;
+                format!("while ({}) {}", condition_text, block_text)
+            }
+            Stmt::Loop(block) => format!("loop {}", self.expected_block_at(self.next_brace(span.start)?, block, indent)?.0),
+            Stmt::Return(value) => format!("return {};", self.expr(value, indent)?),
+            Stmt::Continue => "continue;".to_string(),
+            Stmt::Break => "break;".to_string(),
+            Stmt::Import(import) => match &import.namespace {
+                Some(namespace) => format!("import {} as {};", escape_string(&import.path), namespace),
+                None => format!("import {};", escape_string(&import.path)),
+            },
+            Stmt::Export(expr) => format!("export {};", self.expr_node(expr, Span::default(), indent)?),
+        };
+        Some(formatted)
+    }
+
+    fn expr(&mut self, expr: &Spanned<Expr>, indent: usize) -> Option<String> {
+        self.expr_node(&expr.node, expr.span, indent)
+    }
+
+    /// Formats the expression, adding parentheses if it binds weaker than `min_precedence`.
+    fn operand(&mut self, expr: &Spanned<Expr>, min_precedence: u8, indent: usize) -> Option<String> {
+        let formatted = self.expr(expr, indent)?;
+        if precedence(&expr.node) < min_precedence { return Some(format!("({})", formatted)); }
+        Some(formatted)
+    }
+
+    fn binary(&mut self, left: &Spanned<Expr>, operator: &str, right: &Spanned<Expr>, precedence: u8, indent: usize) -> Option<String> {
+        // operators are left associative
+        let left = self.operand(left, precedence, indent)?;
+        let right = self.operand(right, precedence + 1, indent)?;
+        Some(format!("{} {} {}", left, operator, right))
+    }
+
+    fn unary(&mut self, operator: &str, operand: &Spanned<Expr>, indent: usize) -> Option<String> {
+        let formatted = self.operand(operand, 8, indent)?;
+        // `-2` is a number rather than the negation of one and `!{` isn't an operator
+        let ambiguous = match operator {
+            "-" => formatted.starts_with('-') || formatted.starts_with(|ch: char| ch.is_ascii_digit()),
+            _ => formatted.starts_with('{'),
+        };
+        if ambiguous { return Some(format!("{}({})", operator, formatted)); }
+        Some(format!("{}{}", operator, formatted))
+    }
+
+    fn expr_node(&mut self, expr: &Expr, span: Span, indent: usize) -> Option<String> {
+        let formatted = match expr {
+            Expr::Or(left, right) => self.binary(left, "||", right, 1, indent)?,
+            Expr::And(left, right) => self.binary(left, "&&", right, 2, indent)?,
+            Expr::Eq(left, right) => self.binary(left, "==", right, 3, indent)?,
+            Expr::Neq(left, right) => self.binary(left, "!=", right, 3, indent)?,
+            Expr::LT(left, right) => self.binary(left, "<", right, 4, indent)?,
+            Expr::GT(left, right) => self.binary(left, ">", right, 4, indent)?,
+            Expr::LE(left, right) => self.binary(left, "<=", right, 4, indent)?,
+            Expr::GE(left, right) => self.binary(left, ">=", right, 4, indent)?,
+            Expr::Add(left, right) => self.binary(left, "+", right, 6, indent)?,
+            Expr::Sub(left, right) => self.binary(left, "-", right, 6, indent)?,
+            Expr::Mul(left, right) => self.binary(left, "*", right, 7, indent)?,
+            Expr::Div(left, right) => self.binary(left, "/", right, 7, indent)?,
+            Expr::Mod(left, right) => self.binary(left, "%", right, 7, indent)?,
+            Expr::Range(start, end, inclusive) => {
+                // ranges don't chain
+                let start = self.operand(start, 6, indent)?;
+                let end = self.operand(end, 6, indent)?;
+                format!("{}{}{}", start, if *inclusive { "..=" } else { ".." }, end)
+            }
+            Expr::Neg(operand) => self.unary("!", operand, indent)?,
+            Expr::Minus(operand) => self.unary("-", operand, indent)?,
+            Expr::Init(name, value) => format!("let {} = {}", name, self.expr(value, indent)?),
+            Expr::Assign(name, value) => format!("{} = {}", name, self.expr(value, indent)?),
+            Expr::AddAssign(name, value) => format!("{} += {}", name, self.expr(value, indent)?),
+            Expr::SubAssign(name, value) => format!("{} -= {}", name, self.expr(value, indent)?),
+            Expr::Inc(name) => format!("{}++", name),
+            Expr::IndexAssign(container, index, value) => format!("{}[{}] = {}",
+                                                                  self.operand(container, 9, indent)?,
+                                                                  self.expr(index, indent)?,
+                                                                  self.expr(value, indent)?),
+            Expr::KeyMapping(_) | Expr::TapHold(_) | Expr::Combo(_, _) | Expr::SequenceMapping(_, _) =>
+                self.mapping(expr, span, indent)?,
+            Expr::Name(name) => name.clone(),
+            Expr::Value(ValueType::Bool(value)) => value.to_string(),
+            Expr::Value(ValueType::Number(value)) => value.to_string(),
+            // strings keep their escapes
+            Expr::Value(ValueType::String(value)) => match self.text(span) {
+                Some(text) if text.len() >= 2 && text.starts_with('"') && text.ends_with('"') => text.to_string(),
+                _ => escape_string(value),
+            },
+            Expr::Interpolation(_) => match self.text(span) {
+                Some(text) if text.len() >= 2 && text.starts_with('"') && text.ends_with('"') => {
+                    self.keep(span.start, span.end);
+                    text.to_string()
+                }
+                _ => return None,
+            },
+            Expr::List(items) => {
+                let items = items.iter().map(|item| self.expr(item, indent)).collect::<Option<Vec<_>>>()?;
+                format!("[{}]", items.join(", "))
+            }
+            Expr::Map(entries) => {
+                let entries = entries.iter()
+                    .map(|(key, value)| Some(format!("{}: {}", escape_string(key), self.expr(value, indent)?)))
+                    .collect::<Option<Vec<_>>>()?;
+                format!("{{{}}}", entries.join(", "))
+            }
+            Expr::Index(container, index) => format!("{}[{}]", self.operand(container, 9, indent)?, self.expr(index, indent)?),
+            Expr::Lambda(params, block) => {
+                self.text(span)?;
+                let (block_text, _) = self.expected_block_at(self.next_brace(span.start)?, block, indent)?;
+                format!("|{}| {}", params.join(", "), block_text)
+            }
+            Expr::FunctionCall(name, args) => {
+                let args = args.iter().map(|arg| self.expr(arg, indent)).collect::<Option<Vec<_>>>()?;
+                format!("{}({})", name, args.join(", "))
+            }
+            // only part of mappings that were expanded by the parser
+            Expr::Value(_) | Expr::KeyAction(_) | Expr::SleepAction(_) | Expr::ReleaseRestoreModifiers(_, _, _) => return None,
+        };
+        Some(formatted)
+    }
+
+    /// Formats a mapping, the trigger is kept as it is and blocks are formatted. Other targets are kept as they are.
+    fn mapping(&mut self, expr: &Expr, span: Span, indent: usize) -> Option<String> {
+        let text = self.text(span)?;
+        let triggers: &[TriggerParser] = match expr {
+            Expr::SequenceMapping(_, _) => &[sequence_trigger],
+            Expr::Combo(_, _) => &[combo_trigger],
+            _ => &[wheel_trigger, key_trigger],
+        };
+        let trigger = triggers.iter()
+            .filter_map(|trigger| trigger(text).ok())
+            .find(|(rest, _)| rest.starts_with("::"))
+            .map(|(_, trigger)| trigger)?;
+
+        let target = text[trigger.len() + 2..].trim_start();
+        let trigger = match expr {
+            Expr::Combo(_, _) => trigger.split('&').map(str::trim).collect::<Vec<_>>().join(" & "),
+            _ => trigger.to_string(),
+        };
+
+        let target_start = span.end - target.len();
+        if target.starts_with('{') {
+            if let Some((block_text, end, _)) = self.block_at(target_start, indent) {
+                if end == span.end { return Some(format!("{}::{}", trigger, block_text)); }
+            }
+        }
+
+        self.keep(target_start, span.end);
+        Some(format!("{}::{}", trigger, target))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    fn format(source: &str) -> Result<String> {
+        format_script(&ScriptFile { path: "test.m2".into(), source: source.to_string() })
+    }
+
+    #[test]
+    fn test_format() {
+        let source = indoc! {r#"
+            let a=1+2*3;let b = (1 + 2) * 3;
+            if(a==3){print("a is 3");}else if (a == 4){
+                print("a is 4");
+            }else{}
+            for(let i=0;i<5;i=i+1){ continue; }
+            for n in 1..=6 { break; }
+            while(a<2){a+=1;}
+            loop{ a++; return -(-1); }
+            let f = |x,y|{return x-(y-1);};
+            let m = {"a" : [1,2,], "b\"" : !(true && false)};
+            m["a"][0] = -a[0];
+            import "common.m2" as common;
+        "#};
+        assert_eq!(format(source).unwrap(), indoc! {r#"
+            let a = 1 + 2 * 3;
+            let b = (1 + 2) * 3;
+            if (a == 3) {
+              print("a is 3");
+            } else if (a == 4) {
+              print("a is 4");
+            } else {}
+            for (let i = 0; i < 5; i = i + 1) {
+              continue;
+            }
+            for n in 1..=6 {
+              break;
+            }
+            while (a < 2) {
+              a += 1;
+            }
+            loop {
+              a++;
+              return -(-1);
+            }
+            let f = |x, y| {
+              return x - (y - 1);
+            };
+            let m = {"a": [1, 2], "b\"": !(true && false)};
+            m["a"][0] = -a[0];
+            import "common.m2" as common;
+        "#});
+    }
+
+    #[test]
+    fn test_format_mappings() {
+        let source = indoc! {r#"
+            a::b;
+            !b::{ send("hi ${name}"); };
+            j &k::esc;
+            "ab"::{print("ab");};
+            capslock::tap_hold(esc, leftctrl);
+            window("class:firefox", ||{
+            a::{
+            send("{c down}");
+            };
+            });
+        "#};
+        assert_eq!(format(source).unwrap(), indoc! {r#"
+            a::b;
+            !b::{
+              send("hi ${name}");
+            };
+            j & k::esc;
+            "ab"::{
+              print("ab");
+            };
+            capslock::tap_hold(esc, leftctrl);
+            window("class:firefox", || {
+              a::{
+                send("{c down}");
+              };
+            });
+        "#});
+    }
+
+    #[test]
+    fn test_comments() {
+        let source = indoc! {r#"
+            // leading comment
+            //
+            let a = 1; // trailing comment
+
+
+            /* inline */ let b = 2;
+            if (a == 1) { // after the brace
+                // inside
+                print("a // not a comment");
+
+                /* last */
+            }
+            let c = [1, // one
+                2];
+        "#};
+        assert_eq!(format(source).unwrap(), indoc! {r#"
+            // leading comment
+            //
+            let a = 1; // trailing comment
+
+            /* inline */
+            let b = 2;
+            if (a == 1) { // after the brace
+              // inside
+              print("a // not a comment");
+
+              /* last */
+            }
+            let c = [1, // one
+                2];
+        "#});
+    }
+
+    #[test]
+    fn test_format_errors() {
+        let err = format("let a = ;").unwrap_err().to_string();
+        assert!(err.starts_with("error: "), "{}", err);
+        assert_eq!(format("").unwrap(), "");
+    }
+
+    #[test]
+    fn test_examples() {
+        let mut paths: Vec<PathBuf> = fs::read_dir("examples").unwrap()
+            .chain(fs::read_dir("examples/imports").unwrap())
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("m2"))
+            .collect();
+        paths.sort();
+        assert!(!paths.is_empty());
+
+        for path in paths {
+            let source = fs::read_to_string(&path).unwrap();
+            let formatted = format_script(&ScriptFile { path: path.clone(), source })
+                .unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+            assert_eq!(format(&formatted).unwrap(), formatted, "{} isn't formatted consistently", path.display());
+        }
+    }
+}
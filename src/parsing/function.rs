@@ -1,11 +1,11 @@
 use super::*;
 
-pub(super) fn function_arg(input: &str) -> ResNew<&str, Expr> {
+pub(super) fn function_arg(input: &str) -> ResNew<&str, Spanned<Expr>> {
     expr(input)
 }
 
 pub(super) fn function_call(input: &str) -> ResNew<&str, Expr> {
-    let (input, (ident_res,_)) = tuple((ident, tag_custom("(")))(input)
+    let (input, (ident_res,_)) = tuple((qualified_ident, tag_custom("(")))(input)
         .map_err(|_: NomErr<CustomError<_>>| make_generic_nom_err_options(input, vec!["function call".to_string()]))?;
 
     tuple((
@@ -24,7 +24,7 @@ pub(super) fn function_call(input: &str) -> ResNew<&str, Expr> {
     ))(input).map(|(next, parts)| {
         let expr = match parts.1 {
             Some(arg_v) => {
-                let mut args: Vec<Expr> = arg_v.2.into_iter().map(|x| x.2.0).collect();
+                let mut args: Vec<Spanned<Expr>> = arg_v.2.into_iter().map(|x| x.2.0).collect();
                 args.insert(0, arg_v.0.0);
                  Expr::FunctionCall(ident_res.0, args)
             }
@@ -42,15 +42,15 @@ mod tests {
     fn test_function_call() {
         assert_eq!(function_call("foobar()"), nom_ok( Expr::FunctionCall("foobar".to_string(), vec![])));
         assert_eq!(function_call("foobar(\"hello\", true)"), nom_ok( Expr::FunctionCall("foobar".to_string(), vec![
-            Expr::Value(ValueType::String("hello".to_string())),
-            Expr::Value(ValueType::Bool(true)),
+            Expr::Value(ValueType::String("hello".to_string())).into(),
+            Expr::Value(ValueType::Bool(true)).into(),
         ])));
         assert_eq!(function_call("foobar(true == true)"), nom_ok( Expr::FunctionCall("foobar".to_string(), vec![
             nom_eval(expr("true == true"))
         ])));
 
         assert_eq!(function_call("print(variable)"), nom_ok( Expr::FunctionCall("print".to_string(), vec![
-            nom_eval(variable("variable"))
+            nom_eval(variable("variable")).into()
         ])));
     }
 }
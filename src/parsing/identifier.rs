@@ -9,13 +9,23 @@ pub(super) fn ident(input: &str) -> ResNew<&str, String> {
     };
 
     match id.0.as_ref() {
-        "break" | "continue" | "do" | "else" | "false" | "for" |
-        "if" | "in" | "let" | "loop" | "return" | "true" | "while"
+        "break" | "continue" | "do" | "else" | "export" | "false" | "for" |
+        "if" | "import" | "in" | "let" | "loop" | "return" | "true" | "while"
         => Err(make_generic_nom_err_new(input)),
         _ => Ok((rest, id)),
     }
 }
 
+/// An identifier that may be prefixed by the namespace of an import, i.e. `common.greet`.
+pub(super) fn qualified_ident(input: &str) -> ResNew<&str, String> {
+    let (input, (name, _)) = ident(input)?;
+
+    match tuple((tag_custom::<_, _, CustomError<_>>("."), ident))(input) {
+        Ok((next, (_, (member, _)))) => Ok((next, (format!("{}.{}", name, member), None))),
+        Err(_) => Ok((input, (name, None))),
+    }
+}
+
 pub(super) fn word(input: &str) -> ResNew<&str, String> {
     let (input, _) = ws0(input)?;
 
@@ -43,5 +53,7 @@ mod tests {
         assert_eq!(ident("_foobar"), nom_ok("_foobar".to_string()));
         assert_eq!(ident("btn_forward"), nom_ok("btn_forward".to_string()));
         assert_eq!(ident("foo.bar"), nom_ok_rest(".bar", "foo".to_string()));
+        assert_eq!(qualified_ident("foo.bar"), nom_ok("foo.bar".to_string()));
+        assert_eq!(qualified_ident("foo..bar"), nom_ok_rest("..bar", "foo".to_string()));
     }
 }
@@ -28,7 +28,7 @@ pub(super) fn if_stmt(input: &str) -> ResNew<&str, Stmt> {
         ))),
     ))(input)?;
     let mut last_err = Some(v.8.1);
-    let mut pairs: Vec<(Expr, Block)> = v.8.0.into_iter().map(|v| (v.7.0, v.11.0)).collect();
+    let mut pairs: Vec<(Spanned<Expr>, Block)> = v.8.0.into_iter().map(|v| (v.7.0, v.11.0)).collect();
     let first_pair = (v.3.0, v.7.0);
     pairs.insert(0, first_pair);
 
@@ -61,25 +61,25 @@ mod tests {
                    ], None,
                    )));
         assert_eq!(nom_no_last_err(stmt("if(true){ a::b; }")),
-                   nom_ok(Stmt::If(vec![
+                   nom_ok(Spanned::from(Stmt::If(vec![
                        (nom_eval(expr("true")), nom_eval(block("{a::b;}"))),
                    ], None,
-                   )));
+                   ))));
 
         assert_eq!(nom_no_last_err(stmt("if(\"a\" == \"a\"){ a::b; }")),
-                   nom_ok(Stmt::If(vec![
+                   nom_ok(Spanned::from(Stmt::If(vec![
                        (nom_eval(expr("\"a\" == \"a\"")), nom_eval(block("{a::b;}"))),
                    ], None,
-                   )));
+                   ))));
         assert_eq!(nom_no_last_err(stmt("if(foo() == \"a\"){ a::b; }")),
-                   nom_ok(Stmt::If(vec![
+                   nom_ok(Spanned::from(Stmt::If(vec![
                        (Expr::Eq(
-                           Box::new(Expr::FunctionCall("foo".to_string(), vec![])),
-                           Box::new(Expr::Value(ValueType::String("a".to_string()))),
-                       ),
+                           Box::new(Expr::FunctionCall("foo".to_string(), vec![]).into()),
+                           Box::new(Expr::Value(ValueType::String("a".to_string())).into()),
+                       ).into(),
                         nom_eval(block("{a::b;}"))),
                    ], None,
-                   )));
+                   ))));
     }
 
     #[test]
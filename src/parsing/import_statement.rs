@@ -0,0 +1,55 @@
+use super::*;
+
+/// `import "path";` and `import "path" as name;`
+pub(super) fn import_statement(input: &str) -> ResNew<&str, Stmt> {
+    let (input, _) = tag_custom("import")(input)?;
+
+    tuple((
+        ws0,
+        string_literal,
+        opt(tuple((ws1, tag_custom("as"), ws1, ident))),
+        ws0,
+        tag_custom(";"),
+    ))(input).map(|(next, (_, (path, _), namespace, _, _))| {
+        let namespace = namespace.map(|(_, _, _, (name, _))| name);
+        (next, (Stmt::Import(Import { path, namespace, module: None }), None))
+    })
+}
+
+/// `export let name = value;`
+pub(super) fn export_statement(input: &str) -> ResNew<&str, Stmt> {
+    let (input, _) = tag_custom("export")(input)?;
+
+    tuple((ws1, variable_initialization, ws0, tag_custom(";")))(input)
+        .map(|(next, (_, (init, last_err), _, _))| (next, (Stmt::Export(init), last_err)))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import() {
+        assert_eq!(import_statement("import \"common.m2\";"), nom_ok(Stmt::Import(Import {
+            path: "common.m2".to_string(),
+            namespace: None,
+            module: None,
+        })));
+        assert_eq!(import_statement("import \"../base layout.m2\" as base ;"), nom_ok(Stmt::Import(Import {
+            path: "../base layout.m2".to_string(),
+            namespace: Some("base".to_string()),
+            module: None,
+        })));
+        assert!(import_statement("import common;").is_err());
+        assert!(import_statement("import \"common.m2\" as;").is_err());
+    }
+
+    #[test]
+    fn test_export() {
+        assert_eq!(nom_no_last_err(export_statement("export let a = 1;")), nom_ok(Stmt::Export(
+            Expr::Init("a".to_string(), Box::new(Expr::Value(ValueType::Number(1.0)).into())),
+        )));
+        assert!(export_statement("export a = 1;").is_err());
+    }
+}
@@ -1,14 +1,19 @@
 use super::*;
 
 pub(super) fn key_flags(input: &str) -> ResNew<&str, KeyModifierFlags> {
-    many0(one_of("^!+#"))(input).and_then(|(next, val)| {
+    many0(tuple((opt(one_of("<>")), one_of("^!+#"))))(input).and_then(|(next, val)| {
         let mut flags = KeyModifierFlags::new();
-        for v in val {
+        for (side, v) in val {
+            let side = match side {
+                Some('<') => KeyModifierSide::Left,
+                Some('>') => KeyModifierSide::Right,
+                _ => KeyModifierSide::Any,
+            };
             match v {
-                '!' => { if !flags.alt { flags.alt(); } else { return Err(make_generic_nom_err_new(input)); } }
-                '^' => { if !flags.ctrl { flags.ctrl(); } else { return Err(make_generic_nom_err_new(input)); } }
-                '+' => { if !flags.shift { flags.shift(); } else { return Err(make_generic_nom_err_new(input)); } }
-                '#' => { if !flags.meta { flags.meta(); } else { return Err(make_generic_nom_err_new(input)); } }
+                '!' => { if !flags.alt { flags.alt(); flags.alt_side = side; } else { return Err(make_generic_nom_err_new(input)); } }
+                '^' => { if !flags.ctrl { flags.ctrl(); flags.ctrl_side = side; } else { return Err(make_generic_nom_err_new(input)); } }
+                '+' => { if !flags.shift { flags.shift(); flags.shift_side = side; } else { return Err(make_generic_nom_err_new(input)); } }
+                '#' => { if !flags.meta { flags.meta(); flags.meta_side = side; } else { return Err(make_generic_nom_err_new(input)); } }
                 _ => unreachable!()
             }
         };
@@ -110,5 +115,14 @@ mod tests {
                 v.meta();
             })));
         assert_eq!(key_flags("#a!"), nom_ok_rest("a!", KeyModifierFlags::new().tap_mut(|v| v.meta())));
+
+        assert_eq!(key_flags("<!>^"), nom_ok(KeyModifierFlags::new().tap_mut(|v| {
+            v.alt();
+            v.alt_side = KeyModifierSide::Left;
+            v.ctrl();
+            v.ctrl_side = KeyModifierSide::Right;
+        })));
+        assert_eq!(key_flags("<a"), nom_ok_rest("<a", KeyModifierFlags::new()));
+        assert!(matches!(key_flags("<!>!"), Err(..)));
     }
 }
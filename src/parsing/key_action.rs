@@ -17,28 +17,28 @@ impl ParsedKeyActionVecExt for Vec<ParsedKeyAction> {
         self.into_iter()
             .fold(vec![], |mut acc, v| match v {
                 ParsedKeyAction::KeyAction(action) => {
-                    if action.modifiers.ctrl { acc.push(KeyAction::new(*KEY_LEFT_CTRL, TYPE_DOWN)); }
-                    if action.modifiers.shift { acc.push(KeyAction::new(*KEY_LEFT_SHIFT, TYPE_DOWN)); }
-                    if action.modifiers.alt { acc.push(KeyAction::new(*KEY_LEFT_ALT, TYPE_DOWN)); }
-                    if action.modifiers.meta { acc.push(KeyAction::new(*KEY_LEFT_META, TYPE_DOWN)); }
+                    if action.modifiers.ctrl { acc.push(KeyAction::new(action.modifiers.ctrl_key(), TYPE_DOWN)); }
+                    if action.modifiers.shift { acc.push(KeyAction::new(action.modifiers.shift_key(), TYPE_DOWN)); }
+                    if action.modifiers.alt { acc.push(KeyAction::new(action.modifiers.alt_key(), TYPE_DOWN)); }
+                    if action.modifiers.meta { acc.push(KeyAction::new(action.modifiers.meta_key(), TYPE_DOWN)); }
                     acc.push(KeyAction::new(action.key, action.value));
-                    if action.modifiers.ctrl { acc.push(KeyAction::new(*KEY_LEFT_CTRL, TYPE_UP)); }
-                    if action.modifiers.shift { acc.push(KeyAction::new(*KEY_LEFT_SHIFT, TYPE_UP)); }
-                    if action.modifiers.alt { acc.push(KeyAction::new(*KEY_LEFT_ALT, TYPE_UP)); }
-                    if action.modifiers.meta { acc.push(KeyAction::new(*KEY_LEFT_META, TYPE_UP)); }
+                    if action.modifiers.ctrl { acc.push(KeyAction::new(action.modifiers.ctrl_key(), TYPE_UP)); }
+                    if action.modifiers.shift { acc.push(KeyAction::new(action.modifiers.shift_key(), TYPE_UP)); }
+                    if action.modifiers.alt { acc.push(KeyAction::new(action.modifiers.alt_key(), TYPE_UP)); }
+                    if action.modifiers.meta { acc.push(KeyAction::new(action.modifiers.meta_key(), TYPE_UP)); }
                     acc
                 }
                 ParsedKeyAction::KeyClickAction(action) => {
-                    if action.modifiers.ctrl { acc.push(KeyAction::new(*KEY_LEFT_CTRL, TYPE_DOWN)); }
-                    if action.modifiers.shift { acc.push(KeyAction::new(*KEY_LEFT_SHIFT, TYPE_DOWN)); }
-                    if action.modifiers.alt { acc.push(KeyAction::new(*KEY_LEFT_ALT, TYPE_DOWN)); }
-                    if action.modifiers.meta { acc.push(KeyAction::new(*KEY_LEFT_META, TYPE_DOWN)); }
+                    if action.modifiers.ctrl { acc.push(KeyAction::new(action.modifiers.ctrl_key(), TYPE_DOWN)); }
+                    if action.modifiers.shift { acc.push(KeyAction::new(action.modifiers.shift_key(), TYPE_DOWN)); }
+                    if action.modifiers.alt { acc.push(KeyAction::new(action.modifiers.alt_key(), TYPE_DOWN)); }
+                    if action.modifiers.meta { acc.push(KeyAction::new(action.modifiers.meta_key(), TYPE_DOWN)); }
                     acc.push(KeyAction::new(action.key, TYPE_DOWN));
                     acc.push(KeyAction::new(action.key, TYPE_UP));
-                    if action.modifiers.ctrl { acc.push(KeyAction::new(*KEY_LEFT_CTRL, TYPE_UP)); }
-                    if action.modifiers.shift { acc.push(KeyAction::new(*KEY_LEFT_SHIFT, TYPE_UP)); }
-                    if action.modifiers.alt { acc.push(KeyAction::new(*KEY_LEFT_ALT, TYPE_UP)); }
-                    if action.modifiers.meta { acc.push(KeyAction::new(*KEY_LEFT_META, TYPE_UP)); }
+                    if action.modifiers.ctrl { acc.push(KeyAction::new(action.modifiers.ctrl_key(), TYPE_UP)); }
+                    if action.modifiers.shift { acc.push(KeyAction::new(action.modifiers.shift_key(), TYPE_UP)); }
+                    if action.modifiers.alt { acc.push(KeyAction::new(action.modifiers.alt_key(), TYPE_UP)); }
+                    if action.modifiers.meta { acc.push(KeyAction::new(action.modifiers.meta_key(), TYPE_UP)); }
                     acc
                 }
             })
@@ -1,3 +1,5 @@
+use nom::combinator::map_res;
+
 use super::*;
 
 pub(super) fn key_mapping_inline(input: &str) -> ResNew<&str, Expr> {
@@ -31,7 +33,7 @@ pub(super) fn key_mapping_inline(input: &str) -> ResNew<&str, Expr> {
                     .tap_mut(|b| b.statements = to
                         .to_key_actions()
                         .into_iter()
-                        .map(|v| Stmt::Expr(Expr::KeyAction(v)))
+                        .map(|v| Stmt::Expr(Expr::KeyAction(v)).into())
                         .collect()),
                 )
             }
@@ -52,7 +54,7 @@ pub(super) fn key_mapping_inline(input: &str) -> ResNew<&str, Expr> {
                     .tap_mut(|b| b.statements = to
                         .to_key_actions()
                         .into_iter()
-                        .map(|v| Stmt::Expr(Expr::KeyAction(v)))
+                        .map(|v| Stmt::Expr(Expr::KeyAction(v)).into())
                         .collect()),
                 )
             }
@@ -80,9 +82,144 @@ pub(super) fn key_mapping(input: &str) -> ResNew<&str, Expr> {
     })
 }
 
+pub(super) fn key_mapping_combo(input: &str) -> ResNew<&str, Expr> {
+    tuple((
+        key,
+        many1(tuple((ws0, tag_custom("&"), ws0, key))),
+        tag_custom("::"),
+        ws0,
+        alt((
+            block,
+            map(key_sequence, |(to, last_err)| (key_actions_block(to), last_err)),
+            map(key_action_with_flags, |(to, last_err)| (key_actions_block(vec![to]), last_err)),
+        )),
+    ))(input).and_then(|(next, v)| {
+        let (first, rest, (to, last_err)) = (v.0.0, v.1, v.4);
+
+        let mut keys = vec![];
+        for (key, flags) in std::iter::once(first).chain(rest.into_iter().map(|(_, _, _, (key, _))| key)) {
+            if flags != KeyModifierFlags::new() || keys.contains(&key) {
+                return Err(make_generic_nom_err_options(input, vec!["combo key".to_string()]));
+            }
+            keys.push(key);
+        }
+
+        Ok((next, (Expr::Combo(keys, to), last_err)))
+    })
+}
+
+pub(super) fn key_mapping_sequence(input: &str) -> ResNew<&str, Expr> {
+    tuple((
+        key_sequence,
+        tag_custom("::"),
+        ws0,
+        alt((
+            block,
+            map(key_sequence, |(to, last_err)| (key_actions_block(to), last_err)),
+            map(key_action_with_flags, |(to, last_err)| (key_actions_block(vec![to]), last_err)),
+        )),
+    ))(input).and_then(|(next, v)| {
+        let (from, (to, last_err)) = (v.0.0, v.3);
+
+        let sequence = from.into_iter()
+            .map(|action| match action {
+                ParsedKeyAction::KeyClickAction(action) => Ok(action),
+                ParsedKeyAction::KeyAction(_) => Err(make_generic_nom_err_options(input, vec!["key sequence trigger".to_string()])),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((next, (Expr::SequenceMapping(sequence, to), last_err)))
+    })
+}
+
+pub(super) fn key_mapping_wheel(input: &str) -> ResNew<&str, Expr> {
+    tuple((
+        key_flags,
+        alt((tag("wheel_up"), tag("wheel_down"), tag("wheel_left"), tag("wheel_right"))),
+        tag_custom("::"),
+        ws0,
+        alt((
+            block,
+            map(key_sequence, |(to, last_err)| (key_actions_block(to), last_err)),
+            map(key_action_with_flags, |(to, last_err)| (key_actions_block(vec![to]), last_err)),
+        )),
+    ))(input).map(|(next, v)| {
+        let (modifiers, (key, value), (to, last_err)) = (v.0.0, wheel_trigger(v.1).unwrap(), v.4);
+        (next, (Expr::map_key_block(KeyActionWithMods::new(key, value, modifiers), to), last_err))
+    })
+}
+
+fn key_actions_block(actions: Vec<ParsedKeyAction>) -> Block {
+    Block::new().tap_mut(|b| b.statements = actions
+        .to_key_actions()
+        .into_iter()
+        .map(|v| Stmt::Expr(Expr::KeyAction(v)).into())
+        .collect())
+}
+
+enum TapHoldOption {
+    TappingTerm(u64),
+    PermissiveHold,
+    HoldOnOtherKeyPress,
+}
+
+fn tap_hold_option(input: &str) -> ResNew<&str, TapHoldOption> {
+    alt((
+        map(map_res(digit1, |v: &str| v.parse::<u64>()), |v| (TapHoldOption::TappingTerm(v), None)),
+        map(tag_custom("permissive_hold"), |_| (TapHoldOption::PermissiveHold, None)),
+        map(tag_custom("hold_on_other_key_press"), |_| (TapHoldOption::HoldOnOtherKeyPress, None)),
+    ))(input)
+        .map_err(|_: NomErr<CustomError<_>>| make_generic_nom_err_options(input, vec!["tap-hold option".to_string()]))
+}
+
+pub(super) fn key_mapping_tap_hold(input: &str) -> ResNew<&str, Expr> {
+    tuple((
+        key_action,
+        tag_custom("::"),
+        ws0,
+        tag_custom("tap_hold"),
+        ws0,
+        tag_custom("("),
+        ws0,
+        key_action_with_flags,
+        ws0,
+        tag_custom(","),
+        ws0,
+        key_action_with_flags,
+        many0(tuple((ws0, tag_custom(","), ws0, tap_hold_option))),
+        ws0,
+        tag_custom(")"),
+    ))(input).and_then(|(next, v)| {
+        let (trigger, tap, hold, options) = (v.0.0, v.7.0, v.11.0, v.12);
+
+        let trigger = match trigger {
+            ParsedKeyAction::KeyClickAction(trigger) if trigger.modifiers == KeyModifierFlags::new() => trigger.key,
+            _ => return Err(make_generic_nom_err_options(input, vec!["tap-hold trigger key".to_string()])),
+        };
+
+        let (tap, hold) = match (tap, hold) {
+            (ParsedKeyAction::KeyClickAction(tap), ParsedKeyAction::KeyClickAction(hold)) => (tap, hold),
+            _ => return Err(make_generic_nom_err_options(input, vec!["tap-hold target key".to_string()])),
+        };
+
+        let mut mapping = TapHoldMapping::new(trigger, tap, hold);
+        for (_, _, _, (option, _)) in options {
+            match option {
+                TapHoldOption::TappingTerm(millis) => mapping.tapping_term = time::Duration::from_millis(millis),
+                TapHoldOption::PermissiveHold => mapping.permissive_hold = true,
+                TapHoldOption::HoldOnOtherKeyPress => mapping.hold_on_other_key_press = true,
+            }
+        }
+
+        Ok((next, (Expr::TapHold(mapping), None)))
+    })
+}
+
 
 #[cfg(test)]
 mod tests {
+    use evdev_rs::enums::EV_REL;
+
     use super::*;
 
     #[test]
@@ -113,11 +250,11 @@ mod tests {
                 from: KeyActionWithMods::new(*KEY_A, TYPE_DOWN, KeyModifierFlags::new()),
                 to: Block::new().tap_mut(|b| {
                     b.statements = vec![
-                        Stmt::Expr(Expr::ReleaseRestoreModifiers(KeyModifierFlags::new(), KeyModifierFlags::new(), 0)),
-                        Stmt::Expr(Expr::KeyAction(KeyAction::new(*KEY_A, TYPE_DOWN))),
-                        Stmt::Expr(Expr::KeyAction(KeyAction::new(*KEY_A, TYPE_UP))),
-                        Stmt::Expr(Expr::KeyAction(KeyAction::new(*KEY_B, TYPE_DOWN))),
-                        Stmt::Expr(Expr::KeyAction(KeyAction::new(*KEY_B, TYPE_UP))),
+                        Stmt::Expr(Expr::ReleaseRestoreModifiers(KeyModifierFlags::new(), KeyModifierFlags::new(), 0)).into(),
+                        Stmt::Expr(Expr::KeyAction(KeyAction::new(*KEY_A, TYPE_DOWN))).into(),
+                        Stmt::Expr(Expr::KeyAction(KeyAction::new(*KEY_A, TYPE_UP))).into(),
+                        Stmt::Expr(Expr::KeyAction(KeyAction::new(*KEY_B, TYPE_DOWN))).into(),
+                        Stmt::Expr(Expr::KeyAction(KeyAction::new(*KEY_B, TYPE_UP))).into(),
                     ];
                 }),
             },
@@ -126,6 +263,88 @@ mod tests {
         ])));
     }
 
+    #[test]
+    fn test_key_mapping_combo() {
+        assert_eq!(key_mapping_combo("j & k::esc"), nom_ok(Expr::Combo(
+            vec![*KEY_J, *KEY_K],
+            Block::new().tap_mut(|b| {
+                b.push_expr(Expr::KeyAction(KeyAction::new(*KEY_ESC, TYPE_DOWN)))
+                    .push_expr(Expr::KeyAction(KeyAction::new(*KEY_ESC, TYPE_UP)));
+            }),
+        )));
+
+        assert_eq!(key_mapping_combo("a&b & c::{}"), nom_ok(Expr::Combo(
+            vec![*KEY_A, *KEY_B, *KEY_C],
+            Block::new(),
+        )));
+
+        assert!(matches!(key_mapping_combo("a::b"), Err(..)));
+        assert!(matches!(key_mapping_combo("a & a::b"), Err(..)));
+        assert!(matches!(key_mapping_combo("A & b::c"), Err(..)));
+    }
+
+    #[test]
+    fn test_key_mapping_sequence() {
+        assert_eq!(key_mapping_sequence("\"{capslock}gs\"::{}"), nom_ok(Expr::SequenceMapping(
+            vec![
+                KeyClickActionWithMods::new(*KEY_CAPSLOCK),
+                KeyClickActionWithMods::new(*KEY_G),
+                KeyClickActionWithMods::new(*KEY_S),
+            ],
+            Block::new(),
+        )));
+
+        assert_eq!(key_mapping_sequence("\"gG\"::\"a\""), nom_ok(Expr::SequenceMapping(
+            vec![
+                KeyClickActionWithMods::new(*KEY_G),
+                KeyClickActionWithMods::new_with_mods(*KEY_G, KeyModifierFlags::new().tap_mut(|v| v.shift())),
+            ],
+            Block::new().tap_mut(|b| {
+                b.push_expr(Expr::KeyAction(KeyAction::new(*KEY_A, TYPE_DOWN)))
+                    .push_expr(Expr::KeyAction(KeyAction::new(*KEY_A, TYPE_UP)));
+            }),
+        )));
+
+        assert!(matches!(key_mapping_sequence("\"a{b down}\"::{}"), Err(..)));
+    }
+
+    #[test]
+    fn test_key_mapping_wheel() {
+        assert_eq!(key_mapping_wheel("^wheel_up::{}"), nom_ok(Expr::map_key_block(
+            KeyActionWithMods::new(rel_key(EV_REL::REL_WHEEL), 1, KeyModifierFlags::new().tap_mut(|v| v.ctrl())),
+            Block::new(),
+        )));
+
+        assert_eq!(key_mapping_wheel("wheel_left::a"), nom_ok(Expr::map_key_block(
+            KeyActionWithMods::new(rel_key(EV_REL::REL_HWHEEL), -1, KeyModifierFlags::new()),
+            Block::new().tap_mut(|b| {
+                b.push_expr(Expr::KeyAction(KeyAction::new(*KEY_A, TYPE_DOWN)))
+                    .push_expr(Expr::KeyAction(KeyAction::new(*KEY_A, TYPE_UP)));
+            }),
+        )));
+    }
+
+    #[test]
+    fn test_key_mapping_tap_hold() {
+        assert_eq!(key_mapping_tap_hold("capslock::tap_hold(esc, ctrl)"), nom_ok(Expr::TapHold(
+            TapHoldMapping::new(*KEY_CAPSLOCK, KeyClickActionWithMods::new(*KEY_ESC), KeyClickActionWithMods::new(*KEY_LEFT_CTRL)),
+        )));
+
+        assert_eq!(key_mapping_tap_hold("a::tap_hold(a, +b, 150, permissive_hold)"), nom_ok(Expr::TapHold(
+            TapHoldMapping::new(
+                *KEY_A,
+                KeyClickActionWithMods::new(*KEY_A),
+                KeyClickActionWithMods::new_with_mods(*KEY_B, KeyModifierFlags::new().tap_mut(|v| v.shift())),
+            ).tap_mut(|v| {
+                v.tapping_term = time::Duration::from_millis(150);
+                v.permissive_hold = true;
+            }),
+        )));
+
+        assert!(matches!(key_mapping_tap_hold("A::tap_hold(a, b)"), Err(..)));
+        assert!(matches!(key_mapping_tap_hold("a::tap_hold({a down}, b)"), Err(..)));
+    }
+
     #[test]
     fn test_key_mapping_complex() {
         // TODO add when implemented
@@ -1,47 +1,101 @@
 use itertools::Itertools;
-use nom::combinator::{map_res, recognize};
+use nom::combinator::{map_res, not, recognize};
 use nom::multi::many1;
 
 use super::*;
 
+/// `\{`, `\}`, `\"` and `\\` type the character itself, `\n` and `\t` type enter and tab.
+fn key_sequence_escape(input: &str) -> ResNew<&str, ParsedKeyAction> {
+    let (next, escaped) = preceded(tag_custom("\\"), one_of("{}\"\\nt"))(input)?;
+    let key_name = match escaped {
+        'n' => "enter",
+        't' => "tab",
+        '{' => "{",
+        '}' => "}",
+        '"' => "\"",
+        _ => "\\",
+    };
+
+    let (_, (action, _)) = key_action(key_name).map_err(|_| make_generic_nom_err_new(input))?;
+    Ok((next, (action, None)))
+}
+
+fn key_sequence_item(input: &str) -> IResult<&str, (&str, (ParsedKeyAction, Option<CustomError<&str>>)), CustomError<&str>> {
+    alt((
+        map(key_sequence_escape, |action| ("", action)),
+        map_res(
+            recognize(tuple((
+                                tag_custom("{"),
+                                terminated(take_until("}"), tag_custom("}"))),
+            )),
+            |input| {
+                let (input, action) = key_action(input)?;
+                // TODO properly propagate child error
+                if !input.is_empty() {
+                    return Err(make_generic_nom_err_new(input));
+                }
+
+                Ok((input, action))
+            },
+        ),
+        map_res(take(1usize), key_action),
+    ))(input)
+}
+
+fn collect_key_actions<'a>(input: &'a str, items: Vec<(&'a str, (ParsedKeyAction, Option<CustomError<&'a str>>))>)
+                           -> Result<Vec<ParsedKeyAction>, NomErr<CustomError<&'a str>>> {
+    items.into_iter()
+        .map(|v| {
+            if !v.0.is_empty() { return Err(make_generic_nom_err_new(input)); }
+            Ok(v.1.0)
+        })
+        .fold_ok(vec![], |mut acc, v| {
+            acc.push(v);
+            acc
+        })
+}
+
 pub(super) fn key_sequence(input: &str) -> ResNew<&str, Vec<ParsedKeyAction>> {
     tuple((
         tag_custom("\""),
-        many1(
-            alt((
-                map_res(
-                    recognize(tuple((
-                                        tag_custom("{"),
-                                        terminated(take_until("}"), tag_custom("}"))),
-                    )),
-                    |input| {
-                        let (input, action) = key_action(input)?;
-                        // TODO properly propagate child error
-                        if !input.is_empty() {
-                            return Err(make_generic_nom_err_new(input));
-                        }
-
-                        Ok((input, action))
-                    },
-                ),
-                map_res(take(1usize), key_action),
-            )),
-        ),
+        many1(preceded(not(tag("\"")), key_sequence_item)),
         tag_custom("\""),
     ))(input).and_then(|(next, val)| {
-        let seq = val.1.into_iter()
-            .map(|v| {
-                if !v.0.is_empty() { return Err(make_generic_nom_err_new(input)); }
-                Ok(v.1.0)
-            })
-            .fold_ok(vec![], |mut acc, v| {
-                acc.push(v);
-                acc
-            })?;
+        let seq = collect_key_actions(input, val.1)?;
+        Ok((next, (seq, None)))
+    })
+}
+
+/// A key sequence without the surrounding quotes, i.e. a string passed to `send`, quotes are typed as they are.
+pub(super) fn unquoted_key_sequence(input: &str) -> ResNew<&str, Vec<ParsedKeyAction>> {
+    many1(key_sequence_item)(input).and_then(|(next, items)| {
+        let seq = collect_key_actions(input, items)?;
         Ok((next, (seq, None)))
     })
 }
 
+/// The `{...}` groups of a key sequence that don't describe a key action, they are typed character by character.
+pub(crate) fn unknown_key_groups(input: &str) -> Vec<&str> {
+    let mut groups = vec![];
+    let mut rest = input;
+    while let Some(ch) = rest.chars().next() {
+        if let Ok((next, _)) = key_sequence_escape(rest) {
+            rest = next;
+            continue;
+        }
+
+        match rest.find('}') {
+            Some(end) if ch == '{' => {
+                let group = &rest[..=end];
+                if !matches!(key_action(group), Ok(("", _))) { groups.push(group); }
+                rest = &rest[end + 1..];
+            }
+            _ => rest = &rest[ch.len_utf8()..],
+        }
+    }
+    groups
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -74,4 +128,34 @@ mod tests {
             ParsedKeyAction::KeyAction(KeyActionWithMods::new(*KEY_LEFT_SHIFT, TYPE_UP, KeyModifierFlags::new())),
         ]));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_key_sequence_escapes() {
+        let shifted = |key: Key| ParsedKeyAction::KeyClickAction(KeyClickActionWithMods::new_with_mods(key, KeyModifierFlags::new().tap_mut(|f| { f.shift(); })));
+        let key = |name: &str| Key::from_str(&EventType::EV_KEY, name).unwrap();
+
+        assert_eq!(key_sequence(r#""\{\}\"\\\n""#), nom_ok(vec![
+            shifted(key("KEY_LEFTBRACE")),
+            shifted(key("KEY_RIGHTBRACE")),
+            shifted(key("KEY_APOSTROPHE")),
+            ParsedKeyAction::KeyClickAction(KeyClickActionWithMods::new(key("KEY_BACKSLASH"))),
+            ParsedKeyAction::KeyClickAction(KeyClickActionWithMods::new(key("KEY_ENTER"))),
+        ]));
+
+        // quotes only end quoted sequences
+        assert_eq!(key_sequence(r#""a"b""#), nom_ok_rest(r#"b""#, vec![
+            ParsedKeyAction::KeyClickAction(KeyClickActionWithMods::new(*KEY_A)),
+        ]));
+        assert_eq!(unquoted_key_sequence(r#"a"\{"#), nom_ok(vec![
+            ParsedKeyAction::KeyClickAction(KeyClickActionWithMods::new(*KEY_A)),
+            shifted(key("KEY_APOSTROPHE")),
+            shifted(key("KEY_LEFTBRACE")),
+        ]));
+    }
+
+    #[test]
+    fn test_unknown_key_groups() {
+        assert_eq!(unknown_key_groups("a{shift down}{enter}\\{b}"), Vec::<&str>::new());
+        assert_eq!(unknown_key_groups("{entr}ä{shift dwn}{"), vec!["{entr}", "{shift dwn}"]);
+    }
+}
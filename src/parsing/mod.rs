@@ -7,10 +7,12 @@ use nom::character::complete::*;
 use nom::combinator::{map, opt};
 use nom::error::{ParseError};
 use nom::IResult;
-use nom::multi::many0;
+use nom::multi::{many0, many1};
 use nom::sequence::*;
 use tap::Tap;
 
+use break_statement::*;
+use collection::*;
 use continue_statement::*;
 use custom_combinators::*;
 use error::*;
@@ -19,6 +21,7 @@ use for_loop::*;
 use function::*;
 use identifier::*;
 use if_statement::*;
+use import_statement::*;
 use key::*;
 use key_action::*;
 use key_mapping::*;
@@ -26,43 +29,57 @@ use key_sequence::*;
 use lambda::*;
 use primitives::*;
 use return_statement::*;
+use span::*;
 #[cfg(test)]
 use tests::*;
 use variable::*;
+use while_loop::*;
 
 use crate::*;
 
+pub mod formatter;
 pub mod parser;
+pub mod span;
 mod return_statement;
 mod continue_statement;
+mod break_statement;
+mod collection;
 mod custom_combinators;
 mod expression;
 mod function;
 mod identifier;
 mod if_statement;
+mod import_statement;
 mod key;
 mod key_action;
 mod key_mapping;
-mod key_sequence;
+pub(crate) mod key_sequence;
 mod lambda;
 mod primitives;
 mod variable;
 mod for_loop;
+mod while_loop;
 mod error;
 
 
-fn stmt(input: &str) -> ResNew<&str, Stmt> {
-    alt((
+fn stmt(input: &str) -> ResNew<&str, Spanned<Stmt>> {
+    spanned(alt((
         return_statement,
         continue_statement,
+        import_statement,
+        export_statement,
+        break_statement,
         if_stmt,
         for_loop,
+        for_in_loop,
+        while_loop,
+        loop_stmt,
         map(
             tuple((expr, tag_custom(";"))),
-            |(v, _)| (Stmt::Expr(v.0), v.1),
+            |(v, _)| (Stmt::Expr(v.0.node), v.1),
         ),
         map(block, |(block, last_err)| (Stmt::Block(block), last_err)),
-    ))(input)
+    )))(input)
 }
 
 fn block_body(input: &str) -> ResNew<&str, Block> {
@@ -81,7 +98,7 @@ fn block_body(input: &str) -> ResNew<&str, Block> {
     }
 
     let block = Block::new().tap_mut(|b| {
-        let mut statements: Vec<Stmt> = pairs.into_iter().map(|x| x.1.0).collect();
+        let mut statements: Vec<Spanned<Stmt>> = pairs.into_iter().map(|x| x.1.0).collect();
         statements.insert(0, first_stmt);
         b.statements = statements;
     });
@@ -204,7 +221,7 @@ mod tests {
 
         assert_eq!(nom_no_last_err(block_body("if(true){a::b;}")),
                    nom_ok(Block::new().tap_mut(|b| {
-                       b.statements = vec![nom_eval(if_stmt("if(true){a::b;}"))];
+                       b.statements = vec![nom_eval(if_stmt("if(true){a::b;}")).into()];
                    })));
     }
 }
\ No newline at end of file
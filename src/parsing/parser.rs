@@ -1,46 +1,63 @@
 
-use super::*;
+use crate::script::ScriptFile;
 
-pub(crate) fn parse_script<>(raw_script: &str) -> Result<Block> {
-    match global_block(raw_script) {
-        Ok((v, (block, last_err))) => {
-            if v.is_empty() {
-                Ok(block)
-            } else {
-                if let Some(last_err) = last_err {
-                    let err = convert_custom_error(raw_script, &last_err);
-                    eprintln!("{}", &*err);
-                } else {
-                    eprintln!("generic error");
-                }
-
-                Err(anyhow!("parsing failed unwrapping..."))
-                // Err(anyhow!("parsing failed, remaining input:\n'{}'\n", v))
-            }
-        }
-        Err(err) => {
-            if let NomErr::Error(err) = &err {
-                let err = convert_custom_error(raw_script, err);
-                eprintln!("{}", &*err);
-            }
+use super::*;
 
-            Err(anyhow!("parsing failed: {}", err))
+/// Parses a whole script, errors are rendered with the part of the source they point to.
+///
+/// `file` is the index of the script among the loaded scripts, the spans of the script refer to it.
+pub(crate) fn parse_script(script: &ScriptFile, file: usize) -> Result<Block> {
+    let raw_script = &*script.source;
+    let path = script.path.display().to_string();
+    match span::with_source(raw_script, file, || global_block(raw_script)) {
+        Ok(("", (block, _))) => Ok(block),
+        Ok((rest, (_, last_err))) => {
+            let err = last_err.unwrap_or(CustomError { input: rest, expected: vec![] });
+            Err(anyhow!("{}", convert_custom_error(raw_script, Some(&path), &err)))
         }
+        Err(NomErr::Error(err)) | Err(NomErr::Failure(err)) => Err(anyhow!("{}", convert_custom_error(raw_script, Some(&path), &err))),
+        Err(NomErr::Incomplete(_)) => Err(anyhow!("error: {}: unexpected end of input", path)),
     }
 }
 
 pub(crate) fn parse_key_sequence(raw: &str) -> Result<Vec<KeyAction>> {
-    // TODO remove this workaround (allow seq to be parsed without quotes)
-    let raw = format!("\"{}\"", raw);
-    match key_sequence(&raw) {
+    match unquoted_key_sequence(raw) {
         Ok(v) => {
             if v.0.is_empty() {
                 Ok(v.1.0.to_key_actions())
             } else {
-                Err(anyhow!("parsing failed, remaining input:\n'{}'\n", v.0))
+                Err(anyhow!("invalid key sequence '{}': unexpected input '{}'", raw, v.0))
             }
         }
-        Err(err) => Err(anyhow!("parsing failed: {}", err))
+        Err(NomErr::Error(err)) | Err(NomErr::Failure(err)) =>
+            Err(anyhow!("invalid key sequence '{}': {}", raw, expected_message(&err))),
+        Err(err) => Err(anyhow!("invalid key sequence '{}': {}", raw, err)),
+    }
+}
+
+pub(crate) fn parse_key(raw: &str) -> Result<Key> {
+    match key(raw) {
+        Ok(("", ((key, flags), _))) if flags == KeyModifierFlags::new() => Ok(key),
+        _ => Err(anyhow!("failed to parse key '{}'", raw)),
+    }
+}
+
+/// Parses a mapping trigger into the key actions the mapping is registered for.
+pub(crate) fn parse_mapping_trigger(raw: &str) -> Result<Vec<KeyActionWithMods>> {
+    if let Ok((name, (modifiers, _))) = key_flags(raw) {
+        if let Some((key, value)) = wheel_trigger(name) {
+            return Ok(vec![KeyActionWithMods::new(key, value, modifiers)]);
+        }
+    }
+
+    match key_action_with_flags(raw) {
+        Ok(("", (ParsedKeyAction::KeyClickAction(action), _))) => Ok(vec![
+            action.to_key_action(TYPE_DOWN),
+            action.to_key_action(TYPE_REPEAT),
+            action.to_key_action(TYPE_UP),
+        ]),
+        Ok(("", (ParsedKeyAction::KeyAction(action), _))) => Ok(vec![action]),
+        _ => Err(anyhow!("failed to parse mapping trigger '{}'", raw)),
     }
 }
 
@@ -60,8 +77,29 @@ pub(crate) fn parse_key_action_with_mods(from: &str, to: Block) -> Result<Expr>
 
 #[cfg(test)]
 mod tests {
+    use evdev_rs::enums::EV_REL;
+
     use super::*;
 
+    #[test]
+    fn test_mapping_trigger() {
+        assert_eq!(parse_mapping_trigger("^a").unwrap(), vec![
+            KeyActionWithMods::new(*KEY_A, TYPE_DOWN, KeyModifierFlags::new().tap_mut(|f| f.ctrl())),
+            KeyActionWithMods::new(*KEY_A, TYPE_REPEAT, KeyModifierFlags::new().tap_mut(|f| f.ctrl())),
+            KeyActionWithMods::new(*KEY_A, TYPE_UP, KeyModifierFlags::new().tap_mut(|f| f.ctrl())),
+        ]);
+        assert_eq!(parse_mapping_trigger("{b up}").unwrap(), vec![KeyActionWithMods::new(*KEY_B, TYPE_UP, KeyModifierFlags::new())]);
+        assert_eq!(parse_mapping_trigger("wheel_down").unwrap(), vec![KeyActionWithMods::new(rel_key(EV_REL::REL_WHEEL), -1, KeyModifierFlags::new())]);
+        assert!(parse_mapping_trigger("a b").is_err());
+    }
+
+    #[test]
+    fn test_script_errors() {
+        let script = ScriptFile { path: "layout.m2".into(), source: "let a = 1;\nif (a == 1 {\n}\n".to_string() };
+        let err = parse_script(&script, 0).unwrap_err();
+        assert_eq!(err.to_string(), "error: expected ')'\n --> layout.m2, line 2, column 12\n  |\n2 | if (a == 1 {\n  |            ^");
+    }
+
     #[test]
     fn test_key_sequence() {
         assert_eq!(parse_key_sequence("hello{enter}world").unwrap(),
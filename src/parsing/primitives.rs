@@ -2,12 +2,90 @@ use nom::number::complete::double;
 
 use super::*;
 
+/// Parses the escape sequence at the start of the input.
+///
+/// Unknown escapes (i.e. `\{`) are kept as they are, key sequences give them a meaning of their own.
+fn escape_sequence(input: &str) -> Result<(&str, String), NomErr<CustomError<&str>>> {
+    let escaped = &input[1..];
+    let ch = match escaped.chars().next() {
+        Some(ch) => ch,
+        None => return Err(make_generic_nom_err_options(escaped, vec!["escape sequence".to_string()])),
+    };
+
+    let value = match ch {
+        '"' => "\"".to_string(),
+        '\\' => "\\".to_string(),
+        'n' => "\n".to_string(),
+        't' => "\t".to_string(),
+        '$' => "$".to_string(),
+        'u' => {
+            let (next, (_, code, _)) = tuple((tag_custom("{"), hex_digit1, tag_custom("}")))(&escaped[1..])
+                .map_err(|_: NomErr<CustomError<_>>| make_generic_nom_err_options(&escaped[1..], vec!["unicode escape".to_string()]))?;
+
+            return u32::from_str_radix(code, 16).ok()
+                .and_then(char::from_u32)
+                .map(|ch| (next, ch.to_string()))
+                .ok_or_else(|| make_generic_nom_err_options(&escaped[1..], vec!["valid unicode code point".to_string()]));
+        }
+        ch => format!("\\{}", ch),
+    };
+    Ok((&escaped[ch.len_utf8()..], value))
+}
+
+/// Parses a quoted string into its text and `${expr}` parts, the flag is set if the string contains interpolations.
+fn string_parts(input: &str) -> ResNew<&str, (Vec<Spanned<Expr>>, bool)> {
+    let (mut rest, _) = tag_custom("\"")(input)
+        .map_err(|_: NomErr<CustomError<_>>| make_generic_nom_err_options(input, vec!["string".to_string()]))?;
+
+    let mut parts = vec![];
+    let mut interpolated = false;
+    let mut text = String::new();
+    loop {
+        match rest.chars().next() {
+            None => return Err(make_generic_nom_err_options(rest, vec!["'\"'".to_string()])),
+            Some('"') => break,
+            Some('\\') => {
+                let (next, value) = escape_sequence(rest)?;
+                text.push_str(&value);
+                rest = next;
+            }
+            Some('$') if rest.starts_with("${") => {
+                if !text.is_empty() {
+                    parts.push(Expr::Value(ValueType::String(std::mem::take(&mut text))).into());
+                }
+                let (next, (_, _, (value, _), _, _)) = tuple((tag_custom("${"), ws0, expr, ws0, tag_custom("}")))(rest)?;
+                parts.push(value);
+                interpolated = true;
+                rest = next;
+            }
+            Some(ch) => {
+                text.push(ch);
+                rest = &rest[ch.len_utf8()..];
+            }
+        }
+    }
+
+    if !text.is_empty() || parts.is_empty() {
+        parts.push(Expr::Value(ValueType::String(text)).into());
+    }
+    Ok((&rest[1..], ((parts, interpolated), None)))
+}
+
+/// A string without interpolation, i.e. a map key.
+pub(super) fn string_literal(input: &str) -> ResNew<&str, String> {
+    match string_parts(input)? {
+        (next, ((mut parts, false), _)) => match parts.pop().map(|part| part.node) {
+            Some(Expr::Value(ValueType::String(value))) => Ok((next, (value, None))),
+            _ => unreachable!(),
+        },
+        _ => Err(make_generic_nom_err_options(input, vec!["string without interpolation".to_string()])),
+    }
+}
+
 pub(super) fn string(input: &str) -> ResNew<&str, Expr> {
-    tuple((tag_custom("\""), take_until("\""), tag_custom("\"")))(input)
-        .map_err(|_: NomErr<CustomError<_>>| make_generic_nom_err_options(input, vec!["string".to_string()]))
-        .map(|(next, v)|
-            (next, (Expr::Value(ValueType::String(v.1.to_string())), None))
-        )
+    let (next, ((mut parts, interpolated), _)) = string_parts(input)?;
+    let expr = if interpolated { Expr::Interpolation(parts) } else { parts.pop().unwrap().node };
+    Ok((next, (expr, None)))
 }
 
 pub(super) fn boolean(input: &str) -> ResNew<&str, Expr> {
@@ -29,6 +107,11 @@ pub(super) fn number(input: &str) -> ResNew<&str, Expr> {
         .map_err(|_: NomErr<CustomError<_>>| make_generic_nom_err_options(input, vec!["number".to_string()]))
         .map(|(next, v)|
             {
+                // "0..5" is a range rather than "0." followed by ".5"
+                if next.starts_with('.') && input[..input.len() - next.len()].ends_with('.') {
+                    return (&input[input.len() - next.len() - 1..], (Expr::Value(ValueType::Number(v)), None));
+                }
+
                 let expr = Expr::Value(ValueType::Number(v));
                 (next, (expr, None))
             }
@@ -48,9 +131,44 @@ mod tests {
         assert_eq!(string("\"hello world\""), nom_ok(Expr::Value(ValueType::String("hello world".to_string()))));
     }
 
+    #[test]
+    fn test_string_escapes() {
+        let text = |value: &str| Expr::Value(ValueType::String(value.to_string()));
+
+        assert_eq!(string(r#""say \"hi\"\n\t\\""#), nom_ok(text("say \"hi\"\n\t\\")));
+        assert_eq!(string(r#""\u{48}\u{1F600}""#), nom_ok(text("H\u{1F600}")));
+        assert_eq!(string(r#""\{enter\} \$""#), nom_ok(text("\\{enter\\} $")));
+        assert_eq!(string(r#""a" + "b""#), nom_ok_rest(r#" + "b""#, text("a")));
+        assert_eq!(string(r#""""#), nom_ok(text("")));
+
+        assert!(string(r#""\u{110000}""#).is_err());
+        assert!(string(r#""\u{zz}""#).is_err());
+        assert!(string(r#""unterminated"#).is_err());
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        assert_eq!(nom_eval(string(r#""a is ${a + 1}!""#)), Expr::Interpolation(vec![
+            Expr::Value(ValueType::String("a is ".to_string())).into(),
+            Expr::Add(
+                Box::new(Expr::Name("a".to_string()).into()),
+                Box::new(Expr::Value(ValueType::Number(1.0)).into()),
+            ).into(),
+            Expr::Value(ValueType::String("!".to_string())).into(),
+        ]));
+        assert_eq!(nom_eval(string(r#""${"}"}""#)), Expr::Interpolation(vec![
+            Expr::Value(ValueType::String("}".to_string())).into(),
+        ]));
+        assert_eq!(nom_eval(string(r#""\${a}""#)), Expr::Value(ValueType::String("${a}".to_string())));
+
+        assert!(string_literal(r#""${a}""#).is_err());
+        assert!(string(r#""${a""#).is_err());
+    }
+
     #[test]
     fn test_number() {
         assert_eq!(number("42"), nom_ok(Expr::Value(ValueType::Number(42.0))));
         assert_eq!(number("-42.5"), nom_ok( Expr::Value(ValueType::Number(-42.5))));
+        assert_eq!(number("0..5"), nom_ok_rest("..5", Expr::Value(ValueType::Number(0.0))));
     }
 }
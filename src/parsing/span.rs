@@ -0,0 +1,181 @@
+use std::cell::RefCell;
+use std::fmt;
+
+use super::*;
+
+/// The script that is currently being parsed, used to turn parser input slices into source positions.
+struct SourceInfo {
+    address: usize,
+    len: usize,
+    file: usize,
+    /// byte offsets at which lines start
+    line_starts: Vec<usize>,
+}
+
+thread_local! {
+    static SOURCE: RefCell<Option<SourceInfo>> = RefCell::new(None);
+}
+
+fn line_starts(source: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(idx, _)| idx + 1))
+        .collect()
+}
+
+/// Registers the source for the duration of the parse, spans of parsers that run outside of it stay unknown.
+///
+/// `file` is the index of the script among the loaded scripts, see [`crate::script::load_script`].
+pub(super) fn with_source<T>(source: &str, file: usize, parse: impl FnOnce() -> T) -> T {
+    let line_starts = line_starts(source);
+    SOURCE.with(|s| *s.borrow_mut() = Some(SourceInfo { address: source.as_ptr() as usize, len: source.len(), file, line_starts }));
+    let res = parse();
+    SOURCE.with(|s| *s.borrow_mut() = None);
+    res
+}
+
+/// A region of the script source, lines and columns start at 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    /// 0 if the position is unknown, i.e. for code generated at runtime
+    pub line: usize,
+    pub column: usize,
+    /// the script the span belongs to, 0 is the main script
+    pub file: usize,
+}
+
+impl Span {
+    /// Creates the span between two slices of the registered source, `rest` is the input left after parsing.
+    pub(super) fn between(input: &str, rest: &str) -> Self {
+        SOURCE.with(|source| {
+            let source = source.borrow();
+            let source = match source.as_ref() {
+                Some(source) => source,
+                None => return Span::default(),
+            };
+
+            let offset = |slice: &str| (slice.as_ptr() as usize).checked_sub(source.address)
+                .filter(|offset| *offset <= source.len);
+            let (start, end) = match (offset(input), offset(rest)) {
+                (Some(start), Some(end)) => (start, end),
+                _ => return Span::default(),
+            };
+
+            Span { file: source.file, ..Span::from_offsets(&source.line_starts, start, end) }
+        })
+    }
+
+    /// Creates the span of a byte range in the given source.
+    pub fn at(source: &str, start: usize, end: usize) -> Self {
+        Span::from_offsets(&line_starts(source), start, end)
+    }
+
+    fn from_offsets(line_starts: &[usize], start: usize, end: usize) -> Self {
+        let line_idx = line_starts.partition_point(|line_start| *line_start <= start) - 1;
+        Span { start, end, line: line_idx + 1, column: start - line_starts[line_idx] + 1, file: 0 }
+    }
+
+    /// Creates the span that covers both spans, `self` has to come first.
+    pub fn to(&self, other: &Span) -> Self {
+        if !self.is_known() || !other.is_known() { return Span::default(); }
+        Span { end: other.end, ..*self }
+    }
+
+    pub fn is_known(&self) -> bool { self.line != 0 }
+
+    /// Renders a message followed by the source line the span points to, similar to rustc.
+    ///
+    /// ```text
+    /// error: cannot add number and bool
+    ///  --> layout.m2, line 3, column 14
+    ///   |
+    /// 3 | let broken = 1 + true;
+    ///   |              ^^^^^^^^
+    /// ```
+    pub fn render(&self, source: &str, path: Option<&str>, message: &str) -> String {
+        let line_start = source[..self.start.min(source.len())].rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+        let line = source[line_start..].lines().next().unwrap_or("");
+
+        // the marker doesn't continue onto the following lines
+        let column = (self.start - line_start).min(line.len());
+        let end = (self.end - line_start).min(line.len()).max(column);
+        let indent: String = line[..column].chars().map(|ch| if ch == '\t' { '\t' } else { ' ' }).collect();
+        let carets = "^".repeat(line[column..end].chars().count().max(1));
+
+        let gutter = " ".repeat(self.line.to_string().len());
+        let location = match path {
+            Some(path) => format!("{}, {}", path, self),
+            None => self.to_string(),
+        };
+        format!("error: {}\n{gutter}--> {}\n{gutter} |\n{} | {}\n{gutter} | {}{}",
+                message, location, self.line, line, indent, carets, gutter = gutter)
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// An AST node together with its position in the source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span,
+}
+
+impl<T> From<T> for Spanned<T> {
+    fn from(node: T) -> Self { Spanned { node, span: Span::default() } }
+}
+
+impl<T> Deref for Spanned<T> {
+    type Target = T;
+    fn deref(&self) -> &T { &self.node }
+}
+
+/// Records the span of the input consumed by the parser.
+pub(super) fn spanned<'a, O>(mut parser: impl FnMut(&'a str) -> ResNew<&'a str, O>) -> impl FnMut(&'a str) -> ResNew<&'a str, Spanned<O>> {
+    move |input: &'a str| {
+        let (rest, (node, last_err)) = parser(input)?;
+        Ok((rest, (Spanned { node, span: Span::between(input, rest) }, last_err)))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_span() {
+        let source = "let a = 1;\n  a::b;\n";
+        let span = with_source(source, 1, || Span::between(&source[13..], &source[18..]));
+        assert_eq!((span.start, span.end, span.line, span.column, span.file), (13, 18, 2, 3, 1));
+        assert_eq!(span.to_string(), "line 2, column 3");
+
+        // unrelated input
+        assert!(!Span::between("a::b;", "").is_known());
+
+        let span = Span::at(source, 13, 18).to(&Span::at(source, 18, 19));
+        assert_eq!((span.start, span.end, span.line, span.column), (13, 19, 2, 3));
+    }
+
+    #[test]
+    fn test_render() {
+        let source = "let a = 1;\nlet b = a + true;\n";
+        assert_eq!(Span::at(source, 19, 27).render(source, None, "cannot add number and bool"), indoc! {"
+            error: cannot add number and bool
+             --> line 2, column 9
+              |
+            2 | let b = a + true;
+              |         ^^^^^^^^"});
+
+        // the end of the input
+        assert_eq!(Span::at(source, 29, 29).render(source, Some("layout.m2"), "expected ';'"),
+                   "error: expected ';'\n --> layout.m2, line 3, column 1\n  |\n3 | \n  | ^");
+    }
+}
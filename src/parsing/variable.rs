@@ -18,10 +18,11 @@ pub(super) fn variable_initialization(input: &str) -> ResNew<&str, Expr> {
             let (input_before_expr, ident, expr) = (parts.0.0, parts.0.1.1, parts.2);
             let ((name, _), (expr, last_err)) = (ident, expr);
 
-            match expr {
-                Expr::Name(_) | Expr::Value(_) | Expr::Lambda(_, _) | Expr::FunctionCall(_, _) | Expr::Eq(_, _) | Expr::Neq(_, _) |
+            match expr.node {
+                Expr::Name(_) | Expr::Value(_) | Expr::Interpolation(_) | Expr::List(_) | Expr::Map(_) | Expr::Index(_, _) | Expr::Lambda(_, _) | Expr::FunctionCall(_, _) | Expr::Eq(_, _) | Expr::Neq(_, _) |
                 Expr::LT(_, _) | Expr::GT(_, _) | Expr::Add(_, _) | Expr::Sub(_, _) | Expr::Div(_, _) |
-                Expr::Mul(_, _) | Expr::Neg(_) | Expr::And(_, _) | Expr::Or(_, _)
+                Expr::Mul(_, _) | Expr::Mod(_, _) | Expr::Neg(_) | Expr::Minus(_) | Expr::And(_, _) | Expr::Or(_, _) |
+                Expr::LE(_, _) | Expr::GE(_, _) | Expr::Range(_, _, _)
                 => {}
                 _ => { return Err(make_generic_nom_err_options(input_before_expr, vec!["valid initialization expression".to_string()])); }
             };
@@ -43,8 +44,49 @@ pub(super) fn variable_assignment(input: &str) -> ResNew<&str, Expr> {
     )
 }
 
+/// `a += value`, `a -= value` and `a++`
+pub(super) fn compound_assignment(input: &str) -> ResNew<&str, Expr> {
+    let (input, (name, _)) = ident(input)?;
+    let (input, _) = ws0(input)?;
+
+    if let Ok((next, _)) = tag_custom::<_, _, CustomError<_>>("++")(input) {
+        return Ok((next, (Expr::Inc(name), None)));
+    }
+
+    tuple((
+        alt((tag_custom("+="), tag_custom("-="))),
+        ws0,
+        expr,
+    ))(input).map(|(next, (op, _, (value, last_err)))| {
+        let expr = match op {
+            "+=" => Expr::AddAssign(name, Box::new(value)),
+            _ => Expr::SubAssign(name, Box::new(value)),
+        };
+        (next, (expr, last_err))
+    })
+}
+
+pub(super) fn index_assignment(input: &str) -> ResNew<&str, Expr> {
+    let (input, (name, _)) = spanned(ident)(input)?;
+    tuple((
+        many1(spanned(index)),
+        ws0,
+        tag_custom("="),
+        ws0,
+        expr,
+    ))(input).map(|(next, (mut indices, _, _, _, (value, last_err)))| {
+        // all but the last index select the container that is modified
+        let last = indices.pop().unwrap().0.node;
+        let container = indices.into_iter().fold(Spanned { node: Expr::Name(name.node), span: name.span }, |acc, (index, _)| {
+            let span = acc.span.to(&index.span);
+            Spanned { node: Expr::Index(Box::new(acc), Box::new(index.node)), span }
+        });
+        (next, (Expr::IndexAssign(Box::new(container), Box::new(last), Box::new(value)), last_err))
+    })
+}
+
 pub(super) fn variable(input: &str) -> ResNew<&str, Expr> {
-    ident(input)
+    qualified_ident(input)
         .map(|(next, (name, last_err))|
             (next, (Expr::Name(name), last_err)))
 }
@@ -58,12 +100,39 @@ mod tests {
     fn test_assignment() {
         assert_eq!(ident("hello2"), nom_ok("hello2".to_string()));
         assert_eq!(variable_assignment("foo = true"),
-                   nom_ok(Expr::Assign("foo".to_string(), Box::new(nom_eval(boolean("true")))))
+                   nom_ok(Expr::Assign("foo".to_string(), Box::new(nom_eval(boolean("true")).into())))
         );
 
         assert!(matches!(ident("2hello"), Err(..)));
     }
 
+    #[test]
+    fn test_compound_assignment() {
+        let one = || Box::new(Spanned::from(Expr::Value(ValueType::Number(1.0))));
+        assert_eq!(nom_no_last_err(compound_assignment("a += 1")), nom_ok(Expr::AddAssign("a".to_string(), one())));
+        assert_eq!(nom_no_last_err(compound_assignment("a-=1")), nom_ok(Expr::SubAssign("a".to_string(), one())));
+        assert_eq!(compound_assignment("a++"), nom_ok(Expr::Inc("a".to_string())));
+        assert!(compound_assignment("a + 1").is_err());
+        assert_eq!(nom_no_last_err(expr("a + 1")), nom_ok(Spanned::from(Expr::Add(
+            Box::new(Expr::Name("a".to_string()).into()),
+            one(),
+        ))));
+    }
+
+    #[test]
+    fn test_index_assignment() {
+        let index = |value: &str| Box::new(Spanned::from(Expr::Value(ValueType::String(value.to_string()))));
+        assert_eq!(nom_no_last_err(index_assignment("a[\"b\"][\"c\"] = true")),
+                   nom_ok(Expr::IndexAssign(
+                       Box::new(Expr::Index(Box::new(Expr::Name("a".to_string()).into()), index("b")).into()),
+                       index("c"),
+                       Box::new(nom_eval(boolean("true")).into()),
+                   ))
+        );
+        assert!(index_assignment("a = true").is_err());
+        assert!(index_assignment("a[0] == true").is_err());
+    }
+
     #[test]
     fn test_lambda() {
         assert_eq!(nom_no_last_err(variable_initialization("let a = || {}")),
@@ -0,0 +1,39 @@
+use super::*;
+
+pub(super) fn while_loop(input: &str) -> ResNew<&str, Stmt> {
+    tuple((
+        tag_custom("while"), ws0,
+        tag_custom("("), ws0,
+        expr, ws0,
+        tag_custom(")"), ws0,
+        block,
+    ))(input)
+        .map(|(next, v)| {
+            let stmt = Stmt::While(v.4.0, v.8.0);
+            (next, (stmt, None))
+        })
+}
+
+pub(super) fn loop_stmt(input: &str) -> ResNew<&str, Stmt> {
+    tuple((tag_custom("loop"), ws0, block))(input)
+        .map(|(next, (_, _, (block, _)))| (next, (Stmt::Loop(block), None)))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_while_loop() {
+        assert_eq!(
+            while_loop("while(i < 3){ break; }"),
+            nom_ok(Stmt::While(
+                nom_eval(expr("i < 3")),
+                nom_eval(block("{ break; }")),
+            ))
+        );
+        assert_eq!(loop_stmt("loop { continue; }"), nom_ok(Stmt::Loop(nom_eval(block("{ continue; }")))));
+        assert!(loop_stmt("looping{}").is_err());
+    }
+}
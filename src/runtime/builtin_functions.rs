@@ -1,31 +1,47 @@
-use evdev_rs::enums::int_to_ev_key;
+use evdev_rs::enums::{EV_REL, int_to_ev_key};
 use tokio::process::Command;
 
 use crate::*;
 use crate::messaging::ExecutionMessage;
-use crate::parsing::parser::{parse_key_action_with_mods, parse_key_sequence};
-
-pub async fn throw_error<'a>(err: anyhow::Error, exit_code: i32, amb: &mut Ambient<'a>) -> ValueType {
-    amb.message_tx.borrow_mut().as_ref().unwrap()
-        .send(ExecutionMessage::FatalError(err, exit_code))
-        .await.unwrap();
-    return ValueType::Void;
+use crate::runtime::collections::*;
+use crate::parsing::parser::{parse_key, parse_key_action_with_mods, parse_key_sequence, parse_mapping_trigger};
+
+/// The minimum and maximum number of arguments a built-in function takes, `None` if the function isn't a built-in.
+pub(crate) fn builtin_arity(name: &str) -> Option<(usize, Option<usize>)> {
+    let arity = match name {
+        "active_window_class" | "active_window_instance" | "active_window_title" | "active_window_pid" |
+        "active_window_process" | "clear_mappings" => (0, Some(0)),
+        "exit" | "mappings" | "layer_pop" => (0, Some(1)),
+        "send" | "send_modifier" | "on_window_change" | "on_window_title_change" | "sleep" | "print" | "number_to_key" |
+        "number_to_char" | "char_to_number" | "unmap" | "set_combo_window" | "set_sequence_timeout" |
+        "set_sequence_cancel_keys" | "layer_push" | "layer_toggle" | "layer_oneshot" | "len" | "keys" | "values" => (1, Some(1)),
+        "mouse_scroll" => (1, Some(2)),
+        "execute" => (1, None),
+        "map_key" | "layer" | "device" | "window" | "layer_momentary" | "map_rel" | "mouse_move" | "map_abs" | "send_abs" |
+        "push" | "remove" | "for_each" => (2, Some(2)),
+        "set_abs_deadzone" => (2, Some(3)),
+        "map_abs_key" | "insert" => (3, Some(3)),
+        _ => return None,
+    };
+    Some(arity)
 }
 
-pub async fn evaluate_builtin<'a>(name: &String, args: &Vec<Expr>, var_map: &GuardedVarMap, amb: &mut Ambient<'_>) -> Result<ValueType> {
+pub async fn evaluate_builtin<'a>(name: &String, args: &Vec<Spanned<Expr>>, var_map: &GuardedVarMap, amb: &mut Ambient<'_>) -> Result<ValueType> {
     let mut parsed_args = vec![];
     for expr in args {
-        let arg = eval_expr(expr, var_map, amb).await;
+        let arg = eval_spanned_expr(expr, var_map, amb).await?;
         parsed_args.push(arg);
     }
 
+    // functions declared by the script take precedence over the built-in functions of the same name
+    if let ValueType::Lambda(params, block, lambda_var_map) = eval_expr(&Expr::Name(name.to_string()), var_map, amb).await? {
+        let lambda_args = parsed_args.into_iter().take(params.len()).collect();
+        return Ok(call_lambda(&params, &block, &lambda_var_map, lambda_args, amb).await?);
+    }
+
     match &**name {
         "exit" => {
-            let arg = args.get(0);
-            let val = match arg {
-                Some(arg) => eval_expr(arg, var_map, amb).await,
-                _ => ValueType::Number(0.0),
-            };
+            let val = parsed_args.get(0).cloned().unwrap_or(ValueType::Number(0.0));
 
             let exit_code = match val {
                 ValueType::Number(exit_code) => exit_code as i32,
@@ -35,13 +51,13 @@ pub async fn evaluate_builtin<'a>(name: &String, args: &Vec<Expr>, var_map: &Gua
             amb.message_tx.as_ref().unwrap().send(ExecutionMessage::Exit(exit_code)).await.unwrap();
         }
         "send" => {
-            let val = eval_expr(args.get(0).unwrap(), var_map, amb).await;
+            let val = parsed_args.get(0).cloned().unwrap_or(ValueType::Void);
             let val = match val {
                 ValueType::String(val) => val,
                 _ => return Err(anyhow!("invalid parameter passed to function 'send'")),
             };
 
-            let actions = parse_key_sequence(&*val).unwrap();
+            let actions = parse_key_sequence(&*val)?;
 
             for action in actions {
                 amb.ev_writer_tx.send(action.to_input_ev()).await.unwrap();
@@ -49,13 +65,13 @@ pub async fn evaluate_builtin<'a>(name: &String, args: &Vec<Expr>, var_map: &Gua
             }
         }
         "send_modifier" => {
-            let val = eval_expr(args.get(0).unwrap(), var_map, amb).await;
+            let val = parsed_args.get(0).cloned().unwrap_or(ValueType::Void);
             let val = match val {
                 ValueType::String(val) => val,
                 _ => return Err(anyhow!("invalid parameter passed to function 'send'")),
             };
 
-            let actions = parse_key_sequence(&*val).unwrap();
+            let actions = parse_key_sequence(&*val)?;
 
             if actions.len() != 1 {
                 return Err(anyhow!("expected a single key action, got {}", actions.len()));
@@ -63,8 +79,7 @@ pub async fn evaluate_builtin<'a>(name: &String, args: &Vec<Expr>, var_map: &Gua
 
             let action = actions.get(0).unwrap();
 
-            if [*KEY_LEFT_CTRL, *KEY_RIGHT_CTRL, *KEY_LEFT_ALT, *KEY_RIGHT_ALT, *KEY_LEFT_SHIFT, *KEY_RIGHT_SHIFT, *KEY_LEFT_META, *KEY_RIGHT_META]
-                .contains(&action.key) {
+            if is_modifier_key(&action.key) {
                 amb.message_tx.as_ref().unwrap().send(ExecutionMessage::UpdateModifiers(*action)).await.unwrap();
             } else {
                 return Err(anyhow!("key action needs to be a modifier event"));
@@ -73,38 +88,50 @@ pub async fn evaluate_builtin<'a>(name: &String, args: &Vec<Expr>, var_map: &Gua
             amb.ev_writer_tx.send(action.to_input_ev()).await.unwrap();
             amb.ev_writer_tx.send(SYN_REPORT.clone()).await.unwrap();
         }
-        "active_window_class" => {
+        "active_window_class" | "active_window_instance" | "active_window_title" | "active_window_pid" | "active_window_process" => {
             let (tx, mut rx) = mpsc::channel(1);
             amb.message_tx.as_ref().unwrap().send(ExecutionMessage::GetFocusedWindowInfo(tx)).await.unwrap();
             if let Some(active_window) = rx.recv().await.unwrap() {
-                return Ok(ValueType::String(active_window.class));
+                return Ok(match &**name {
+                    "active_window_class" => ValueType::String(active_window.class),
+                    "active_window_instance" => ValueType::String(active_window.instance),
+                    "active_window_pid" => active_window.pid
+                        .map_or(ValueType::Void, |pid| ValueType::Number(pid as f64)),
+                    "active_window_process" => active_window.process
+                        .map_or(ValueType::Void, |process| ValueType::String(process.name)),
+                    _ => ValueType::String(active_window.name),
+                });
             }
         }
-        "on_window_change" => {
+        "on_window_change" | "on_window_title_change" => {
             if args.len() != 1 {
                 return Err(anyhow!("function takes 1 argument"));
             }
 
             let inner_block;
             let inner_var_map;
-            if let ValueType::Lambda(_, _block, _var_map) = eval_expr(args.get(0).unwrap(), var_map, amb).await {
+            if let ValueType::Lambda(_, _block, _var_map) = parsed_args.get(0).cloned().unwrap_or(ValueType::Void) {
                 inner_block = _block;
                 inner_var_map = _var_map;
             } else {
                 return Err(anyhow!("type mismatch, function takes lambda argument"));
             }
 
-            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::RegisterWindowChangeCallback(inner_block, inner_var_map)).await.unwrap();
+            let message = match &**name {
+                "on_window_change" => ExecutionMessage::RegisterWindowChangeCallback(amb.window_cycle_token, inner_block, inner_var_map),
+                _ => ExecutionMessage::RegisterWindowTitleChangeCallback(amb.window_cycle_token, inner_block, inner_var_map),
+            };
+            amb.message_tx.as_ref().unwrap().send(message).await.unwrap();
         }
         "sleep" => {
-            let val = eval_expr(args.get(0).unwrap(), var_map, amb).await;
+            let val = parsed_args.get(0).cloned().unwrap_or(ValueType::Void);
             match val {
                 ValueType::Number(millis) => tokio::time::sleep(time::Duration::from_millis(millis as u64)).await,
                 _ => return Err(anyhow!("sleep expects a number argument")),
             }
         }
         "print" => {
-            let val = eval_expr(args.get(0).unwrap(), var_map, amb).await;
+            let val = parsed_args.get(0).cloned().unwrap_or(ValueType::Void);
             let val = format!("{}\n", val);
 
             amb.message_tx.borrow_mut().as_ref().unwrap()
@@ -112,19 +139,19 @@ pub async fn evaluate_builtin<'a>(name: &String, args: &Vec<Expr>, var_map: &Gua
                 .unwrap();
         }
         "number_to_key" => {
-            let val = eval_expr(args.get(0).unwrap(), var_map, amb).await;
+            let val = parsed_args.get(0).cloned().unwrap_or(ValueType::Void);
             let val = match val {
                 ValueType::Number(val) => val,
                 _ => return Err(anyhow!("only numbers can be converted to keys")),
             };
             let val = val as u32;
 
-            let key = int_to_ev_key(val).expect(&*format!("key for scan code '{}' not found", val));
+            let key = int_to_ev_key(val).ok_or_else(|| anyhow!("key for scan code '{}' not found", val))?;
 
             return Ok(ValueType::String(format!("{{{}}}", EventCode::EV_KEY(key).to_string())));
         }
         "number_to_char" => {
-            let val = eval_expr(args.get(0).unwrap(), var_map, amb).await;
+            let val = parsed_args.get(0).cloned().unwrap_or(ValueType::Void);
             let val = match val {
                 ValueType::Number(val) => val,
                 _ => return Err(anyhow!("only numbers can be converted to chars")),
@@ -134,12 +161,12 @@ pub async fn evaluate_builtin<'a>(name: &String, args: &Vec<Expr>, var_map: &Gua
             return Ok(ValueType::String(format!("{}", val)));
         }
         "char_to_number" => {
-            let val = eval_expr(args.get(0).unwrap(), var_map, amb).await;
+            let val = parsed_args.get(0).cloned().unwrap_or(ValueType::Void);
             let val = match val {
                 ValueType::String(val) => val,
                 _ => return Err(anyhow!("only chars can be converted to chars")),
             };
-            if val.len() != 1 { panic!("string needs to contain exactly 1 character") }
+            if val.len() != 1 { return Err(anyhow!("string needs to contain exactly 1 character")); }
 
             let first_ch = val.chars().next().unwrap();
             let val = first_ch as u8 as f64;
@@ -147,15 +174,15 @@ pub async fn evaluate_builtin<'a>(name: &String, args: &Vec<Expr>, var_map: &Gua
         }
         "map_key" => {
             let val = (
-                eval_expr(args.get(0).unwrap(), var_map, amb).await,
-                eval_expr(args.get(1).unwrap(), var_map, amb).await,
+                parsed_args.get(0).cloned().unwrap_or(ValueType::Void),
+                parsed_args.get(1).cloned().unwrap_or(ValueType::Void),
             );
             let (from, to) = match val {
                 (ValueType::String(from), ValueType::Lambda(_, to, var_map)) => (from, (to, var_map)),
                 _ => return Err(anyhow!("invalid arguments passed to 'map_key'")),
             };
 
-            let mappings = match parse_key_action_with_mods(&*from, to.0).unwrap() {
+            let mappings = match parse_key_action_with_mods(&*from, to.0)? {
                 Expr::KeyMapping(v) => v,
                 _ => unreachable!(),
             };
@@ -164,10 +191,215 @@ pub async fn evaluate_builtin<'a>(name: &String, args: &Vec<Expr>, var_map: &Gua
                 let mapping = mapping.clone();
 
                 amb.message_tx.borrow_mut().as_ref().unwrap()
-                    .send(ExecutionMessage::AddMapping(amb.window_cycle_token, mapping.from, mapping.to, to.1.clone())).await
+                    .send(ExecutionMessage::AddMapping(amb.window_cycle_token, amb.layer.clone(), amb.condition.clone(), mapping.from, mapping.to, to.1.clone())).await
                     .unwrap();
             }
         }
+        "unmap" => {
+            let from = match parsed_args.get(0) {
+                Some(ValueType::String(from)) => parse_mapping_trigger(from)?,
+                _ => return Err(anyhow!("function 'unmap' expects a mapping trigger")),
+            };
+
+            for from in from {
+                amb.message_tx.as_ref().unwrap()
+                    .send(ExecutionMessage::RemoveMapping(amb.window_cycle_token, amb.layer.clone(), amb.condition.clone(), from)).await
+                    .unwrap();
+            }
+        }
+        "clear_mappings" => {
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::ClearMappings(amb.window_cycle_token)).await.unwrap();
+        }
+        "mappings" => {
+            let layer = match parsed_args.get(0) {
+                Some(ValueType::String(layer)) => Some(layer.clone()),
+                None => None,
+                _ => return Err(anyhow!("function 'mappings' expects an optional layer name")),
+            };
+
+            let (tx, mut rx) = mpsc::channel(1);
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::GetMappings(layer, tx)).await.unwrap();
+            let triggers = rx.recv().await.unwrap();
+            return Ok(new_list(triggers.into_iter().map(ValueType::String).collect()));
+        }
+        "set_combo_window" => {
+            match parsed_args.get(0) {
+                Some(ValueType::Number(millis)) if *millis >= 0.0 => {
+                    amb.message_tx.as_ref().unwrap()
+                        .send(ExecutionMessage::SetComboWindow(amb.window_cycle_token, time::Duration::from_millis(*millis as u64))).await
+                        .unwrap();
+                }
+                _ => return Err(anyhow!("set_combo_window expects a positive number argument")),
+            }
+        }
+        "set_sequence_timeout" => {
+            match parsed_args.get(0) {
+                Some(ValueType::Number(millis)) if *millis >= 0.0 => {
+                    amb.message_tx.as_ref().unwrap()
+                        .send(ExecutionMessage::SetSequenceTimeout(amb.window_cycle_token, time::Duration::from_millis(*millis as u64))).await
+                        .unwrap();
+                }
+                _ => return Err(anyhow!("set_sequence_timeout expects a positive number argument")),
+            }
+        }
+        "set_sequence_cancel_keys" => {
+            let keys = match parsed_args.get(0) {
+                Some(ValueType::String(keys)) => parse_key_sequence(keys)?.into_iter()
+                    .filter(|action| action.value == TYPE_DOWN)
+                    .map(|action| action.key)
+                    .collect(),
+                _ => return Err(anyhow!("set_sequence_cancel_keys expects a key sequence argument")),
+            };
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::SetSequenceCancelKeys(amb.window_cycle_token, keys)).await.unwrap();
+        }
+        "layer" => {
+            let (name, block, lambda_var_map) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::String(name)), Some(ValueType::Lambda(_, block, var_map))) => (name.clone(), block.clone(), var_map.clone()),
+                _ => return Err(anyhow!("invalid arguments passed to 'layer'")),
+            };
+
+            // mappings defined in the lambda body are added to the layer
+            let parent_layer = amb.layer.replace(name);
+            let ret = eval_block(&block, &lambda_var_map, amb).await;
+            amb.layer = parent_layer;
+            ret?;
+        }
+        "device" => {
+            let (selector, block, lambda_var_map) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::String(selector)), Some(ValueType::Lambda(_, block, var_map))) =>
+                    (DeviceSelector::parse(selector)?, block.clone(), var_map.clone()),
+                _ => return Err(anyhow!("invalid arguments passed to 'device'")),
+            };
+
+            // mappings defined in the lambda body only apply to events of the selected devices
+            let parent_device = amb.condition.device.replace(selector);
+            let ret = eval_block(&block, &lambda_var_map, amb).await;
+            amb.condition.device = parent_device;
+            ret?;
+        }
+        "window" => {
+            let (selector, block, lambda_var_map) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::String(selector)), Some(ValueType::Lambda(_, block, var_map))) =>
+                    (WindowSelector::parse(selector)?, block.clone(), var_map.clone()),
+                _ => return Err(anyhow!("invalid arguments passed to 'window'")),
+            };
+
+            // mappings defined in the lambda body only apply while a matching window is active
+            amb.condition.window.push(selector);
+            let ret = eval_block(&block, &lambda_var_map, amb).await;
+            amb.condition.window.pop();
+            ret?;
+        }
+        "layer_push" | "layer_toggle" | "layer_oneshot" => {
+            let layer = match parsed_args.get(0) {
+                Some(ValueType::String(layer)) => layer.clone(),
+                _ => return Err(anyhow!("function '{}' expects a layer name", name)),
+            };
+
+            let command = match &**name {
+                "layer_push" => LayerCommand::Push(layer),
+                "layer_toggle" => LayerCommand::Toggle(layer),
+                _ => LayerCommand::OneShot(layer),
+            };
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::LayerCommand(amb.window_cycle_token, command)).await.unwrap();
+        }
+        "layer_pop" => {
+            let layer = match parsed_args.get(0) {
+                Some(ValueType::String(layer)) => Some(layer.clone()),
+                None => None,
+                _ => return Err(anyhow!("function 'layer_pop' expects a layer name")),
+            };
+            amb.message_tx.as_ref().unwrap().send(ExecutionMessage::LayerCommand(amb.window_cycle_token, LayerCommand::Pop(layer))).await.unwrap();
+        }
+        "layer_momentary" => {
+            let (key, layer) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::String(key)), Some(ValueType::String(layer))) => (parse_key(key)?, layer.clone()),
+                _ => return Err(anyhow!("invalid arguments passed to 'layer_momentary'")),
+            };
+            amb.message_tx.as_ref().unwrap()
+                .send(ExecutionMessage::AddMomentaryLayer(amb.window_cycle_token, key, layer)).await
+                .unwrap();
+        }
+        "map_rel" => {
+            let (axis, (params, block, lambda_var_map)) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::String(axis)), Some(ValueType::Lambda(params, block, var_map))) =>
+                    (parse_rel_axis(axis)?, (params.clone(), block.clone(), var_map.clone())),
+                _ => return Err(anyhow!("invalid arguments passed to 'map_rel'")),
+            };
+
+            amb.message_tx.as_ref().unwrap()
+                .send(ExecutionMessage::AddRelHandler(amb.window_cycle_token, axis, params, block, lambda_var_map)).await
+                .unwrap();
+        }
+        "mouse_move" => {
+            let (x, y) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::Number(x)), Some(ValueType::Number(y))) => (*x as i32, *y as i32),
+                _ => return Err(anyhow!("mouse_move expects 2 number arguments")),
+            };
+
+            if x != 0 { amb.ev_writer_tx.send(KeyAction::new(rel_key(EV_REL::REL_X), x).to_input_ev()).await.unwrap(); }
+            if y != 0 { amb.ev_writer_tx.send(KeyAction::new(rel_key(EV_REL::REL_Y), y).to_input_ev()).await.unwrap(); }
+            amb.ev_writer_tx.send(SYN_REPORT.clone()).await.unwrap();
+        }
+        "mouse_scroll" => {
+            let (vertical, horizontal) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::Number(vertical)), None) => (*vertical as i32, 0),
+                (Some(ValueType::Number(vertical)), Some(ValueType::Number(horizontal))) => (*vertical as i32, *horizontal as i32),
+                _ => return Err(anyhow!("mouse_scroll expects 1 or 2 number arguments")),
+            };
+
+            for (axis, ticks) in [(EV_REL::REL_WHEEL, vertical), (EV_REL::REL_HWHEEL, horizontal)].iter() {
+                if *ticks == 0 { continue; }
+                let hi_res_axis = wheel_hi_res_axis(*axis).unwrap();
+                amb.ev_writer_tx.send(KeyAction::new(rel_key(hi_res_axis), ticks * WHEEL_HI_RES_TICK).to_input_ev()).await.unwrap();
+                amb.ev_writer_tx.send(KeyAction::new(rel_key(*axis), *ticks).to_input_ev()).await.unwrap();
+            }
+            amb.ev_writer_tx.send(SYN_REPORT.clone()).await.unwrap();
+        }
+        "map_abs" => {
+            let (axis, (params, block, lambda_var_map)) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::String(axis)), Some(ValueType::Lambda(params, block, var_map))) =>
+                    (parse_abs_axis(axis)?, (params.clone(), block.clone(), var_map.clone())),
+                _ => return Err(anyhow!("invalid arguments passed to 'map_abs'")),
+            };
+
+            amb.message_tx.as_ref().unwrap()
+                .send(ExecutionMessage::AddAbsHandler(amb.window_cycle_token, axis, params, block, lambda_var_map)).await
+                .unwrap();
+        }
+        "map_abs_key" => {
+            let (axis, threshold, key) = match (parsed_args.get(0), parsed_args.get(1), parsed_args.get(2)) {
+                (Some(ValueType::String(axis)), Some(ValueType::Number(threshold)), Some(ValueType::String(key))) =>
+                    (parse_abs_axis(axis)?, *threshold as i32, parse_key(key)?),
+                _ => return Err(anyhow!("invalid arguments passed to 'map_abs_key'")),
+            };
+
+            amb.message_tx.as_ref().unwrap()
+                .send(ExecutionMessage::AddAbsThreshold(amb.window_cycle_token, axis, AbsThreshold::new(threshold, key))).await
+                .unwrap();
+        }
+        "set_abs_deadzone" => {
+            let (axis, radius, center) = match (parsed_args.get(0), parsed_args.get(1), parsed_args.get(2)) {
+                (Some(ValueType::String(axis)), Some(ValueType::Number(radius)), None) =>
+                    (parse_abs_axis(axis)?, *radius as i32, 0),
+                (Some(ValueType::String(axis)), Some(ValueType::Number(radius)), Some(ValueType::Number(center))) =>
+                    (parse_abs_axis(axis)?, *radius as i32, *center as i32),
+                _ => return Err(anyhow!("invalid arguments passed to 'set_abs_deadzone'")),
+            };
+
+            amb.message_tx.as_ref().unwrap()
+                .send(ExecutionMessage::SetAbsDeadzone(amb.window_cycle_token, axis, AbsDeadzone { center, radius })).await
+                .unwrap();
+        }
+        "send_abs" => {
+            let (axis, value) = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::String(axis)), Some(ValueType::Number(value))) => (parse_abs_axis(axis)?, *value as i32),
+                _ => return Err(anyhow!("invalid arguments passed to 'send_abs'")),
+            };
+
+            amb.ev_writer_tx.send(KeyAction::new(abs_key(axis), value).to_input_ev()).await.unwrap();
+            amb.ev_writer_tx.send(SYN_REPORT.clone()).await.unwrap();
+        }
         "execute" => {
             if parsed_args.len() < 1 { return Err(anyhow!("argument error: function 'execute' expected at least 1 argument")); }
 
@@ -197,35 +429,81 @@ pub async fn evaluate_builtin<'a>(name: &String, args: &Vec<Expr>, var_map: &Gua
 
             return Ok(ValueType::String(output.to_string()));
         }
-        name => {
-            let (lambda_params, lambda_block, lambda_var_map) = match eval_expr(&Expr::Name(name.to_string()), var_map, amb).await {
-                ValueType::Lambda(params, block, var_map) => (params, block, var_map),
-                ValueType::Void => return Err(anyhow!("function '{}' not found in this scope", name)),
-                _ => return Err(anyhow!("variable '{}' is not a lambda function", name)),
+        "len" => {
+            let len = match parsed_args.get(0) {
+                Some(ValueType::List(list)) => list.lock().unwrap().len(),
+                Some(ValueType::Map(map)) => map.lock().unwrap().len(),
+                Some(ValueType::String(string)) => string.chars().count(),
+                _ => return Err(anyhow!("function 'len' expects a list, map or string")),
             };
-
-            // we need to clone the lambda's var_map since each lambda execution needs to not affect the next one
-            // TODO make GuardedVarMap a proper struct and implement a proper deep clone method
-            let mut lambda_var_map = GuardedVarMap::new(Mutex::new(VarMap::new(
-                lambda_var_map.lock().unwrap().parent.clone()
-            )));
-
-            for (idx, param) in lambda_params.iter().enumerate() {
-                let val = match args.get(idx) {
-                    Some(expr) => eval_expr(expr, var_map, amb).await,
-                    None => ValueType::Void,
-                };
-
-                eval_expr(&Expr::Init(param.clone(), Box::new(Expr::Value(val))), &lambda_var_map, amb).await;
+            return Ok(ValueType::Number(len as f64));
+        }
+        "push" => {
+            match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::List(list)), Some(value)) => list.lock().unwrap().push(value.clone()),
+                _ => return Err(anyhow!("function 'push' expects a list and a value")),
             }
+        }
+        "insert" => {
+            match (parsed_args.get(0), parsed_args.get(1), parsed_args.get(2)) {
+                (Some(ValueType::List(list)), Some(index), Some(value)) => {
+                    let mut list = list.lock().unwrap();
+                    let position = list_position(index, list.len(), true)?;
+                    list.insert(position, value.clone());
+                }
+                (Some(ValueType::Map(map)), Some(key), Some(value)) => { map.lock().unwrap().insert(map_key(key)?, value.clone()); }
+                _ => return Err(anyhow!("function 'insert' expects a list or map, an index or key and a value")),
+            }
+        }
+        "remove" => {
+            let removed = match (parsed_args.get(0), parsed_args.get(1)) {
+                (Some(ValueType::List(list)), Some(index)) => {
+                    let mut list = list.lock().unwrap();
+                    let position = list_position(index, list.len(), false)?;
+                    list.remove(position)
+                }
+                (Some(ValueType::Map(map)), Some(key)) => map.lock().unwrap().remove(&map_key(key)?).unwrap_or(ValueType::Void),
+                _ => return Err(anyhow!("function 'remove' expects a list or map and an index or key")),
+            };
+            return Ok(removed);
+        }
+        "keys" | "values" => {
+            let map = match parsed_args.get(0) {
+                Some(ValueType::Map(map)) => map.lock().unwrap(),
+                _ => return Err(anyhow!("function '{}' expects a map", name)),
+            };
+            let values = match &**name {
+                "keys" => map.keys().map(|key| ValueType::String(key.clone())).collect(),
+                _ => map.values().cloned().collect(),
+            };
+            return Ok(new_list(values));
+        }
+        "for_each" => {
+            // the callback gets a snapshot, it may modify the collection
+            let entries: Vec<(ValueType, ValueType)> = match parsed_args.get(0) {
+                Some(ValueType::List(list)) => list.lock().unwrap().iter().cloned().enumerate()
+                    .map(|(idx, value)| (value, ValueType::Number(idx as f64)))
+                    .collect(),
+                Some(ValueType::Map(map)) => map.lock().unwrap().iter()
+                    .map(|(key, value)| (value.clone(), ValueType::String(key.clone())))
+                    .collect(),
+                _ => return Err(anyhow!("function 'for_each' expects a list or map and a callback")),
+            };
+            let (params, block, lambda_var_map) = match parsed_args.get(1) {
+                Some(ValueType::Lambda(params, block, var_map)) => (params, block, var_map),
+                _ => return Err(anyhow!("function 'for_each' expects a list or map and a callback")),
+            };
 
-            let ret = eval_block(&lambda_block, &mut lambda_var_map, amb).await;
-            match ret {
-                BlockRet::Return(ret) => return Ok(ret),
-                BlockRet::Continue => return Err(anyhow!("function cannot return a continue statement")),
-                BlockRet::None => {}
+            for (value, key) in entries {
+                call_lambda(params, block, lambda_var_map, vec![value, key], amb).await?;
             }
         }
+        name => {
+            return match eval_expr(&Expr::Name(name.to_string()), var_map, amb).await? {
+                ValueType::Void => Err(anyhow!("function '{}' not found in this scope", name)),
+                _ => Err(anyhow!("variable '{}' is not a lambda function", name)),
+            };
+        }
     };
 
     Ok(ValueType::Void)
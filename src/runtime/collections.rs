@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::*;
+
+/// Lists and maps are shared between all variables that hold them, like lambdas they are modified in place.
+pub type GuardedList = Arc<Mutex<Vec<ValueType>>>;
+pub type GuardedMap = Arc<Mutex<BTreeMap<String, ValueType>>>;
+
+pub fn new_list(values: Vec<ValueType>) -> ValueType { ValueType::List(Arc::new(Mutex::new(values))) }
+
+pub fn new_map(values: BTreeMap<String, ValueType>) -> ValueType { ValueType::Map(Arc::new(Mutex::new(values))) }
+
+/// Converts an index into a position in the list, `allow_end` accepts `len` for inserting at the end.
+pub fn list_position(index: &ValueType, len: usize, allow_end: bool) -> RuntimeResult<usize> {
+    let index = match index {
+        ValueType::Number(index) if index.fract() == 0.0 && *index >= 0.0 => *index as usize,
+        ValueType::Number(index) => return Err(RuntimeError::new(format!("invalid list index {}", index))),
+        index => return Err(RuntimeError::new(format!("lists can't be indexed with a {}", index.type_name()))),
+    };
+
+    let end = if allow_end { len + 1 } else { len };
+    if index >= end {
+        return Err(RuntimeError::new(format!("index {} is out of bounds for a list of length {}", index, len)));
+    }
+    Ok(index)
+}
+
+/// Map keys are strings, numbers are converted.
+pub fn map_key(key: &ValueType) -> RuntimeResult<String> {
+    match key {
+        ValueType::String(key) => Ok(key.clone()),
+        ValueType::Number(key) => Ok(key.to_string()),
+        key => Err(RuntimeError::new(format!("maps can't be indexed with a {}", key.type_name()))),
+    }
+}
+
+/// Looks up an element, missing map keys are void.
+pub fn get_index(container: &ValueType, index: &ValueType) -> RuntimeResult<ValueType> {
+    match container {
+        ValueType::List(list) => {
+            let list = list.lock().unwrap();
+            Ok(list[list_position(index, list.len(), false)?].clone())
+        }
+        ValueType::Map(map) => Ok(map.lock().unwrap().get(&map_key(index)?).cloned().unwrap_or(ValueType::Void)),
+        container => Err(RuntimeError::new(format!("cannot index into a {}", container.type_name()))),
+    }
+}
+
+/// Replaces an element, maps gain new keys while lists need to contain the index already.
+pub fn set_index(container: &ValueType, index: &ValueType, value: ValueType) -> RuntimeResult<()> {
+    match container {
+        ValueType::List(list) => {
+            let mut list = list.lock().unwrap();
+            let position = list_position(index, list.len(), false)?;
+            list[position] = value;
+        }
+        ValueType::Map(map) => { map.lock().unwrap().insert(map_key(index)?, value); }
+        container => return Err(RuntimeError::new(format!("cannot index into a {}", container.type_name()))),
+    }
+    Ok(())
+}
+
+/// Compares the contents, a collection that is currently locked (i.e. because it contains itself) is never equal.
+pub(super) fn collections_equal(left: &ValueType, right: &ValueType) -> bool {
+    match (left, right) {
+        (ValueType::List(l), ValueType::List(r)) => Arc::ptr_eq(l, r) || match (l.try_lock(), r.try_lock()) {
+            (Ok(l), Ok(r)) => *l == *r,
+            _ => false,
+        },
+        (ValueType::Map(l), ValueType::Map(r)) => Arc::ptr_eq(l, r) || match (l.try_lock(), r.try_lock()) {
+            (Ok(l), Ok(r)) => *l == *r,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Elements are shown the way they are written in scripts, i.e. `["a", 1]`.
+pub(super) fn fmt_element(value: &ValueType, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match value {
+        ValueType::String(value) => write!(f, "{:?}", value),
+        value => write!(f, "{}", value),
+    }
+}
+
+pub(super) fn fmt_list(list: &GuardedList, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    // the list contains itself
+    let list = match list.try_lock() {
+        Ok(list) => list,
+        Err(_) => return write!(f, "[...]"),
+    };
+
+    write!(f, "[")?;
+    for (idx, value) in list.iter().enumerate() {
+        if idx > 0 { write!(f, ", ")?; }
+        fmt_element(value, f)?;
+    }
+    write!(f, "]")
+}
+
+pub(super) fn fmt_map(map: &GuardedMap, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let map = match map.try_lock() {
+        Ok(map) => map,
+        Err(_) => return write!(f, "{{...}}"),
+    };
+
+    write!(f, "{{")?;
+    for (idx, (key, value)) in map.iter().enumerate() {
+        if idx > 0 { write!(f, ", ")?; }
+        write!(f, "{:?}: ", key)?;
+        fmt_element(value, f)?;
+    }
+    write!(f, "}}")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collections() {
+        let list = new_list(vec![ValueType::Number(1.0), ValueType::String("a".to_string())]);
+        assert_eq!(list.to_string(), "[1, \"a\"]");
+        assert_eq!(get_index(&list, &ValueType::Number(1.0)), Ok(ValueType::String("a".to_string())));
+        assert!(get_index(&list, &ValueType::Number(2.0)).is_err());
+        assert!(get_index(&list, &ValueType::Number(0.5)).is_err());
+
+        set_index(&list, &ValueType::Number(0.0), ValueType::Bool(true)).unwrap();
+        assert_eq!(list, new_list(vec![ValueType::Bool(true), ValueType::String("a".to_string())]));
+
+        let map = new_map(BTreeMap::new());
+        set_index(&map, &ValueType::String("b".to_string()), list.clone()).unwrap();
+        set_index(&map, &ValueType::Number(1.0), ValueType::Number(2.0)).unwrap();
+        assert_eq!(map.to_string(), "{\"1\": 2, \"b\": [true, \"a\"]}");
+        assert!(matches!(get_index(&map, &ValueType::String("c".to_string())), Ok(ValueType::Void)));
+
+        // lists that contain themselves
+        if let ValueType::List(inner) = &list { inner.lock().unwrap().push(list.clone()); }
+        assert_eq!(list.to_string(), "[true, \"a\", [...]]");
+    }
+}
@@ -1,4 +1,5 @@
 use std::borrow::BorrowMut;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fmt::Formatter;
 
@@ -7,11 +8,33 @@ use messaging::*;
 use crate::*;
 
 use super::builtin_functions::evaluate_builtin;
-use super::builtin_functions::throw_error;
+use super::collections::*;
+
+/// Restricts a mapping to events that match all of the given conditions.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyActionCondition {
+    pub device: Option<DeviceSelector>,
+    /// the active window needs to match all selectors
+    pub window: Vec<WindowSelector>,
+}
+
+impl KeyActionCondition {
+    pub fn matches(&self, device: Option<&InputDeviceInfo>, window: Option<&ActiveWindowInfo>) -> bool {
+        let device_matches = match (&self.device, device) {
+            (Some(selector), Some(device)) => selector.matches(device),
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+
+        let window_matches = match window {
+            Some(window) => self.window.iter().all(|selector| selector.matches(window)),
+            None => self.window.is_empty(),
+        };
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
-pub(crate) struct KeyActionCondition {
-    pub(crate) window_class_name: Option<String>,
+        device_matches && window_matches
+    }
+
+    pub fn is_unconditional(&self) -> bool { self.device.is_none() && self.window.is_empty() }
 }
 
 #[derive(Clone, Debug)]
@@ -20,6 +43,8 @@ pub enum ValueType {
     String(String),
     Lambda(Vec<String>, Block, GuardedVarMap),
     Number(f64),
+    List(GuardedList),
+    Map(GuardedMap),
     Void,
 }
 
@@ -30,11 +55,175 @@ impl PartialEq for ValueType {
             (String(l), String(r)) => l == r,
             (Bool(l), Bool(r)) => l == r,
             (Number(l), Number(r)) => l == r,
+            (l @ List(_), r @ List(_)) | (l @ Map(_), r @ Map(_)) => collections_equal(l, r),
             (_, _) => false,
         }
     }
 }
 
+impl ValueType {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ValueType::Bool(_) => "bool",
+            ValueType::String(_) => "string",
+            ValueType::Lambda(_, _, _) => "lambda",
+            ValueType::Number(_) => "number",
+            ValueType::List(_) => "list",
+            ValueType::Map(_) => "map",
+            ValueType::Void => "void",
+        }
+    }
+}
+
+fn binary_type_error(operation: &str, left: &ValueType, right: &ValueType) -> RuntimeError {
+    RuntimeError::new(format!("cannot {} {} and {}", operation, left.type_name(), right.type_name()))
+}
+
+fn add_values(left: ValueType, right: ValueType) -> RuntimeResult<ValueType> {
+    match (left, right) {
+        (ValueType::Number(left), ValueType::Number(right)) => Ok(ValueType::Number(left + right)),
+        (ValueType::String(left), right) => Ok(ValueType::String(format!("{}{}", left, right))),
+        (left, ValueType::String(right)) => Ok(ValueType::String(format!("{}{}", left, right))),
+        (left, right) => Err(binary_type_error("add", &left, &right)),
+    }
+}
+
+fn sub_values(left: ValueType, right: ValueType) -> RuntimeResult<ValueType> {
+    match (left, right) {
+        (ValueType::Number(left), ValueType::Number(right)) => Ok(ValueType::Number(left - right)),
+        (left, right) => Err(binary_type_error("subtract", &left, &right)),
+    }
+}
+
+/// Repeated strings that are longer (in bytes) are rejected rather than exhausting the memory.
+const MAX_REPEATED_STRING_LEN: usize = 100_000_000;
+
+fn repeat_string(string: &str, count: f64) -> RuntimeResult<ValueType> {
+    if count < 0.0 || count.fract() != 0.0 {
+        return Err(RuntimeError::new(format!("cannot repeat a string {} times", count)));
+    }
+    let too_long = count >= usize::MAX as f64 ||
+        !matches!(string.len().checked_mul(count as usize), Some(len) if len <= MAX_REPEATED_STRING_LEN);
+    if too_long {
+        return Err(RuntimeError::new(format!("cannot repeat a string {} times, the result would be too long", count)));
+    }
+    Ok(ValueType::String(string.repeat(count as usize)))
+}
+
+/// Ranges that are longer can't be turned into lists, `for` loops iterate over ranges of any length.
+const MAX_RANGE_LEN: usize = 1_000_000;
+
+/// The numbers of the range `start..end`, they are created on demand.
+fn range_values(start: f64, end: f64, inclusive: bool) -> impl Iterator<Item=f64> {
+    (0u64..)
+        .map(move |step| start + step as f64)
+        .take_while(move |value| if inclusive { *value <= end } else { *value < end })
+}
+
+async fn eval_range_bounds(start: &Spanned<Expr>, end: &Spanned<Expr>, var_map: &GuardedVarMap, amb: &mut Ambient<'_>) -> RuntimeResult<(f64, f64)> {
+    match (eval_spanned_expr(start, var_map, amb).await?, eval_spanned_expr(end, var_map, amb).await?) {
+        (ValueType::Number(start), ValueType::Number(end)) => Ok((start, end)),
+        (start, end) => Err(binary_type_error("create a range from", &start, &end)),
+    }
+}
+
+/// Finds the top level scope of the script imported as `namespace`.
+fn get_namespace(var_map: &GuardedVarMap, namespace: &str) -> Option<(Arc<Module>, GuardedVarMap)> {
+    let mut map = var_map.clone();
+    loop {
+        let tmp;
+        let map_guard = map.lock().unwrap();
+        match map_guard.namespaces.get(namespace) {
+            Some(imported) => return Some(imported.clone()),
+            None => match &map_guard.parent {
+                Some(parent) => tmp = parent.clone(),
+                None => return None,
+            }
+        }
+        drop(map_guard);
+        map = tmp;
+    }
+}
+
+/// Resolves `namespace.name` to the scope of the imported script and `name`, if `name` is exported by it.
+fn get_export_scope<'n>(var_map: &GuardedVarMap, var_name: &'n str) -> Option<(GuardedVarMap, &'n str)> {
+    let (namespace, name) = var_name.split_once('.')?;
+    match get_namespace(var_map, namespace) {
+        Some((module, scope)) if module.exports.iter().any(|export| export == name) => Some((scope, name)),
+        _ => None,
+    }
+}
+
+/// Fails for mappings that always apply to all layers, devices and windows, if they are declared inside of a
+/// `layer(..)`, `device(..)` or `window(..)` callback.
+fn ensure_unscoped(what: &str, amb: &Ambient<'_>) -> RuntimeResult<()> {
+    if amb.layer.is_some() || !amb.condition.is_unconditional() {
+        return Err(RuntimeError::new(format!("{} can't be declared inside of layer, device or window callbacks", what)));
+    }
+    Ok(())
+}
+
+/// Looks up a variable in the scope it was declared in.
+fn get_var(var_map: &GuardedVarMap, var_name: &str) -> Option<ValueType> {
+    if var_name.contains('.') {
+        let (scope, name) = get_export_scope(var_map, var_name)?;
+        let value = scope.lock().unwrap().scope_values.get(name).cloned();
+        return value;
+    }
+
+    let mut map = var_map.clone();
+    loop {
+        let tmp;
+        let map_guard = map.lock().unwrap();
+        match map_guard.scope_values.get(var_name) {
+            Some(v) => return Some(v.clone()),
+            None => match &map_guard.parent {
+                Some(parent) => tmp = parent.clone(),
+                None => return None,
+            }
+        }
+        drop(map_guard);
+        map = tmp;
+    }
+}
+
+/// Replaces the value of a variable in the scope it was declared in.
+fn set_var(var_map: &GuardedVarMap, var_name: &str, value: ValueType) -> RuntimeResult<()> {
+    if var_name.contains('.') {
+        let (scope, name) = get_export_scope(var_map, var_name).ok_or_else(|| undefined_var_error(var_name))?;
+        let mut scope_guard = scope.lock().unwrap();
+        return match scope_guard.scope_values.get_mut(name) {
+            Some(v) => {
+                *v = value;
+                Ok(())
+            }
+            None => Err(undefined_var_error(var_name)),
+        };
+    }
+
+    let mut map = var_map.clone();
+    loop {
+        let tmp;
+        let mut map_guard = map.lock().unwrap();
+        match map_guard.scope_values.get_mut(var_name) {
+            Some(v) => {
+                *v = value;
+                return Ok(());
+            }
+            None => match &map_guard.parent {
+                Some(parent) => tmp = parent.clone(),
+                None => return Err(undefined_var_error(var_name)),
+            }
+        }
+        drop(map_guard);
+        map = tmp;
+    }
+}
+
+fn undefined_var_error(var_name: &str) -> RuntimeError {
+    RuntimeError::new(format!("variable '{}' does not exist", var_name))
+}
+
 impl fmt::Display for ValueType {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
@@ -42,6 +231,8 @@ impl fmt::Display for ValueType {
             ValueType::String(v) => write!(f, "{}", v),
             ValueType::Number(v) => write!(f, "{}", v),
             ValueType::Lambda(_, _, _) => write!(f, "Lambda"),
+            ValueType::List(list) => fmt_list(list, f),
+            ValueType::Map(map) => fmt_map(map, f),
             ValueType::Void => write!(f, "Void"),
         }
     }
@@ -50,12 +241,14 @@ impl fmt::Display for ValueType {
 #[derive(Debug)]
 pub struct VarMap {
     pub(crate) scope_values: HashMap<String, ValueType>,
+    /// the imported scripts by namespace, along with the scope their exports live in
+    pub(crate) namespaces: HashMap<String, (Arc<Module>, GuardedVarMap)>,
     pub(crate) parent: Option<GuardedVarMap>,
 }
 
 impl VarMap {
     pub fn new(parent: Option<GuardedVarMap>) -> Self {
-        VarMap { scope_values: Default::default(), parent }
+        VarMap { scope_values: Default::default(), namespaces: Default::default(), parent }
     }
 }
 
@@ -74,27 +267,29 @@ pub type GuardedVarMap = Arc<Mutex<VarMap>>;
 
 
 #[async_recursion]
-pub(crate) async fn eval_expr<'a>(expr: &Expr, var_map: &GuardedVarMap, amb: &mut Ambient<'_>) -> ValueType {
+pub(crate) async fn eval_expr<'a>(expr: &Expr, var_map: &GuardedVarMap, amb: &mut Ambient<'_>) -> RuntimeResult<ValueType> {
     use ValueType::*;
-    match expr {
+    let value = match expr {
         Expr::Eq(left, right) => {
-            match (eval_expr(left, var_map, amb).await, eval_expr(right, var_map, amb).await) {
+            match (eval_spanned_expr(left, var_map, amb).await?, eval_spanned_expr(right, var_map, amb).await?) {
                 (Bool(left), Bool(right)) => Bool(left == right),
                 (String(left), String(right)) => Bool(left == right),
                 (Number(left), Number(right)) => Bool(left == right),
+                (left @ List(_), right @ List(_)) | (left @ Map(_), right @ Map(_)) => Bool(left == right),
                 _ => Bool(false),
             }
         }
         Expr::Neq(left, right) => {
-            match (eval_expr(left, var_map, amb).await, eval_expr(right, var_map, amb).await) {
+            match (eval_spanned_expr(left, var_map, amb).await?, eval_spanned_expr(right, var_map, amb).await?) {
                 (Bool(left), Bool(right)) => Bool(left != right),
                 (String(left), String(right)) => Bool(left != right),
                 (Number(left), Number(right)) => Bool(left != right),
+                (left @ List(_), right @ List(_)) | (left @ Map(_), right @ Map(_)) => Bool(left != right),
                 _ => Bool(true),
             }
         }
         Expr::LT(left, right) => {
-            match (eval_expr(left, var_map, amb).await, eval_expr(right, var_map, amb).await) {
+            match (eval_spanned_expr(left, var_map, amb).await?, eval_spanned_expr(right, var_map, amb).await?) {
                 (Bool(left), Bool(right)) => Bool(left < right),
                 (String(left), String(right)) => Bool(left < right),
                 (Number(left), Number(right)) => Bool(left < right),
@@ -102,86 +297,119 @@ pub(crate) async fn eval_expr<'a>(expr: &Expr, var_map: &GuardedVarMap, amb: &mu
             }
         }
         Expr::GT(left, right) => {
-            match (eval_expr(left, var_map, amb).await, eval_expr(right, var_map, amb).await) {
+            match (eval_spanned_expr(left, var_map, amb).await?, eval_spanned_expr(right, var_map, amb).await?) {
                 (Bool(left), Bool(right)) => Bool(left > right),
                 (String(left), String(right)) => Bool(left > right),
                 (Number(left), Number(right)) => Bool(left > right),
                 _ => Bool(false),
             }
         }
-        Expr::Add(left, right) => {
-            match (eval_expr(left, var_map, amb).await, eval_expr(right, var_map, amb).await) {
-                (Number(left), Number(right)) => Number(left + right),
-                (String(left), right) => String(format!("{}{}", left, right)),
-                (left, String(right)) => String(format!("{}{}", left, right)),
-                _ => panic!("cannot add unsupported types"),
+        Expr::LE(left, right) => {
+            match (eval_spanned_expr(left, var_map, amb).await?, eval_spanned_expr(right, var_map, amb).await?) {
+                (Bool(left), Bool(right)) => Bool(left <= right),
+                (String(left), String(right)) => Bool(left <= right),
+                (Number(left), Number(right)) => Bool(left <= right),
+                _ => Bool(false),
             }
         }
-        Expr::Sub(left, right) => {
-            match (eval_expr(left, var_map, amb).await, eval_expr(right, var_map, amb).await) {
-                (Number(left), Number(right)) => Number(left - right),
-                _ => panic!("cannot subtract unsupported types"),
+        Expr::GE(left, right) => {
+            match (eval_spanned_expr(left, var_map, amb).await?, eval_spanned_expr(right, var_map, amb).await?) {
+                (Bool(left), Bool(right)) => Bool(left >= right),
+                (String(left), String(right)) => Bool(left >= right),
+                (Number(left), Number(right)) => Bool(left >= right),
+                _ => Bool(false),
             }
         }
+        Expr::Add(left, right) => {
+            add_values(eval_spanned_expr(left, var_map, amb).await?, eval_spanned_expr(right, var_map, amb).await?)?
+        }
+        Expr::Sub(left, right) => {
+            sub_values(eval_spanned_expr(left, var_map, amb).await?, eval_spanned_expr(right, var_map, amb).await?)?
+        }
         Expr::Mul(left, right) => {
-            match (eval_expr(left, var_map, amb).await, eval_expr(right, var_map, amb).await) {
+            match (eval_spanned_expr(left, var_map, amb).await?, eval_spanned_expr(right, var_map, amb).await?) {
                 (Number(left), Number(right)) => Number(left * right),
-                _ => panic!("cannot multiply unsupported types"),
+                (String(string), Number(count)) | (Number(count), String(string)) => repeat_string(&string, count)?,
+                (left, right) => return Err(binary_type_error("multiply", &left, &right)),
+            }
+        }
+        Expr::Mod(left, right) => {
+            match (eval_spanned_expr(left, var_map, amb).await?, eval_spanned_expr(right, var_map, amb).await?) {
+                (Number(left), Number(right)) => {
+                    if right == 0.0 { return Err(RuntimeError::new("division by zero")); }
+                    Number(left % right)
+                }
+                (left, right) => return Err(binary_type_error("calculate the remainder of", &left, &right)),
             }
         }
         Expr::Div(left, right) => {
-            match (eval_expr(left, var_map, amb).await, eval_expr(right, var_map, amb).await) {
+            match (eval_spanned_expr(left, var_map, amb).await?, eval_spanned_expr(right, var_map, amb).await?) {
                 (Number(left), Number(right)) => {
-                    if right == 0.0 { panic!("error: division by zero"); }
+                    if right == 0.0 { return Err(RuntimeError::new("division by zero")); }
                     Number(left / right)
                 }
-                _ => panic!("cannot multiply unsupported types"),
+                (left, right) => return Err(binary_type_error("divide", &left, &right)),
             }
         }
         Expr::Neg(expr) => {
-            match eval_expr(expr, var_map, amb).await {
+            match eval_spanned_expr(expr, var_map, amb).await? {
                 Bool(val) => { Bool(!val) }
-                _ => panic!("cannot negate unsupported type"),
+                val => return Err(RuntimeError::new(format!("cannot negate {}", val.type_name()))),
             }
         }
+        Expr::Minus(expr) => {
+            match eval_spanned_expr(expr, var_map, amb).await? {
+                Number(val) => Number(-val),
+                val => return Err(RuntimeError::new(format!("cannot negate {}", val.type_name()))),
+            }
+        }
+        // the right side is only evaluated if it decides the result
         Expr::And(left, right) => {
-            match (eval_expr(left, var_map, amb).await, eval_expr(right, var_map, amb).await) {
-                (Bool(left), Bool(right)) => Bool(left == right),
-                _ => panic!("cannot perform \"and\" operation on unsupported types"),
+            match eval_spanned_expr(left, var_map, amb).await? {
+                Bool(false) => Bool(false),
+                Bool(true) => match eval_spanned_expr(right, var_map, amb).await? {
+                    Bool(right) => Bool(right),
+                    right => return Err(binary_type_error("perform \"and\" operation on", &Bool(true), &right)),
+                },
+                left => return Err(RuntimeError::new(format!("cannot perform \"and\" operation on {}", left.type_name()))),
             }
         }
         Expr::Or(left, right) => {
-            match (eval_expr(left, var_map, amb).await, eval_expr(right, var_map, amb).await) {
-                (Bool(left), Bool(right)) => Bool(left || right),
-                _ => panic!("cannot perform \"or\" operation on unsupported types"),
+            match eval_spanned_expr(left, var_map, amb).await? {
+                Bool(true) => Bool(true),
+                Bool(false) => match eval_spanned_expr(right, var_map, amb).await? {
+                    Bool(right) => Bool(right),
+                    right => return Err(binary_type_error("perform \"or\" operation on", &Bool(false), &right)),
+                },
+                left => return Err(RuntimeError::new(format!("cannot perform \"or\" operation on {}", left.type_name()))),
             }
         }
         Expr::Init(var_name, value) => {
-            let value = eval_expr(value, var_map, amb).await;
+            let value = eval_spanned_expr(value, var_map, amb).await?;
 
             var_map.lock().unwrap().scope_values.insert(var_name.clone(), value);
-            return ValueType::Void;
+            ValueType::Void
         }
         Expr::Assign(var_name, value) => {
-            let value = eval_expr(value, var_map, amb).await;
-
-            let mut map = var_map.clone();
-            loop {
-                let tmp;
-                let mut map_guard = map.lock().unwrap();
-                match map_guard.scope_values.get_mut(var_name) {
-                    Some(v) => {
-                        *v = value;
-                        break;
-                    }
-                    None => match &map_guard.parent {
-                        Some(parent) => tmp = parent.clone(),
-                        None => { panic!("variable '{}' does not exist", var_name); }
-                    }
-                }
-                drop(map_guard);
-                map = tmp;
-            }
+            let value = eval_spanned_expr(value, var_map, amb).await?;
+            set_var(var_map, var_name, value)?;
+            ValueType::Void
+        }
+        Expr::AddAssign(var_name, value) => {
+            let value = eval_spanned_expr(value, var_map, amb).await?;
+            let current = get_var(var_map, var_name).ok_or_else(|| undefined_var_error(var_name))?;
+            set_var(var_map, var_name, add_values(current, value)?)?;
+            ValueType::Void
+        }
+        Expr::SubAssign(var_name, value) => {
+            let value = eval_spanned_expr(value, var_map, amb).await?;
+            let current = get_var(var_map, var_name).ok_or_else(|| undefined_var_error(var_name))?;
+            set_var(var_map, var_name, sub_values(current, value)?)?;
+            ValueType::Void
+        }
+        Expr::Inc(var_name) => {
+            let current = get_var(var_map, var_name).ok_or_else(|| undefined_var_error(var_name))?;
+            set_var(var_map, var_name, add_values(current, Number(1.0))?)?;
             ValueType::Void
         }
         Expr::KeyMapping(mappings) => {
@@ -189,50 +417,89 @@ pub(crate) async fn eval_expr<'a>(expr: &Expr, var_map: &GuardedVarMap, amb: &mu
                 let mapping = mapping.clone();
 
                 amb.message_tx.borrow_mut().as_ref().unwrap()
-                    .send(ExecutionMessage::AddMapping(amb.window_cycle_token, mapping.from, mapping.to, var_map.clone())).await
+                    .send(ExecutionMessage::AddMapping(amb.window_cycle_token, amb.layer.clone(), amb.condition.clone(), mapping.from, mapping.to, var_map.clone())).await
                     .unwrap();
             }
 
-            return ValueType::Void;
+            ValueType::Void
         }
-        Expr::Name(var_name) => {
-            let mut value = None;
-            let mut map = var_map.clone();
+        Expr::TapHold(mapping) => {
+            ensure_unscoped("tap-hold keys", amb)?;
+            amb.message_tx.borrow_mut().as_ref().unwrap()
+                .send(ExecutionMessage::AddTapHold(amb.window_cycle_token, mapping.clone())).await
+                .unwrap();
 
-            loop {
-                let tmp;
-                let map_guard = map.lock().unwrap();
-                match map_guard.scope_values.get(var_name) {
-                    Some(v) => {
-                        value = Some(v.clone());
-                        break;
-                    }
-                    None => match &map_guard.parent {
-                        Some(parent) => tmp = parent.clone(),
-                        None => { break; }
-                    }
-                }
-                drop(map_guard);
-                map = tmp;
-            }
+            ValueType::Void
+        }
+        Expr::Combo(keys, block) => {
+            ensure_unscoped("combos", amb)?;
+            let combo = ComboMapping { keys: keys.iter().cloned().collect(), target: Arc::new((block.clone(), var_map.clone())) };
 
-            match value {
-                Some(value) => value,
-                None => ValueType::Void,
-            }
+            amb.message_tx.borrow_mut().as_ref().unwrap()
+                .send(ExecutionMessage::AddCombo(amb.window_cycle_token, combo)).await
+                .unwrap();
+
+            ValueType::Void
+        }
+        Expr::SequenceMapping(sequence, block) => {
+            ensure_unscoped("sequence triggers", amb)?;
+            amb.message_tx.borrow_mut().as_ref().unwrap()
+                .send(ExecutionMessage::AddSequence(amb.window_cycle_token, sequence.clone(), block.clone(), var_map.clone())).await
+                .unwrap();
+
+            ValueType::Void
         }
+        Expr::Name(var_name) => get_var(var_map, var_name).unwrap_or(ValueType::Void),
         Expr::Value(value) => {
-            return value.clone();
+            value.clone()
+        }
+        Expr::List(values) => {
+            let mut list = vec![];
+            for value in values { list.push(eval_spanned_expr(value, var_map, amb).await?); }
+            new_list(list)
+        }
+        Expr::Map(entries) => {
+            let mut map = BTreeMap::new();
+            for (key, value) in entries { map.insert(key.clone(), eval_spanned_expr(value, var_map, amb).await?); }
+            new_map(map)
+        }
+        Expr::Index(container, index) => {
+            let container = eval_spanned_expr(container, var_map, amb).await?;
+            let index = eval_spanned_expr(index, var_map, amb).await?;
+            get_index(&container, &index)?
+        }
+        Expr::Interpolation(parts) => {
+            let mut string = std::string::String::new();
+            for part in parts {
+                string.push_str(&eval_spanned_expr(part, var_map, amb).await?.to_string());
+            }
+            String(string)
+        }
+        Expr::Range(start, end, inclusive) => {
+            let (start, end) = eval_range_bounds(start, end, var_map, amb).await?;
+            let mut values = range_values(start, end, *inclusive).map(Number);
+            let list: Vec<ValueType> = values.by_ref().take(MAX_RANGE_LEN).collect();
+            if values.next().is_some() {
+                return Err(RuntimeError::new(format!("cannot create a list from a range of more than {} numbers", MAX_RANGE_LEN)));
+            }
+            new_list(list)
+        }
+        Expr::IndexAssign(container, index, value) => {
+            let container = eval_spanned_expr(container, var_map, amb).await?;
+            let index = eval_spanned_expr(index, var_map, amb).await?;
+            let value = eval_spanned_expr(value, var_map, amb).await?;
+            set_index(&container, &index, value)?;
+            ValueType::Void
         }
         Expr::Lambda(params, block) => {
             let lambda_var_map = GuardedVarMap::new(Mutex::new(VarMap::new(Some(var_map.clone()))));
-            return ValueType::Lambda(params.clone(), block.clone(), lambda_var_map);
+            ValueType::Lambda(params.clone(), block.clone(), lambda_var_map)
         }
         Expr::KeyAction(action) => {
             amb.ev_writer_tx.send(action.to_input_ev()).await.unwrap();
             amb.ev_writer_tx.send(SYN_REPORT.clone()).await.unwrap();
 
-            return ValueType::Void;
+            ValueType::Void
         }
         // Expr::EatKeyAction(action) => {
         //     match &amb.message_tx {
@@ -243,14 +510,16 @@ pub(crate) async fn eval_expr<'a>(expr: &Expr, var_map: &GuardedVarMap, amb: &mu
         // }
         Expr::SleepAction(duration) => {
             tokio::time::sleep(*duration).await;
-            return ValueType::Void;
+            ValueType::Void
         }
         Expr::FunctionCall(name, args) => {
             match evaluate_builtin(name, args, var_map, amb).await {
-                Ok(v) => v,
+                Ok(value) => value,
                 Err(err) => {
-                    throw_error(err, 1, amb).await;
-                    ValueType::Void
+                    let mut err = RuntimeError::from(err);
+                    // nested errors (i.e. in lambdas) already point to their origin
+                    if err.span.is_none() { err.message = format!("{}: {}", name, err.message); }
+                    return Err(err);
                 }
             }
         }
@@ -293,9 +562,16 @@ pub(crate) async fn eval_expr<'a>(expr: &Expr, var_map: &GuardedVarMap, amb: &mu
 
             // TODO eat keys we just released, un-eat keys we just restored
 
-            return ValueType::Void;
+            ValueType::Void
         }
-    }
+    };
+
+    Ok(value)
+}
+
+/// Evaluates a nested expression, errors without a location point to it.
+pub(crate) async fn eval_spanned_expr(expr: &Spanned<Expr>, var_map: &GuardedVarMap, amb: &mut Ambient<'_>) -> RuntimeResult<ValueType> {
+    eval_expr(expr, var_map, amb).await.map_err(|err| err.with_span(expr.span))
 }
 
 pub type SleepSender = tokio::sync::mpsc::Sender<Block>;
@@ -305,76 +581,182 @@ pub struct Ambient<'a> {
     pub message_tx: Option<&'a mut ExecutionMessageSender>,
     pub window_cycle_token: usize,
     pub modifier_state: &'a KeyModifierState,
+    /// the layer new mappings are added to
+    pub layer: Option<String>,
+    /// the condition new mappings are restricted to
+    pub condition: KeyActionCondition,
 }
 
 pub enum BlockRet {
     None,
     Continue,
+    Break,
     Return(ValueType),
 }
 
 #[async_recursion]
-pub async fn eval_block<'a>(block: &Block, var_map: &GuardedVarMap, amb: &mut Ambient<'a>) -> BlockRet {
-    let mut var_map = GuardedVarMap::new(Mutex::new(VarMap::new(Some(var_map.clone()))));
-
-    'outer: for stmt in &block.statements {
-        match stmt {
-            Stmt::Expr(expr) => { eval_expr(expr, &var_map, amb).await; }
-            Stmt::Block(nested_block) => {
-                let ret = eval_block(nested_block, &mut var_map, amb).await;
-                match ret {
-                    BlockRet::None => {}
-                    _ => return ret,
-                };
-            }
-            Stmt::If(if_else_if_pairs, else_pair) => {
-                for (expr, block) in if_else_if_pairs {
-                    if eval_expr(expr, &mut var_map, amb).await == ValueType::Bool(true) {
-                        let ret = eval_block(block, &mut var_map, amb).await;
-                        match ret {
-                            BlockRet::None => {}
-                            _ => return ret,
-                        };
-                        continue 'outer;
-                    }
-                }
-                if let Some(block) = else_pair {
-                    let ret = eval_block(block, &mut var_map, amb).await;
-                    match ret {
-                        BlockRet::None => {}
-                        _ => return ret,
-                    };
+pub async fn eval_block<'a>(block: &Block, var_map: &GuardedVarMap, amb: &mut Ambient<'a>) -> RuntimeResult<BlockRet> {
+    let var_map = GuardedVarMap::new(Mutex::new(VarMap::new(Some(var_map.clone()))));
+
+    for stmt in &block.statements {
+        match eval_stmt(&stmt.node, &var_map, amb).await.map_err(|err| err.with_span(stmt.span))? {
+            BlockRet::None => {}
+            ret => return Ok(ret),
+        }
+    }
+
+    Ok(BlockRet::None)
+}
+
+#[async_recursion]
+async fn eval_stmt<'a>(stmt: &Stmt, var_map: &GuardedVarMap, amb: &mut Ambient<'a>) -> RuntimeResult<BlockRet> {
+    match stmt {
+        Stmt::Expr(expr) => { eval_expr(expr, var_map, amb).await?; }
+        Stmt::Block(nested_block) => {
+            return eval_block(nested_block, var_map, amb).await;
+        }
+        Stmt::If(if_else_if_pairs, else_pair) => {
+            for (expr, block) in if_else_if_pairs {
+                if eval_spanned_expr(expr, var_map, amb).await? == ValueType::Bool(true) {
+                    return eval_block(block, var_map, amb).await;
                 }
             }
-            Stmt::For(init_expr, termination_expr, advance_expr, block) => {
-                eval_expr(init_expr, &var_map, amb).await;
+            if let Some(block) = else_pair {
+                return eval_block(block, var_map, amb).await;
+            }
+        }
+        Stmt::For(init_expr, termination_expr, advance_expr, block) => {
+            eval_spanned_expr(init_expr, var_map, amb).await?;
 
-                loop {
-                    let should_continue = match eval_expr(termination_expr, &var_map, amb).await {
-                        ValueType::Bool(v) => v,
-                        _ => panic!("termination condition in for loop needs to return a boolean"),
-                    };
-                    if !should_continue { break; }
+            loop {
+                let should_continue = match eval_spanned_expr(termination_expr, var_map, amb).await? {
+                    ValueType::Bool(v) => v,
+                    _ => return Err(RuntimeError::new("termination condition in for loop needs to return a boolean")),
+                };
+                if !should_continue { break; }
 
-                    let ret = eval_block(block, &mut var_map, amb).await;
-                    match ret {
-                        BlockRet::Return(_) => return ret,
-                        _ => {}
-                    };
+                match eval_block(block, var_map, amb).await? {
+                    BlockRet::Break => break,
+                    ret @ BlockRet::Return(_) => return Ok(ret),
+                    BlockRet::None | BlockRet::Continue => {}
+                };
 
-                    eval_expr(advance_expr, &var_map, amb).await;
+                eval_spanned_expr(advance_expr, var_map, amb).await?;
+            }
+        }
+        Stmt::ForIn(var_name, iterable, block) => {
+            let values: Box<dyn Iterator<Item=ValueType> + Send> = match &iterable.node {
+                // ranges don't need to fit into a list
+                Expr::Range(start, end, inclusive) => {
+                    let (start, end) = eval_range_bounds(start, end, var_map, amb).await.map_err(|err| err.with_span(iterable.span))?;
+                    Box::new(range_values(start, end, *inclusive).map(ValueType::Number))
                 }
+                // iterate over a snapshot, the body may modify the collection
+                _ => match eval_spanned_expr(iterable, var_map, amb).await? {
+                    ValueType::List(list) => Box::new(list.lock().unwrap().clone().into_iter()),
+                    ValueType::Map(map) => Box::new(map.lock().unwrap().keys().map(|key| ValueType::String(key.clone())).collect::<Vec<_>>().into_iter()),
+                    ValueType::String(string) => Box::new(string.chars().map(|ch| ValueType::String(ch.to_string())).collect::<Vec<_>>().into_iter()),
+                    value => return Err(RuntimeError::new(format!("cannot iterate over a {}", value.type_name()))
+                        .with_span(iterable.span)),
+                },
+            };
+
+            for value in values {
+                let iteration_var_map = GuardedVarMap::new(Mutex::new(VarMap::new(Some(var_map.clone()))));
+                iteration_var_map.lock().unwrap().scope_values.insert(var_name.clone(), value);
+
+                match eval_block(block, &iteration_var_map, amb).await? {
+                    BlockRet::Break => break,
+                    ret @ BlockRet::Return(_) => return Ok(ret),
+                    BlockRet::None | BlockRet::Continue => {}
+                };
             }
-            Stmt::Return(expr) => {
-                return BlockRet::Return(eval_expr(expr, &var_map, amb).await);
+        }
+        Stmt::While(condition, block) => {
+            loop {
+                match eval_spanned_expr(condition, var_map, amb).await? {
+                    ValueType::Bool(true) => {}
+                    ValueType::Bool(false) => break,
+                    _ => return Err(RuntimeError::new("condition in while loop needs to return a boolean").with_span(condition.span)),
+                };
+
+                match eval_block(block, var_map, amb).await? {
+                    BlockRet::Break => break,
+                    ret @ BlockRet::Return(_) => return Ok(ret),
+                    BlockRet::None | BlockRet::Continue => {}
+                };
             }
-            Stmt::Continue => {
-                return BlockRet::Continue;
+        }
+        Stmt::Loop(block) => {
+            loop {
+                match eval_block(block, var_map, amb).await? {
+                    BlockRet::Break => break,
+                    ret @ BlockRet::Return(_) => return Ok(ret),
+                    BlockRet::None | BlockRet::Continue => {}
+                };
             }
         }
+        Stmt::Return(expr) => {
+            return Ok(BlockRet::Return(eval_spanned_expr(expr, var_map, amb).await?));
+        }
+        Stmt::Continue => {
+            return Ok(BlockRet::Continue);
+        }
+        Stmt::Break => {
+            return Ok(BlockRet::Break);
+        }
+        Stmt::Import(import) => {
+            let (module, namespace) = match (&import.module, &import.namespace) {
+                (Some(module), Some(namespace)) => (module, namespace),
+                _ => return Err(RuntimeError::new(format!("script '{}' was not loaded, imports are only allowed at the top level", import.path))),
+            };
+
+            // scripts that are imported more than once only run the first time, all imports share their scope
+            let cached_scope = module.scope.lock().unwrap().clone();
+            let module_var_map = match cached_scope {
+                Some(module_var_map) => module_var_map,
+                None => {
+                    // the top level of the imported script gets a scope of its own so the exported variables can be found
+                    let module_var_map = GuardedVarMap::new(Mutex::new(VarMap::new(None)));
+                    *module.scope.lock().unwrap() = Some(module_var_map.clone());
+                    for stmt in &module.block.statements {
+                        match eval_stmt(&stmt.node, &module_var_map, amb).await.map_err(|err| err.with_span(stmt.span))? {
+                            BlockRet::None => {}
+                            _ => break,
+                        }
+                    }
+                    module_var_map
+                }
+            };
+
+            var_map.lock().unwrap().namespaces.insert(namespace.clone(), (module.clone(), module_var_map));
+        }
+        Stmt::Export(expr) => { eval_expr(expr, var_map, amb).await?; }
     }
 
-    BlockRet::None
+    Ok(BlockRet::None)
+}
+
+/// Calls a lambda with already evaluated arguments, missing arguments are void.
+pub async fn call_lambda(params: &[String], block: &Block, var_map: &GuardedVarMap, args: Vec<ValueType>, amb: &mut Ambient<'_>) -> RuntimeResult<ValueType> {
+    // we need to clone the lambda's var_map since each lambda execution needs to not affect the next one
+    // TODO make GuardedVarMap a proper struct and implement a proper deep clone method
+    let lambda_var_map = GuardedVarMap::new(Mutex::new(VarMap::new(
+        var_map.lock().unwrap().parent.clone()
+    )));
+
+    let mut args = args.into_iter();
+    for param in params {
+        let val = args.next().unwrap_or(ValueType::Void);
+        eval_expr(&Expr::Init(param.clone(), Box::new(Expr::Value(val).into())), &lambda_var_map, amb).await?;
+    }
+
+    match eval_block(block, &lambda_var_map, amb).await? {
+        BlockRet::Return(ret) => Ok(ret),
+        BlockRet::Continue => Err(RuntimeError::new("function cannot return a continue statement")),
+        BlockRet::Break => Err(RuntimeError::new("function cannot return a break statement")),
+        BlockRet::None => Ok(ValueType::Void),
+    }
 }
 
 fn arc_mutexes_are_equal<T>(first: &Arc<Mutex<T>>, second: &Arc<Mutex<T>>) -> bool
@@ -382,7 +764,7 @@ fn arc_mutexes_are_equal<T>(first: &Arc<Mutex<T>>, second: &Arc<Mutex<T>>) -> bo
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Block {
-    pub(crate) statements: Vec<Stmt>,
+    pub(crate) statements: Vec<Spanned<Stmt>>,
 }
 
 impl Block {
@@ -394,28 +776,50 @@ impl Block {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
-    Eq(Box<Expr>, Box<Expr>),
-    Neq(Box<Expr>, Box<Expr>),
-    LT(Box<Expr>, Box<Expr>),
-    GT(Box<Expr>, Box<Expr>),
-    // Inc(Expr),
-    // Dec(Expr),
-    Add(Box<Expr>, Box<Expr>),
-    Sub(Box<Expr>, Box<Expr>),
-    Div(Box<Expr>, Box<Expr>),
-    Mul(Box<Expr>, Box<Expr>),
-    Neg(Box<Expr>),
-    And(Box<Expr>, Box<Expr>),
-    Or(Box<Expr>, Box<Expr>),
-    Init(String, Box<Expr>),
-    Assign(String, Box<Expr>),
+    Eq(Box<Spanned<Expr>>, Box<Spanned<Expr>>),
+    Neq(Box<Spanned<Expr>>, Box<Spanned<Expr>>),
+    LT(Box<Spanned<Expr>>, Box<Spanned<Expr>>),
+    GT(Box<Spanned<Expr>>, Box<Spanned<Expr>>),
+    LE(Box<Spanned<Expr>>, Box<Spanned<Expr>>),
+    GE(Box<Spanned<Expr>>, Box<Spanned<Expr>>),
+    Add(Box<Spanned<Expr>>, Box<Spanned<Expr>>),
+    Sub(Box<Spanned<Expr>>, Box<Spanned<Expr>>),
+    Div(Box<Spanned<Expr>>, Box<Spanned<Expr>>),
+    Mul(Box<Spanned<Expr>>, Box<Spanned<Expr>>),
+    Mod(Box<Spanned<Expr>>, Box<Spanned<Expr>>),
+    /// logical not
+    Neg(Box<Spanned<Expr>>),
+    /// unary minus
+    Minus(Box<Spanned<Expr>>),
+    And(Box<Spanned<Expr>>, Box<Spanned<Expr>>),
+    Or(Box<Spanned<Expr>>, Box<Spanned<Expr>>),
+    Init(String, Box<Spanned<Expr>>),
+    Assign(String, Box<Spanned<Expr>>),
+    /// `a += value`
+    AddAssign(String, Box<Spanned<Expr>>),
+    /// `a -= value`
+    SubAssign(String, Box<Spanned<Expr>>),
+    /// `a++`
+    Inc(String),
+    /// `container[index] = value`
+    IndexAssign(Box<Spanned<Expr>>, Box<Spanned<Expr>>, Box<Spanned<Expr>>),
     KeyMapping(Vec<KeyMapping>),
+    TapHold(TapHoldMapping),
+    Combo(Vec<Key>, Block),
+    SequenceMapping(Vec<KeyClickActionWithMods>, Block),
 
     Name(String),
     Value(ValueType),
+    List(Vec<Spanned<Expr>>),
+    Map(Vec<(String, Spanned<Expr>)>),
+    /// a string with `${expr}` parts, the values are concatenated
+    Interpolation(Vec<Spanned<Expr>>),
+    Index(Box<Spanned<Expr>>, Box<Spanned<Expr>>),
+    /// `start..end`, the end is included if the flag is set
+    Range(Box<Spanned<Expr>>, Box<Spanned<Expr>>, bool),
     Lambda(Vec<String>, Block),
 
-    FunctionCall(String, Vec<Expr>),
+    FunctionCall(String, Vec<Spanned<Expr>>),
 
     KeyAction(KeyAction),
     SleepAction(time::Duration),
@@ -430,9 +834,98 @@ pub enum Expr {
 pub(crate) enum Stmt {
     Expr(Expr),
     Block(Block),
-    If(Vec<(Expr, Block)>, Option<Block>),
-    For(Expr, Expr, Expr, Block),
-    // While
-    Return(Expr),
+    If(Vec<(Spanned<Expr>, Block)>, Option<Block>),
+    For(Spanned<Expr>, Spanned<Expr>, Spanned<Expr>, Block),
+    ForIn(String, Spanned<Expr>, Block),
+    While(Spanned<Expr>, Block),
+    Loop(Block),
+    Return(Spanned<Expr>),
     Continue,
-}
\ No newline at end of file
+    Break,
+    Import(Import),
+    /// `export let name = value;`, only used at the top level of imported scripts
+    Export(Expr),
+}
+
+/// `import "path" as namespace;`, the imported script is loaded before the evaluation starts.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Import {
+    pub(crate) path: String,
+    /// defaults to the file name without the extension
+    pub(crate) namespace: Option<String>,
+    pub(crate) module: Option<Arc<Module>>,
+}
+
+/// A script loaded by an import statement.
+#[derive(Debug)]
+pub(crate) struct Module {
+    pub(crate) block: Block,
+    /// the variables initialized by top level `export let` statements
+    pub(crate) exports: Vec<String>,
+    /// the top level scope of the script, set once the first import of it runs
+    pub(crate) scope: Mutex<Option<GuardedVarMap>>,
+}
+
+impl PartialEq for Module {
+    fn eq(&self, other: &Self) -> bool {
+        self.block == other.block && self.exports == other.exports
+    }
+}
+
+impl Module {
+    pub(crate) fn new(block: Block) -> Self {
+        let exports = block.statements.iter()
+            .filter_map(|stmt| match &stmt.node {
+                Stmt::Export(Expr::Init(name, _)) => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+        Module { block, exports, scope: Default::default() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeat_string() {
+        assert_eq!(repeat_string("ab", 3.0).unwrap(), ValueType::String("ababab".to_string()));
+        assert_eq!(repeat_string("ab", -1.0).unwrap_err().to_string(), "cannot repeat a string -1 times");
+        assert_eq!(repeat_string("ab", 1e20).unwrap_err().to_string(),
+                   "cannot repeat a string 100000000000000000000 times, the result would be too long");
+        assert!(repeat_string("ab", 1e8).is_err());
+    }
+
+    async fn eval_scoped(expr: Expr, layer: Option<String>, condition: KeyActionCondition) -> RuntimeResult<ValueType> {
+        let (ev_writer_tx, _ev_writer_rx) = mpsc::channel(8);
+        let mut amb = Ambient {
+            ev_writer_tx,
+            message_tx: None,
+            window_cycle_token: 0,
+            modifier_state: &KeyModifierState::new(),
+            layer,
+            condition,
+        };
+        eval_expr(&expr, &GuardedVarMap::new(Mutex::new(VarMap::new(None))), &mut amb).await
+    }
+
+    #[tokio::test]
+    async fn test_scoped_mappings() {
+        let device_condition = KeyActionCondition { device: Some(DeviceSelector::parse("name:Macro Pad").unwrap()), ..Default::default() };
+
+        let tap_hold = Expr::TapHold(TapHoldMapping::new(*KEY_CAPSLOCK, KeyClickActionWithMods::new(*KEY_ESC),
+                                                         KeyClickActionWithMods::new(*KEY_LEFT_CTRL)));
+        assert_eq!(eval_scoped(tap_hold.clone(), Some("navigation".to_string()), Default::default()).await.unwrap_err().to_string(),
+                   "tap-hold keys can't be declared inside of layer, device or window callbacks");
+        assert!(eval_scoped(tap_hold, None, device_condition.clone()).await.is_err());
+
+        let combo = Expr::Combo(vec![*KEY_J, *KEY_K], Block::new());
+        assert_eq!(eval_scoped(combo.clone(), None, device_condition.clone()).await.unwrap_err().to_string(),
+                   "combos can't be declared inside of layer, device or window callbacks");
+
+        let sequence = Expr::SequenceMapping(vec![KeyClickActionWithMods::new(*KEY_G), KeyClickActionWithMods::new(*KEY_S)], Block::new());
+        assert_eq!(eval_scoped(sequence, Some("navigation".to_string()), Default::default()).await.unwrap_err().to_string(),
+                   "sequence triggers can't be declared inside of layer, device or window callbacks");
+    }
+}
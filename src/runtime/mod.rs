@@ -1,3 +1,5 @@
+pub mod collections;
 pub mod evaluation;
-mod builtin_functions;
+pub mod runtime_error;
+pub(crate) mod builtin_functions;
 
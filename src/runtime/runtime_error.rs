@@ -0,0 +1,123 @@
+use std::fmt;
+
+use crate::*;
+use crate::messaging::*;
+use crate::script::ScriptFile;
+
+/// An error that occurred while evaluating a script, i.e. a type mismatch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub message: String,
+    /// the innermost statement that failed, if it is known
+    pub span: Option<Span>,
+}
+
+pub type RuntimeResult<T> = std::result::Result<T, RuntimeError>;
+
+impl RuntimeError {
+    pub fn new(message: impl Into<String>) -> Self {
+        RuntimeError { message: message.into(), span: None }
+    }
+
+    /// Renders the error together with the file and code it points to if the location is known.
+    pub fn render(&self, files: &[ScriptFile]) -> String {
+        match self.span.and_then(|span| files.get(span.file).map(|file| (span, file))) {
+            Some((span, file)) if span.end <= file.source.len() =>
+                span.render(&file.source, Some(&file.path.display().to_string()), &self.message),
+            _ => format!("error: {}", self),
+        }
+    }
+
+    /// Sets the location of the error unless a more specific one was set already.
+    pub fn with_span(mut self, span: Span) -> Self {
+        if self.span.is_none() && span.is_known() { self.span = Some(span); }
+        self
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.span {
+            Some(span) => write!(f, "{}: {}", span, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+impl From<anyhow::Error> for RuntimeError {
+    fn from(err: anyhow::Error) -> Self {
+        // errors of nested evaluations (i.e. lambdas called by builtins) keep their location
+        match err.downcast::<RuntimeError>() {
+            Ok(err) => err,
+            Err(err) => RuntimeError::new(err.to_string()),
+        }
+    }
+}
+
+/// Reports the error of a top level evaluation (i.e. a mapping block) to the main loop.
+pub async fn report_runtime_error<T>(message_tx: &ExecutionMessageSender, ret: RuntimeResult<T>) {
+    if let Err(err) = ret {
+        let _ = message_tx.send(ExecutionMessage::RuntimeError(err)).await;
+    }
+}
+
+/// What happens after a runtime error got reported.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorPolicy {
+    /// only the failed block is aborted, the script keeps running
+    Continue,
+    /// the script exits
+    Abort,
+}
+
+impl Default for ErrorPolicy {
+    fn default() -> Self { ErrorPolicy::Continue }
+}
+
+impl std::str::FromStr for ErrorPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw {
+            "continue" => Ok(ErrorPolicy::Continue),
+            "abort" => Ok(ErrorPolicy::Abort),
+            _ => Err(anyhow!("invalid error policy '{}', expected 'continue' or 'abort'", raw)),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runtime_error() {
+        let span = Span { start: 4, end: 9, line: 2, column: 3, file: 0 };
+        let err = RuntimeError::new("division by zero").with_span(span);
+        assert_eq!(err.to_string(), "line 2, column 3: division by zero");
+
+        // the innermost location wins
+        let outer = Span { start: 0, end: 20, line: 1, column: 1, file: 0 };
+        assert_eq!(err.clone().with_span(outer).span.unwrap().line, 2);
+
+        // the location survives builtins that return anyhow errors
+        assert_eq!(RuntimeError::from(anyhow::Error::new(err.clone())), err);
+        assert_eq!(RuntimeError::from(anyhow!("oops")).to_string(), "oops");
+    }
+
+    #[test]
+    fn test_render() {
+        let files = vec![
+            ScriptFile { path: "main.m2".into(), source: "import \"common.m2\";\n".to_string() },
+            ScriptFile { path: "common.m2".into(), source: "let a = 1;\nlet b = 1 / 0;\n".to_string() },
+        ];
+        let span = Span { file: 1, ..Span::at(&files[1].source, 19, 24) };
+        let err = RuntimeError::new("division by zero").with_span(span);
+        assert_eq!(err.render(&files), "error: division by zero\n --> common.m2, line 2, column 9\n  |\n2 | let b = 1 / 0;\n  |         ^^^^^");
+
+        assert_eq!(RuntimeError::new("division by zero").render(&files), "error: division by zero");
+    }
+}
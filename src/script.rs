@@ -1,29 +1,144 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use itertools::Itertools;
+use notify::{DebouncedEvent, Watcher};
+use unicode_xid::UnicodeXID;
+use xdg::BaseDirectories;
+
 use crate::*;
-use std::io::{Read, Seek, SeekFrom};
 use crate::messaging::ExecutionMessage;
 
 
-pub fn parse_script(script_file: &mut fs::File) -> Block {
-    let script_file_length = script_file.seek(SeekFrom::End(0))
-        .map_err(|err| anyhow!("failed seek operation on script file: {}", err))
-        .unwrap();
+/// A loaded script, spans refer to it by its index in the list of loaded scripts.
+#[derive(Debug, Clone)]
+pub struct ScriptFile {
+    pub path: PathBuf,
+    pub source: String,
+}
+
+/// Loads the script and the scripts it imports, the main script comes first in the list of loaded scripts.
+///
+/// Imports are resolved relative to the importing script, followed by the `map2` directory in the XDG config
+/// directories (i.e. `~/.config/map2`).
+pub fn load_script(path: &Path) -> Result<(Block, Vec<ScriptFile>)> {
+    let mut loader = ScriptLoader::default();
+    let block = loader.load(path.to_path_buf())?;
+    Ok((block, loader.files))
+}
+
+#[derive(Default)]
+struct ScriptLoader {
+    files: Vec<ScriptFile>,
+    /// the canonical paths and indices of the scripts that are currently being loaded, used to detect import cycles
+    loading: Vec<(PathBuf, usize)>,
+    /// scripts that are imported more than once are only parsed once
+    modules: HashMap<PathBuf, Arc<Module>>,
+}
+
+impl ScriptLoader {
+    fn load(&mut self, path: PathBuf) -> Result<Block> {
+        let source = fs::read_to_string(&path)
+            .map_err(|err| anyhow!("failed to read script file '{}': {}", path.display(), err))?;
+        let canonical_path = path.canonicalize().unwrap_or_else(|_| path.clone());
+
+        let file = self.files.len();
+        self.files.push(ScriptFile { path, source });
+        let mut block = parsing::parser::parse_script(&self.files[file], file)?;
+
+        self.loading.push((canonical_path, file));
+        for stmt in block.statements.iter_mut() {
+            let span = stmt.span;
+            if let Stmt::Import(import) = &mut stmt.node {
+                self.resolve_import(import, file, span)?;
+            }
+        }
+        self.loading.pop();
 
-    // restore head
-    script_file.seek(SeekFrom::Start(0))
-        .map_err(|err| anyhow!("failed seek operation on script file: {}", err))
-        .unwrap();
+        Ok(block)
+    }
 
-    let mut raw = String::with_capacity(script_file_length as usize);
-    script_file.read_to_string(&mut raw)
-        .map_err(|err| anyhow!("failed to read script file: {}", err))
-        .unwrap();
+    fn resolve_import(&mut self, import: &mut Import, file: usize, span: Span) -> Result<()> {
+        let error = |files: &[ScriptFile], message: String| anyhow!("{}", RuntimeError::new(message).with_span(span).render(files));
 
-    let global = parsing::parser::parse_script(&*raw).unwrap();
+        let path = self.find_import(&self.files[file].path, &import.path)
+            .ok_or_else(|| error(&self.files, format!("cannot find imported script '{}'", import.path)))?;
+        let canonical_path = path.canonicalize().unwrap_or_else(|_| path.clone());
 
-    global
+        if let Some(idx) = self.loading.iter().position(|(loading_path, _)| *loading_path == canonical_path) {
+            let cycle = self.loading[idx..].iter()
+                .map(|(_, file)| self.files[*file].path.display().to_string())
+                .chain(std::iter::once(path.display().to_string()))
+                .join(" -> ");
+            return Err(error(&self.files, format!("import cycle: {}", cycle)));
+        }
+
+        if import.namespace.is_none() {
+            let name = path.file_stem().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+            if !is_identifier(&name) {
+                return Err(error(&self.files, format!("'{}' can't be used as a namespace, name it using 'import \"{}\" as name;'", name, import.path)));
+            }
+            import.namespace = Some(name);
+        }
+
+        let module = match self.modules.get(&canonical_path) {
+            Some(module) => module.clone(),
+            None => {
+                let block = self.load(path)?;
+                let module = Arc::new(Module::new(block));
+                self.modules.insert(canonical_path, module.clone());
+                module
+            }
+        };
+        import.module = Some(module);
+        Ok(())
+    }
+
+    fn find_import(&self, importing_path: &Path, path: &str) -> Option<PathBuf> {
+        let relative_path = importing_path.parent().unwrap_or_else(|| Path::new("")).join(path);
+        if relative_path.is_file() { return Some(relative_path); }
+
+        BaseDirectories::with_prefix("map2").ok()?.find_config_file(path)
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(ch) if UnicodeXID::is_xid_start(ch) || ch == '_') && chars.all(UnicodeXID::is_xid_continue)
 }
 
 
+/// Watches the loaded scripts, a message is sent whenever one of them changes. Dropping the watcher stops watching.
+///
+/// The directories are watched rather than the files themselves since editors often replace a file when saving it.
+pub fn watch_script_files(files: &[ScriptFile], changed_tx: mpsc::Sender<()>) -> Result<notify::RecommendedWatcher> {
+    let paths: HashSet<PathBuf> = files.iter()
+        .filter_map(|file| fs::canonicalize(&file.path).ok())
+        .collect();
+
+    let (watch_tx, watch_rx) = std::sync::mpsc::channel();
+    let mut watcher: notify::RecommendedWatcher = notify::Watcher::new(watch_tx, time::Duration::from_millis(300))?;
+    for dir in paths.iter().filter_map(|path| path.parent()).unique() {
+        watcher.watch(dir, notify::RecursiveMode::NonRecursive)?;
+    }
+
+    thread::spawn(move || {
+        // the channel closes once the watcher is dropped
+        while let Ok(event) = watch_rx.recv() {
+            let path = match event {
+                DebouncedEvent::Create(path) | DebouncedEvent::Write(path) | DebouncedEvent::Rename(_, path) => path,
+                _ => continue,
+            };
+            if !paths.contains(&path) { continue; }
+
+            if futures::executor::block_on(changed_tx.send(())).is_err() { return; }
+        }
+    });
+
+    Ok(watcher)
+}
+
 pub async fn evaluate_script(
     script_ast: Block,
     mut execution_message_tx: mpsc::Sender<ExecutionMessage>,
@@ -35,7 +150,104 @@ pub async fn evaluate_script(
         window_cycle_token,
         message_tx: Some(&mut execution_message_tx),
         modifier_state: &KeyModifierState::new(),
+        layer: None,
+        condition: Default::default(),
     };
 
-    eval_block(&script_ast, &mut GuardedVarMap::new(Mutex::new(VarMap::new(None))), &mut amb).await;
+    let ret = eval_block(&script_ast, &mut GuardedVarMap::new(Mutex::new(VarMap::new(None))), &mut amb).await;
+    report_runtime_error(&execution_message_tx, ret).await;
+    let _ = execution_message_tx.send(ExecutionMessage::ScriptEvaluated(window_cycle_token)).await;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A temporary directory that is removed once the test is done with it.
+    struct ScriptDir(PathBuf);
+
+    impl std::ops::Deref for ScriptDir {
+        type Target = Path;
+        fn deref(&self) -> &Path { &self.0 }
+    }
+
+    impl Drop for ScriptDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn write_scripts(name: &str, scripts: &[(&str, &str)]) -> ScriptDir {
+        let dir = std::env::temp_dir().join(format!("map2-test-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (file_name, source) in scripts {
+            fs::write(dir.join(file_name), source).unwrap();
+        }
+        ScriptDir(dir)
+    }
+
+    #[test]
+    fn test_load_script() {
+        let dir = write_scripts("imports", &[
+            ("main.m2", "import \"lib.m2\";\nimport \"lib.m2\" as other;\n"),
+            ("lib.m2", "export let a = 1;\nlet b = 2;\n"),
+        ]);
+        let (block, files) = load_script(&dir.join("main.m2")).unwrap();
+        assert_eq!(files.len(), 2);
+
+        let imports: Vec<&Import> = block.statements.iter()
+            .filter_map(|stmt| match &stmt.node {
+                Stmt::Import(import) => Some(import),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(imports[0].namespace.as_deref(), Some("lib"));
+        assert_eq!(imports[1].namespace.as_deref(), Some("other"));
+        assert_eq!(imports[0].module.as_ref().unwrap().exports, vec!["a".to_string()]);
+        assert!(Arc::ptr_eq(imports[0].module.as_ref().unwrap(), imports[1].module.as_ref().unwrap()));
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let dir = write_scripts("import-errors", &[
+            ("a.m2", "import \"b.m2\";\n"),
+            ("b.m2", "\nimport \"a.m2\";\n"),
+            ("missing.m2", "import \"nothing here.m2\";\n"),
+            ("broken.m2", "import \"syntax-error.m2\" as broken;\n"),
+            ("syntax-error.m2", "let a = ;\n"),
+            ("dashed.m2", "import \"my-layout.m2\";\n"),
+            ("my-layout.m2", ""),
+        ]);
+        let path = |name: &str| dir.join(name).display().to_string();
+
+        let err = load_script(&dir.join("a.m2")).unwrap_err().to_string();
+        assert!(err.starts_with(&format!("error: import cycle: {} -> {} -> {}\n --> {}, line 2, column 1",
+                                         path("a.m2"), path("b.m2"), path("a.m2"), path("b.m2"))), "{}", err);
+
+        let err = load_script(&dir.join("missing.m2")).unwrap_err().to_string();
+        assert!(err.starts_with(&format!("error: cannot find imported script 'nothing here.m2'\n --> {}, line 1", path("missing.m2"))), "{}", err);
+
+        // errors in imported scripts point to the imported script
+        let err = load_script(&dir.join("broken.m2")).unwrap_err().to_string();
+        assert!(err.contains(&format!(" --> {}, line ", path("syntax-error.m2"))), "{}", err);
+
+        let err = load_script(&dir.join("dashed.m2")).unwrap_err().to_string();
+        assert!(err.starts_with("error: 'my-layout' can't be used as a namespace, name it using 'import \"my-layout.m2\" as name;'"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_watch_script_files() {
+        let dir = write_scripts("watch", &[("main.m2", "let a = 1;\n"), ("notes.txt", "")]);
+        let files = vec![ScriptFile { path: dir.join("main.m2"), source: String::new() }];
+        let (changed_tx, mut changed_rx) = mpsc::channel(8);
+        let _watcher = watch_script_files(&files, changed_tx).unwrap();
+
+        // other files in the same directory are ignored
+        fs::write(dir.join("notes.txt"), "todo").unwrap();
+        assert!(tokio::time::timeout(time::Duration::from_secs(1), changed_rx.recv()).await.is_err());
+
+        fs::write(dir.join("main.m2"), "let a = 2;\n").unwrap();
+        assert_eq!(tokio::time::timeout(time::Duration::from_secs(5), changed_rx.recv()).await, Ok(Some(())));
+    }
 }
@@ -0,0 +1,192 @@
+use std::collections::HashSet;
+
+use crate::*;
+
+pub const DEFAULT_SEQUENCE_TIMEOUT: time::Duration = time::Duration::from_millis(1000);
+
+#[derive(Debug, Clone, Default)]
+struct SequenceNode {
+    children: HashMap<KeyClickActionWithMods, SequenceNode>,
+    target: Option<Arc<(Block, GuardedVarMap)>>,
+}
+
+/// Maps key sequences (i.e. leader key sequences) to blocks.
+#[derive(Debug, Clone, Default)]
+pub struct SequenceTrie {
+    root: SequenceNode,
+}
+
+impl SequenceTrie {
+    pub fn new() -> Self { Default::default() }
+
+    pub fn insert(&mut self, sequence: &[KeyClickActionWithMods], target: Arc<(Block, GuardedVarMap)>) {
+        let node = sequence.iter()
+            .fold(&mut self.root, |node, key| node.children.entry(*key).or_default());
+        node.target = Some(target);
+    }
+
+    fn get(&self, sequence: &[KeyClickActionWithMods]) -> Option<&SequenceNode> {
+        sequence.iter()
+            .try_fold(&self.root, |node, key| node.children.get(key))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SequenceStep {
+    /// pass the event on to the regular mapping stage
+    Process(InputEvent),
+    /// run the block of a matched sequence
+    Trigger(Arc<(Block, GuardedVarMap)>),
+    /// notify the sequence stage after the duration passed
+    StartTimer(usize, time::Duration),
+}
+
+#[derive(Debug)]
+struct PendingSequence {
+    matched: Vec<KeyClickActionWithMods>,
+    timer_id: usize,
+    buffer: Vec<InputEvent>,
+}
+
+/// Holds back key presses while they form a prefix of a sequence.
+#[derive(Debug)]
+pub struct SequenceState {
+    pub timeout: time::Duration,
+    /// keys that abort a partially matched sequence
+    pub cancel_keys: Vec<Key>,
+    pending: Option<PendingSequence>,
+    /// keys that are part of a matched or cancelled sequence, their remaining events are swallowed
+    consumed: HashSet<Key>,
+    timer_id: usize,
+}
+
+impl Default for SequenceState {
+    fn default() -> Self {
+        SequenceState {
+            timeout: DEFAULT_SEQUENCE_TIMEOUT,
+            cancel_keys: vec![*KEY_ESC],
+            pending: None,
+            consumed: Default::default(),
+            timer_id: 0,
+        }
+    }
+}
+
+impl SequenceState {
+    pub fn new() -> Self { Default::default() }
+
+    /// Drops the held back keys of a partially typed sequence and forgets the keys of matched sequences.
+    pub fn reset(&mut self) {
+        self.pending = None;
+        self.consumed.clear();
+    }
+
+    /// Feeds a key event into the sequence stage, returns `None` if the event is not affected by it.
+    pub fn handle_event(&mut self, ev: &InputEvent, modifiers: KeyModifierFlags, trie: &SequenceTrie) -> Option<Vec<SequenceStep>> {
+        let action = KeyAction::from_input_ev(ev);
+
+        if self.consumed.contains(&action.key) {
+            if action.value == TYPE_UP { self.consumed.remove(&action.key); }
+            return Some(vec![]);
+        }
+
+        // modifiers are needed to type the sequence, pass them on
+        if is_modifier_key(&action.key) { return None; }
+
+        let pending = match &mut self.pending {
+            Some(pending) => pending,
+            None => {
+                if action.value != TYPE_DOWN { return None; }
+
+                let matched = vec![KeyClickActionWithMods::new_with_mods(action.key, modifiers)];
+                let node = trie.get(&matched)?;
+                if node.children.is_empty() {
+                    self.consumed.insert(action.key);
+                    return node.target.clone().map(|target| vec![SequenceStep::Trigger(target)]);
+                }
+
+                self.timer_id += 1;
+                self.pending = Some(PendingSequence { matched, timer_id: self.timer_id, buffer: vec![ev.clone()] });
+                return Some(vec![SequenceStep::StartTimer(self.timer_id, self.timeout)]);
+            }
+        };
+
+        if action.value != TYPE_DOWN {
+            pending.buffer.push(ev.clone());
+            return Some(vec![]);
+        }
+
+        if self.cancel_keys.contains(&action.key) {
+            self.consume_pending();
+            self.consumed.insert(action.key);
+            return Some(vec![]);
+        }
+
+        pending.matched.push(KeyClickActionWithMods::new_with_mods(action.key, modifiers));
+        pending.buffer.push(ev.clone());
+
+        match trie.get(&pending.matched) {
+            Some(node) if !node.children.is_empty() => {
+                // the sequence might continue, wait for the next key
+                self.timer_id += 1;
+                pending.timer_id = self.timer_id;
+                Some(vec![SequenceStep::StartTimer(self.timer_id, self.timeout)])
+            }
+            Some(node) => {
+                let target = node.target.clone();
+                self.consume_pending();
+                Some(target.into_iter().map(SequenceStep::Trigger).collect())
+            }
+            None => {
+                pending.matched.pop();
+                pending.buffer.pop();
+
+                // a shorter sequence matched, the current key starts over
+                let mut steps = self.resolve(trie);
+                match self.handle_event(ev, modifiers, trie) {
+                    Some(next) => steps.extend(next),
+                    None => steps.push(SequenceStep::Process(ev.clone())),
+                }
+                Some(steps)
+            }
+        }
+    }
+
+    /// Called once the timeout of a pending sequence ran out.
+    pub fn handle_timeout(&mut self, timer_id: usize, trie: &SequenceTrie) -> Vec<SequenceStep> {
+        match &self.pending {
+            Some(pending) if pending.timer_id == timer_id => self.resolve(trie),
+            _ => vec![],
+        }
+    }
+
+    /// Triggers the pending sequence if it matches one, otherwise releases the held back keys unchanged.
+    fn resolve(&mut self, trie: &SequenceTrie) -> Vec<SequenceStep> {
+        let target = match &self.pending {
+            Some(pending) => trie.get(&pending.matched).and_then(|node| node.target.clone()),
+            None => return vec![],
+        };
+
+        match target {
+            Some(target) => {
+                self.consume_pending();
+                vec![SequenceStep::Trigger(target)]
+            }
+            None => self.pending.take().unwrap().buffer.into_iter().map(SequenceStep::Process).collect(),
+        }
+    }
+
+    /// Drops the held back keys, keys that are still pressed get swallowed until they are released.
+    fn consume_pending(&mut self) {
+        if let Some(pending) = self.pending.take() {
+            for ev in pending.buffer {
+                let action = KeyAction::from_input_ev(&ev);
+                if action.value == TYPE_UP {
+                    self.consumed.remove(&action.key);
+                } else {
+                    self.consumed.insert(action.key);
+                }
+            }
+        }
+    }
+}
@@ -1,17 +1,256 @@
+use std::collections::HashSet;
+use std::mem;
+
+use evdev_rs::enums::{EV_ABS, EV_REL};
+
 use ignore_list::*;
 
 use crate::*;
+use crate::script::ScriptFile;
+
+/// Mappings per trigger, conditional mappings come first since they take precedence.
+pub type KeyMappingTable = HashMap<KeyActionWithMods, Vec<(KeyActionCondition, Arc<(Block, GuardedVarMap)>)>>;
 
 #[derive(Clone, Debug)]
-pub struct CompiledKeyMappings(pub HashMap<KeyActionWithMods, Arc<(Block, GuardedVarMap)>>);
+pub struct CompiledKeyMappings {
+    pub base: KeyMappingTable,
+    pub layers: HashMap<String, KeyMappingTable>,
+    pub sequences: SequenceTrie,
+}
+
+impl CompiledKeyMappings {
+    pub fn new() -> Self { CompiledKeyMappings { base: Default::default(), layers: Default::default(), sequences: SequenceTrie::new() } }
+
+    pub fn table_mut(&mut self, layer: Option<String>) -> &mut KeyMappingTable {
+        match layer {
+            Some(layer) => self.layers.entry(layer).or_default(),
+            None => &mut self.base,
+        }
+    }
+
+    /// Adds a mapping, replacing an existing mapping with the same trigger and condition.
+    pub fn insert(&mut self, layer: Option<String>, condition: KeyActionCondition, from: KeyActionWithMods, to: Arc<(Block, GuardedVarMap)>) {
+        let targets = self.table_mut(layer).entry(from).or_default();
+        targets.retain(|(existing, _)| *existing != condition);
+
+        if condition.is_unconditional() {
+            targets.push((condition, to));
+        } else {
+            targets.insert(0, (condition, to));
+        }
+    }
 
-impl CompiledKeyMappings { pub fn new() -> Self { CompiledKeyMappings(Default::default()) } }
+    /// Removes the mapping with the given trigger and condition, returns `true` if a mapping was removed.
+    pub fn remove(&mut self, layer: Option<String>, condition: &KeyActionCondition, from: &KeyActionWithMods) -> bool {
+        let table = self.table_mut(layer);
+        let targets = match table.get_mut(from) {
+            Some(targets) => targets,
+            None => return false,
+        };
+
+        let len = targets.len();
+        targets.retain(|(existing, _)| existing != condition);
+        let removed = targets.len() != len;
+        if targets.is_empty() { table.remove(from); }
+        removed
+    }
+
+    /// Removes all key mappings, including layers and sequences.
+    pub fn clear(&mut self) {
+        self.base.clear();
+        self.layers.clear();
+        self.sequences = SequenceTrie::new();
+    }
+
+    /// Returns the triggers of the base mappings or of a layer, formatted the way they are written in scripts.
+    /// Triggers that are mapped for all key states are listed as a single key click.
+    pub fn triggers(&self, layer: Option<&String>) -> Vec<String> {
+        let table = match layer {
+            Some(layer) => match self.layers.get(layer) {
+                Some(table) => table,
+                None => return vec![],
+            },
+            None => &self.base,
+        };
+
+        let mut triggers: Vec<String> = table.keys()
+            .filter_map(|from| {
+                let is_mapped = |value| table.contains_key(&KeyActionWithMods { value, ..*from });
+                if !(is_mapped(TYPE_DOWN) && is_mapped(TYPE_UP) && is_mapped(TYPE_REPEAT)) { return Some(from.to_string()); }
+                if from.value != TYPE_DOWN { return None; }
+                Some(KeyClickActionWithMods::new_with_mods(from.key, from.modifiers).to_string())
+            })
+            .collect();
+        triggers.sort();
+        triggers
+    }
+
+    /// Returns whether any mapping is triggered by the given key.
+    pub fn is_mapped(&self, key: &Key) -> bool {
+        std::iter::once(&self.base)
+            .chain(self.layers.values())
+            .any(|table| table.keys().any(|from| from.key == *key))
+    }
+
+    /// Looks up the mapping in the given layers first, falling through to the base mappings.
+    /// Within a layer the actions are tried in order.
+    pub fn get(&self, layers: &[&String], actions: &[KeyActionWithMods], device: Option<&InputDeviceInfo>, window: Option<&ActiveWindowInfo>)
+               -> Option<&Arc<(Block, GuardedVarMap)>> {
+        self.find(layers, actions, device, window).map(|(_, _, _, target)| target)
+    }
+
+    /// Looks up the mapping of a key press like [`CompiledKeyMappings::get`], together with the targets the same
+    /// mapping has for the repeat and release events of the key. If the key press isn't mapped, the repeat and release
+    /// events are looked up on their own, i.e. for `{a up}::b`.
+    pub fn get_pressed(&self, layers: &[&String], actions: &[KeyActionWithMods], device: Option<&InputDeviceInfo>, window: Option<&ActiveWindowInfo>)
+                       -> (Option<Arc<(Block, GuardedVarMap)>>, PressedMapping) {
+        match self.find(layers, actions, device, window) {
+            Some((table, from, condition, target)) => {
+                let sibling = |value| table.get(&KeyActionWithMods { value, ..from })
+                    .and_then(|targets| targets.iter().find(|(existing, _)| existing == condition))
+                    .map(|(_, target)| target.clone());
+                (Some(target.clone()), PressedMapping { repeat: sibling(TYPE_REPEAT), up: sibling(TYPE_UP) })
+            }
+            None => {
+                let get = |value| {
+                    let actions: Vec<KeyActionWithMods> = actions.iter().map(|action| KeyActionWithMods { value, ..*action }).collect();
+                    self.get(layers, &actions, device, window).cloned()
+                };
+                (None, PressedMapping { repeat: get(TYPE_REPEAT), up: get(TYPE_UP) })
+            }
+        }
+    }
+
+    fn find(&self, layers: &[&String], actions: &[KeyActionWithMods], device: Option<&InputDeviceInfo>, window: Option<&ActiveWindowInfo>)
+            -> Option<(&KeyMappingTable, KeyActionWithMods, &KeyActionCondition, &Arc<(Block, GuardedVarMap)>)> {
+        layers.iter()
+            .filter_map(|layer| self.layers.get(*layer))
+            .chain(std::iter::once(&self.base))
+            .find_map(|table| actions.iter()
+                .filter_map(|action| table.get(action).map(|targets| (*action, targets)))
+                .flat_map(|(action, targets)| targets.iter().map(move |(condition, target)| (action, condition, target)))
+                .find(|(_, condition, _)| condition.matches(device, window))
+                .map(|(action, condition, target)| (table, action, condition, target))
+            )
+    }
+}
+
+/// The mapping that handled a key press. The repeat and release events of the key are handled by it as well, even if
+/// the active layers or the active window changed in the meantime.
+#[derive(Clone)]
+pub struct PressedMapping {
+    pub repeat: Option<Arc<(Block, GuardedVarMap)>>,
+    pub up: Option<Arc<(Block, GuardedVarMap)>>,
+}
+
+/// Callbacks that run when the active window changes.
+#[derive(Default)]
+pub struct WindowChangeHandlers {
+    pub focus: Vec<(Block, GuardedVarMap)>,
+    pub title: Vec<(Block, GuardedVarMap)>,
+}
+
+/// A reloaded script whose global scope is still being evaluated, it replaces the running script once it's done.
+///
+/// Holds every table and setting that is built by the global scope of a script, they are swapped with the ones of the
+/// running script all at once.
+pub struct PendingReload {
+    /// the window cycle token the new script is evaluated with, no other evaluation uses it
+    pub token: usize,
+    /// the task that evaluates the global scope of the new script
+    pub task: Option<task::JoinHandle<()>>,
+    /// set once the new script replaced the running one before its global scope finished, the tables then hold the
+    /// ones of the previous script
+    pub replaced: bool,
+    pub mappings: CompiledKeyMappings,
+    pub window_change_handlers: WindowChangeHandlers,
+    pub tap_hold: HashMap<Key, TapHoldMapping>,
+    pub combos: Vec<ComboMapping>,
+    pub combo_window: time::Duration,
+    pub sequence_timeout: time::Duration,
+    pub sequence_cancel_keys: Vec<Key>,
+    pub layers: LayerState,
+    pub rel_handlers: HashMap<EV_REL, (Vec<String>, Block, GuardedVarMap)>,
+    pub abs_handlers: HashMap<EV_ABS, (Vec<String>, Block, GuardedVarMap)>,
+    pub abs_thresholds: HashMap<EV_ABS, Vec<AbsThreshold>>,
+    pub abs_deadzones: HashMap<EV_ABS, AbsDeadzone>,
+}
+
+impl PendingReload {
+    pub fn new(token: usize) -> Self {
+        // the settings start out with their defaults, like they do for a freshly started script
+        let (combos, sequences) = (ComboState::new(), SequenceState::new());
+        PendingReload {
+            token,
+            task: None,
+            replaced: false,
+            mappings: CompiledKeyMappings::new(),
+            window_change_handlers: Default::default(),
+            tap_hold: Default::default(),
+            combos: vec![],
+            combo_window: combos.window,
+            sequence_timeout: sequences.timeout,
+            sequence_cancel_keys: sequences.cancel_keys,
+            layers: LayerState::new(),
+            rel_handlers: Default::default(),
+            abs_handlers: Default::default(),
+            abs_thresholds: Default::default(),
+            abs_deadzones: Default::default(),
+        }
+    }
+
+    /// Exchanges the tables of the reloaded script with the ones of the running script.
+    pub fn swap(&mut self, state: &mut State, mappings: &mut CompiledKeyMappings, window_change_handlers: &mut WindowChangeHandlers) {
+        mem::swap(&mut self.mappings, mappings);
+        mem::swap(&mut self.window_change_handlers, window_change_handlers);
+        mem::swap(&mut self.tap_hold, &mut state.tap_hold.mappings);
+        mem::swap(&mut self.combos, &mut state.combos.combos);
+        mem::swap(&mut self.combo_window, &mut state.combos.window);
+        mem::swap(&mut self.sequence_timeout, &mut state.sequences.timeout);
+        mem::swap(&mut self.sequence_cancel_keys, &mut state.sequences.cancel_keys);
+        mem::swap(&mut self.layers, &mut state.layers);
+        mem::swap(&mut self.rel_handlers, &mut state.mouse.handlers);
+        mem::swap(&mut self.abs_handlers, &mut state.gamepad.handlers);
+        mem::swap(&mut self.abs_thresholds, &mut state.gamepad.thresholds);
+        mem::swap(&mut self.abs_deadzones, &mut state.gamepad.deadzones);
+        state.mouse.update_mapped_wheels(mappings);
+    }
+}
+
+/// The keys that are held down on the output device, shared with the task that writes to it.
+pub type HeldKeys = Arc<Mutex<HashSet<Key>>>;
+
+/// Records a key event that got written to the output device.
+pub fn track_held_key(held_keys: &HeldKeys, ev: &InputEvent) {
+    if let EventCode::EV_KEY(_) = ev.event_code {
+        let key = Key { event_code: ev.event_code };
+        let mut held_keys = held_keys.lock().unwrap();
+        if ev.value == TYPE_UP { held_keys.remove(&key); } else { held_keys.insert(key); }
+    }
+}
 
 pub struct State {
     pub modifiers: Arc<KeyModifierState>,
 
     pub ignore_list: IgnoreList,
     pub active_window: Option<ActiveWindowInfo>,
+    pub tap_hold: TapHoldState,
+    pub layers: LayerState,
+    pub combos: ComboState,
+    pub sequences: SequenceState,
+    pub mouse: MouseState,
+    pub gamepad: GamepadState,
+    /// the device that sent the latest event of each key
+    pub key_devices: HashMap<Key, Arc<InputDeviceInfo>>,
+    pub error_policy: ErrorPolicy,
+    /// the running script and its imports, used to show where runtime errors occurred
+    pub script_files: Vec<ScriptFile>,
+    pub held_keys: HeldKeys,
+    /// the mappings that handled the key presses of the keys that are held down
+    pub pressed_mappings: HashMap<Key, PressedMapping>,
+    pub pending_reload: Option<PendingReload>,
+    /// the task that evaluates the global scope of the running script
+    pub script_task: Option<task::JoinHandle<()>>,
 }
 
 
@@ -21,6 +260,19 @@ impl State {
             modifiers: Arc::new(KeyModifierState::new()),
             ignore_list: IgnoreList::new(),
             active_window: None,
+            tap_hold: TapHoldState::new(),
+            layers: LayerState::new(),
+            combos: ComboState::new(),
+            sequences: SequenceState::new(),
+            mouse: MouseState::new(),
+            gamepad: GamepadState::new(),
+            key_devices: Default::default(),
+            error_policy: Default::default(),
+            script_files: vec![],
+            held_keys: Default::default(),
+            pressed_mappings: Default::default(),
+            pending_reload: None,
+            script_task: None,
         }
     }
 }
\ No newline at end of file
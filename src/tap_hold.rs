@@ -0,0 +1,162 @@
+use crate::*;
+
+pub const DEFAULT_TAPPING_TERM: time::Duration = time::Duration::from_millis(200);
+
+/// A dual-role key, acts as `tap` when pressed and released quickly and as `hold` when held down.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TapHoldMapping {
+    pub trigger: Key,
+    pub tap: KeyClickActionWithMods,
+    pub hold: KeyClickActionWithMods,
+    pub tapping_term: time::Duration,
+    /// resolve as hold if another key is pressed and released while the trigger is held
+    pub permissive_hold: bool,
+    /// resolve as hold as soon as another key is pressed while the trigger is held
+    pub hold_on_other_key_press: bool,
+}
+
+impl TapHoldMapping {
+    pub fn new(trigger: Key, tap: KeyClickActionWithMods, hold: KeyClickActionWithMods) -> Self {
+        TapHoldMapping {
+            trigger,
+            tap,
+            hold,
+            tapping_term: DEFAULT_TAPPING_TERM,
+            permissive_hold: false,
+            hold_on_other_key_press: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TapHoldStep {
+    /// write the action to the output device
+    Emit(KeyAction),
+    /// pass the event on to the regular mapping stage
+    Process(InputEvent),
+    /// notify the tap-hold stage after the duration passed
+    StartTimer(usize, time::Duration),
+}
+
+#[derive(Debug)]
+struct PendingTapHold {
+    mapping: TapHoldMapping,
+    timer_id: usize,
+    buffer: Vec<InputEvent>,
+}
+
+/// Resolves dual-role keys, events are held back while it is undecided whether a key is tapped or held.
+#[derive(Debug, Default)]
+pub struct TapHoldState {
+    pub mappings: HashMap<Key, TapHoldMapping>,
+    pending: Option<PendingTapHold>,
+    held: HashMap<Key, KeyClickActionWithMods>,
+    timer_id: usize,
+}
+
+impl TapHoldState {
+    pub fn new() -> Self { Default::default() }
+
+    /// Drops the held back events and forgets the keys that resolved as hold, the timer of a dropped key is ignored.
+    pub fn reset(&mut self) {
+        self.pending = None;
+        self.held.clear();
+    }
+
+    /// Feeds a key event into the tap-hold stage, returns `None` if the event is not affected by it.
+    pub fn handle_event(&mut self, ev: &InputEvent) -> Option<Vec<TapHoldStep>> {
+        let action = KeyAction::from_input_ev(ev);
+
+        // the trigger of a key that resolved as hold got released
+        if self.held.contains_key(&action.key) {
+            if action.value != TYPE_UP { return Some(vec![]); }
+
+            let hold = self.held.remove(&action.key).unwrap();
+            return Some(release_actions(&hold).into_iter().map(TapHoldStep::Emit).collect());
+        }
+
+        if let Some(pending) = &mut self.pending {
+            if action.key == pending.mapping.trigger {
+                if action.value != TYPE_UP { return Some(vec![]); }
+
+                // released within the tapping term
+                let pending = self.pending.take().unwrap();
+                let mut steps: Vec<TapHoldStep> = press_actions(&pending.mapping.tap).into_iter()
+                    .chain(release_actions(&pending.mapping.tap))
+                    .map(TapHoldStep::Emit)
+                    .collect();
+                steps.extend(pending.buffer.into_iter().map(TapHoldStep::Process));
+                return Some(steps);
+            }
+
+            let resolves_hold = if action.value == TYPE_DOWN {
+                pending.mapping.hold_on_other_key_press
+            } else if action.value == TYPE_UP {
+                // a different key was tapped while the trigger is still held
+                pending.mapping.permissive_hold && pending.buffer.iter()
+                    .any(|buffered| buffered.event_code == ev.event_code && buffered.value == TYPE_DOWN)
+            } else { false };
+
+            if resolves_hold {
+                let mut steps = self.resolve_hold();
+                steps.push(TapHoldStep::Process(ev.clone()));
+                return Some(steps);
+            }
+
+            pending.buffer.push(ev.clone());
+            return Some(vec![]);
+        }
+
+        if action.value == TYPE_DOWN {
+            if let Some(mapping) = self.mappings.get(&action.key) {
+                self.timer_id += 1;
+                let step = TapHoldStep::StartTimer(self.timer_id, mapping.tapping_term);
+                self.pending = Some(PendingTapHold { mapping: mapping.clone(), timer_id: self.timer_id, buffer: vec![] });
+                return Some(vec![step]);
+            }
+        }
+
+        None
+    }
+
+    /// Called once the timer of a pending key ran out, the key is treated as held from this point on.
+    pub fn handle_timeout(&mut self, timer_id: usize) -> Vec<TapHoldStep> {
+        match &self.pending {
+            Some(pending) if pending.timer_id == timer_id => self.resolve_hold(),
+            _ => vec![],
+        }
+    }
+
+    fn resolve_hold(&mut self) -> Vec<TapHoldStep> {
+        let pending = match self.pending.take() {
+            Some(pending) => pending,
+            None => return vec![],
+        };
+
+        let mut steps: Vec<TapHoldStep> = press_actions(&pending.mapping.hold).into_iter()
+            .map(TapHoldStep::Emit)
+            .collect();
+        self.held.insert(pending.mapping.trigger, pending.mapping.hold);
+        steps.extend(pending.buffer.into_iter().map(TapHoldStep::Process));
+        steps
+    }
+}
+
+fn press_actions(target: &KeyClickActionWithMods) -> Vec<KeyAction> {
+    let mut actions = vec![];
+    if target.modifiers.ctrl { actions.push(KeyAction::new(target.modifiers.ctrl_key(), TYPE_DOWN)); }
+    if target.modifiers.shift { actions.push(KeyAction::new(target.modifiers.shift_key(), TYPE_DOWN)); }
+    if target.modifiers.alt { actions.push(KeyAction::new(target.modifiers.alt_key(), TYPE_DOWN)); }
+    if target.modifiers.meta { actions.push(KeyAction::new(target.modifiers.meta_key(), TYPE_DOWN)); }
+    actions.push(KeyAction::new(target.key, TYPE_DOWN));
+    actions
+}
+
+fn release_actions(target: &KeyClickActionWithMods) -> Vec<KeyAction> {
+    let mut actions = vec![KeyAction::new(target.key, TYPE_UP)];
+    if target.modifiers.ctrl { actions.push(KeyAction::new(target.modifiers.ctrl_key(), TYPE_UP)); }
+    if target.modifiers.shift { actions.push(KeyAction::new(target.modifiers.shift_key(), TYPE_UP)); }
+    if target.modifiers.alt { actions.push(KeyAction::new(target.modifiers.alt_key(), TYPE_UP)); }
+    if target.modifiers.meta { actions.push(KeyAction::new(target.modifiers.meta_key(), TYPE_UP)); }
+    actions
+}
@@ -1,6 +1,9 @@
 use crate::*;
 use messaging::*;
+use std::path::PathBuf;
+
 use crate::cli::Configuration;
+use crate::script::ScriptFile;
 
 #[derive(Default)]
 pub struct ScriptTestingParameters<'a> {
@@ -10,10 +13,14 @@ pub struct ScriptTestingParameters<'a> {
 pub struct ScriptTestingAPI {
     pub event_delay: Option<u64>,
 
-    ev_reader_tx: mpsc::Sender<InputEvent>,
+    ev_reader_tx: mpsc::Sender<(Arc<InputDeviceInfo>, InputEvent)>,
     ev_writer_rx: mpsc::Receiver<InputEvent>,
+    window_tx: mpsc::Sender<WindowEvent>,
+    reload_tx: mpsc::Sender<(Block, Vec<ScriptFile>)>,
+    script_path: PathBuf,
     stop_tx: futures_intrusive::channel::shared::Sender<()>,
     stdout: Arc<tokio::sync::Mutex<Vec<u8>>>,
+    errors: Arc<tokio::sync::Mutex<Vec<RuntimeError>>>,
 }
 
 impl ScriptTestingAPI {
@@ -26,11 +33,15 @@ impl ScriptTestingAPI {
     }
 
     pub async fn write_event(&mut self, ev: InputEvent) -> Result<()> {
+        self.write_device_event(&InputDeviceInfo::default(), ev).await
+    }
+
+    pub async fn write_device_event(&mut self, device: &InputDeviceInfo, ev: InputEvent) -> Result<()> {
         if let Some(delay) = self.event_delay {
             sleep(delay);
         }
 
-        self.ev_reader_tx.send(ev).await?;
+        self.ev_reader_tx.send((Arc::new(device.clone()), ev)).await?;
         Ok(())
     }
 
@@ -38,6 +49,43 @@ impl ScriptTestingAPI {
         self.write_event(action.to_input_ev()).await
     }
 
+    #[allow(unused)]
+    pub async fn write_device_action(&mut self, device: &InputDeviceInfo, action: KeyAction) -> Result<()> {
+        self.write_device_event(device, action.to_input_ev()).await
+    }
+
+    #[allow(unused)]
+    pub async fn set_active_window(&mut self, window: ActiveWindowInfo) -> Result<()> {
+        if let Some(delay) = self.event_delay {
+            sleep(delay);
+        }
+
+        self.window_tx.send(WindowEvent::Focus(window)).await?;
+        Ok(())
+    }
+
+    #[allow(unused)]
+    pub async fn change_active_window_title(&mut self, window: ActiveWindowInfo) -> Result<()> {
+        if let Some(delay) = self.event_delay {
+            sleep(delay);
+        }
+
+        self.window_tx.send(WindowEvent::TitleChange(window)).await?;
+        Ok(())
+    }
+
+    /// Reloads the script from disk the way a changed script file is reloaded, fails if the script can't be loaded.
+    #[allow(unused)]
+    pub async fn reload_script(&mut self) -> Result<()> {
+        if let Some(delay) = self.event_delay {
+            sleep(delay);
+        }
+
+        let (script_ast, script_files) = script::load_script(&self.script_path)?;
+        self.reload_tx.send((script_ast, script_files)).await.map_err(|_| anyhow!("the script stopped"))?;
+        Ok(())
+    }
+
     pub async fn collect_output_ev(&mut self) -> Vec<InputEvent> {
         let mut vec = vec![];
         while let Ok(ev) = self.ev_writer_rx.try_recv() {
@@ -55,46 +103,86 @@ impl ScriptTestingAPI {
 
     #[allow(unused)]
     pub async fn reset_stdout(&mut self) { self.stdout.lock().await.clear(); }
+
+    /// Returns the runtime errors reported since the last call.
+    #[allow(unused)]
+    pub async fn collect_errors(&mut self) -> Vec<RuntimeError> {
+        self.errors.lock().await.drain(..).collect()
+    }
 }
 
 pub async fn test_script(
     parameters: ScriptTestingParameters<'_>,
 ) -> Result<ScriptTestingAPI> {
-    let mut config = Configuration {
-        script_file: fs::File::open(parameters.script_path)?,
+    let config = Configuration {
+        script_path: parameters.script_path.into(),
         verbosity: 0,
         devices: vec![],
+        error_policy: ErrorPolicy::Continue,
     };
 
-    let script_ast = script::parse_script(&mut config.script_file);
+    let (script_ast, script_files) = script::load_script(&config.script_path)?;
+    let script_path = config.script_path.clone();
 
     let mut state = State::new();
-    let window_cycle_token: usize = 0;
+    state.script_files = script_files;
+    let mut window_cycle_token: usize = 0;
     let mut mappings = CompiledKeyMappings::new();
-    let mut window_change_handlers = vec![];
+    let mut window_change_handlers = WindowChangeHandlers::default();
     let stdout = Arc::new(tokio::sync::Mutex::new(vec![]));
+    let errors = Arc::new(tokio::sync::Mutex::new(vec![]));
 
     let (execution_message_tx, mut execution_message_rx) = mpsc::channel(128);
     let (ev_reader_tx, mut ev_reader_rx) = mpsc::channel(128);
-    let (mut ev_writer_tx, ev_writer_rx) = mpsc::channel(128);
+    let (mut ev_writer_tx, mut output_rx) = mpsc::channel(128);
+    let script_ev_writer_tx = ev_writer_tx.clone();
+
+    // stands in for the output device
+    let (output_tx, ev_writer_rx) = mpsc::channel(128);
+    {
+        let held_keys = state.held_keys.clone();
+        task::spawn(async move {
+            while let Some(ev) = output_rx.recv().await {
+                track_held_key(&held_keys, &ev);
+                if output_tx.send(ev).await.is_err() { return; }
+            }
+        });
+    }
+
+    let (reload_tx, mut reload_rx) = mpsc::channel(1);
+
+    let (window_tx, mut window_rx) = mpsc::channel(128);
 
     let (stop_tx, stop_rx) = futures_intrusive::channel::shared::unbuffered_channel();
     {
         let mut execution_message_tx = execution_message_tx.clone();
         let stdout = stdout.clone();
+        let errors = errors.clone();
         task::spawn(async move {
             loop {
                 tokio::select! {
-                        Some(ev) = ev_reader_rx.recv() => {
-                            event_handlers::handle_stdin_ev(&mut state, ev, &mut mappings,
+                        Some(window_event) = window_rx.recv() => {
+                            event_handlers::handle_window_event(&mut state, window_event, &mut window_cycle_token,
+                                &mut window_change_handlers, &mut ev_writer_tx, &mut execution_message_tx);
+                        }
+                        Some((device, ev)) = ev_reader_rx.recv() => {
+                            event_handlers::handle_stdin_ev(&mut state, device, ev, &mut mappings,
                                 &mut ev_writer_tx, &mut execution_message_tx, window_cycle_token, &config).await.unwrap();
                         }
                         Some(msg) = execution_message_rx.recv() => {
                             // don't terminate during testing
                             if let ExecutionMessage::Exit(_) = msg{ return; }
+                            if let ExecutionMessage::RuntimeError(err) = msg {
+                                errors.lock().await.push(err);
+                                continue;
+                            }
 
-                            event_handlers::handle_execution_message(&mut *stdout.lock().await, window_cycle_token, msg, &mut state,
-                                &mut mappings, &mut window_change_handlers).await;
+                            event_handlers::handle_execution_message(&mut *stdout.lock().await, &mut window_cycle_token, msg, &mut state,
+                                &mut mappings, &mut window_change_handlers, &mut ev_writer_tx, &mut execution_message_tx).await;
+                        }
+                        Some((script_ast, script_files)) = reload_rx.recv() => {
+                            event_handlers::reload_script(&mut state, script_ast, script_files, &mut window_cycle_token,
+                                &mut ev_writer_tx, &mut execution_message_tx).await;
                         }
                         Some(_) = stop_rx.receive() => {
                             return;
@@ -104,13 +192,17 @@ pub async fn test_script(
         });
     }
 
-    script::evaluate_script(script_ast, execution_message_tx, ev_reader_tx.clone(), 0).await;
+    script::evaluate_script(script_ast, execution_message_tx, script_ev_writer_tx, 0).await;
 
     let api = ScriptTestingAPI {
         ev_reader_tx,
         ev_writer_rx,
+        window_tx,
+        reload_tx,
+        script_path,
         stop_tx,
         stdout,
+        errors,
         event_delay: None,
     };
 
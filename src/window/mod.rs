@@ -0,0 +1,187 @@
+use std::time;
+
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
+use regex::Regex;
+use tokio::sync::mpsc;
+use tokio::task;
+
+#[cfg(feature = "x11")]
+pub mod x11;
+pub mod sway;
+pub mod process;
+
+pub use process::ProcessInfo;
+
+/// How long to wait before trying to reconnect to the display server.
+pub const RECONNECT_INTERVAL: time::Duration = time::Duration::from_secs(1);
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ActiveWindowInfo {
+    pub class: String,
+    pub instance: String,
+    pub name: String,
+    /// the process that owns the window, if the backend knows about it
+    pub pid: Option<u32>,
+    pub process: Option<ProcessInfo>,
+    pub geometry: Option<WindowGeometry>,
+}
+
+/// The position and size of a window in absolute screen coordinates.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Selects windows by a regular expression matching their class, instance, title or process name.
+#[derive(Debug, Clone)]
+pub enum WindowSelector {
+    Class(Regex),
+    Instance(Regex),
+    Title(Regex),
+    Process(Regex),
+}
+
+impl WindowSelector {
+    /// Parses a selector in the form of `class:<regex>`, `instance:<regex>`, `title:<regex>` or `process:<regex>`,
+    /// selectors without a prefix match the window class.
+    pub fn parse(raw: &str) -> Result<Self> {
+        let parse_regex = |pattern: &str| Regex::new(pattern)
+            .map_err(|err| anyhow!("invalid window selector '{}': {}", raw, err));
+
+        if let Some(pattern) = raw.strip_prefix("class:") { return Ok(WindowSelector::Class(parse_regex(pattern)?)); }
+        if let Some(pattern) = raw.strip_prefix("instance:") { return Ok(WindowSelector::Instance(parse_regex(pattern)?)); }
+        if let Some(pattern) = raw.strip_prefix("title:") { return Ok(WindowSelector::Title(parse_regex(pattern)?)); }
+        if let Some(pattern) = raw.strip_prefix("process:") { return Ok(WindowSelector::Process(parse_regex(pattern)?)); }
+        Ok(WindowSelector::Class(parse_regex(raw)?))
+    }
+
+    pub fn matches(&self, window: &ActiveWindowInfo) -> bool {
+        match self {
+            WindowSelector::Class(regex) => regex.is_match(&window.class),
+            WindowSelector::Instance(regex) => regex.is_match(&window.instance),
+            WindowSelector::Title(regex) => regex.is_match(&window.name),
+            WindowSelector::Process(regex) => window.process.as_ref()
+                .map_or(false, |process| regex.is_match(&process.name)),
+        }
+    }
+}
+
+impl PartialEq for WindowSelector {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (WindowSelector::Class(a), WindowSelector::Class(b)) => a.as_str() == b.as_str(),
+            (WindowSelector::Instance(a), WindowSelector::Instance(b)) => a.as_str() == b.as_str(),
+            (WindowSelector::Title(a), WindowSelector::Title(b)) => a.as_str() == b.as_str(),
+            (WindowSelector::Process(a), WindowSelector::Process(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowEvent {
+    /// a different window got focused
+    Focus(ActiveWindowInfo),
+    /// the title of the focused window changed
+    TitleChange(ActiveWindowInfo),
+}
+
+impl WindowEvent {
+    pub fn window_mut(&mut self) -> &mut ActiveWindowInfo {
+        match self {
+            WindowEvent::Focus(window) | WindowEvent::TitleChange(window) => window,
+        }
+    }
+}
+
+/// A source of active window information, i.e. the X server or a wayland compositor.
+pub trait WindowInfoBackend: Send {
+    /// Waits until a different window got focused or the title of the focused window changed.
+    fn next_event(&mut self) -> BoxFuture<'_, Result<WindowEvent>>;
+}
+
+/// Picks the window info backend based on the environment, returns `None` if there is no display server to get
+/// window information from, i.e. when running on a TTY. Compositors that speak the i3 IPC protocol are preferred
+/// since X11 only knows about XWayland windows on wayland.
+pub async fn initialize_window_backend() -> Result<Option<Box<dyn WindowInfoBackend>>> {
+    if let Some(socket_path) = sway::socket_path_from_env() {
+        return Ok(Some(Box::new(sway::SwayBackend::connect(&socket_path).await?)));
+    }
+    initialize_x11_backend().await
+}
+
+#[cfg(feature = "x11")]
+async fn initialize_x11_backend() -> Result<Option<Box<dyn WindowInfoBackend>>> {
+    if std::env::var_os("DISPLAY").map_or(true, |display| display.is_empty()) { return Ok(None); }
+    Ok(Some(Box::new(x11::x11_initialize().await?)))
+}
+
+#[cfg(not(feature = "x11"))]
+async fn initialize_x11_backend() -> Result<Option<Box<dyn WindowInfoBackend>>> { Ok(None) }
+
+/// Sends window events until the receiver is dropped, reconnects if the connection to the display server gets lost.
+/// Returns immediately if there is no display server.
+pub async fn watch_window_events(window_ev_tx: mpsc::Sender<WindowEvent>) {
+    let mut reported_error = false;
+    loop {
+        match initialize_window_backend().await {
+            Ok(Some(mut backend)) => {
+                reported_error = false;
+                loop {
+                    let mut event = match backend.next_event().await {
+                        Ok(event) => event,
+                        Err(err) => {
+                            eprintln!("lost connection to the display server, reconnecting: {}", err);
+                            break;
+                        }
+                    };
+                    let window = event.window_mut();
+                    // reading from /proc blocks
+                    window.process = match window.pid {
+                        Some(pid) => task::spawn_blocking(move || ProcessInfo::from_pid(pid)).await.ok().flatten(),
+                        None => None,
+                    };
+
+                    if window_ev_tx.send(event).await.is_err() { return; }
+                }
+            }
+            Ok(None) => return,
+            // the display server might not be up yet, only report the first failed attempt
+            Err(err) if !reported_error => {
+                eprintln!("failed to connect to the display server, window information is unavailable: {}", err);
+                reported_error = true;
+            }
+            Err(_) => {}
+        }
+        tokio::time::sleep(RECONNECT_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_selector() {
+        let window = ActiveWindowInfo {
+            class: "firefox".to_string(),
+            instance: "Navigator".to_string(),
+            name: "GitHub - Mozilla Firefox".to_string(),
+            process: Some(ProcessInfo { name: "firefox-bin".to_string(), ..Default::default() }),
+            ..Default::default()
+        };
+
+        assert!(WindowSelector::parse("firefox").unwrap().matches(&window));
+        assert!(WindowSelector::parse("class:^firefox$").unwrap().matches(&window));
+        assert!(WindowSelector::parse("instance:Navigator").unwrap().matches(&window));
+        assert!(WindowSelector::parse("title:^GitHub").unwrap().matches(&window));
+        assert!(!WindowSelector::parse("title:^Firefox").unwrap().matches(&window));
+        assert!(WindowSelector::parse("process:^firefox").unwrap().matches(&window));
+        assert!(!WindowSelector::parse("process:^firefox").unwrap().matches(&ActiveWindowInfo::default()));
+        assert!(WindowSelector::parse("class:(").is_err());
+    }
+}
@@ -0,0 +1,55 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The process that owns a window, read from `/proc`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProcessInfo {
+    /// the file name of the executable, i.e. "firefox"
+    pub name: String,
+    /// the path of the executable, only readable for processes of the same user
+    pub exe: Option<PathBuf>,
+    pub cmdline: Vec<String>,
+}
+
+impl ProcessInfo {
+    /// Returns `None` if the process doesn't exist (anymore).
+    pub fn from_pid(pid: u32) -> Option<Self> {
+        Self::from_proc_dir(&Path::new("/proc").join(pid.to_string()))
+    }
+
+    fn from_proc_dir(dir: &Path) -> Option<Self> {
+        let cmdline: Vec<String> = fs::read(dir.join("cmdline")).ok()?
+            .split(|&b| b == 0)
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| String::from_utf8_lossy(arg).to_string())
+            .collect();
+        let exe = fs::read_link(dir.join("exe")).ok();
+
+        let file_name = |path: &Path| path.file_name().map(|name| name.to_string_lossy().to_string());
+        // fall back to the command line and the kernel's process name if the executable is not accessible
+        let name = exe.as_deref().and_then(file_name)
+            .or_else(|| cmdline.first().and_then(|arg| file_name(Path::new(arg))))
+            .or_else(|| fs::read_to_string(dir.join("comm")).ok().map(|comm| comm.trim_end().to_string()))
+            .unwrap_or_default();
+
+        Some(ProcessInfo { name, exe, cmdline })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_info() {
+        let process = ProcessInfo::from_pid(std::process::id()).unwrap();
+        let exe = std::env::current_exe().unwrap();
+
+        assert_eq!(process.exe.as_ref(), Some(&exe));
+        assert_eq!(process.name, exe.file_name().unwrap().to_string_lossy());
+        assert_eq!(process.cmdline, std::env::args().collect::<Vec<_>>());
+
+        assert_eq!(ProcessInfo::from_pid(u32::MAX), None);
+    }
+}
@@ -0,0 +1,175 @@
+use std::convert::TryInto;
+use std::env;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::UnixStream;
+
+use super::*;
+
+const MAGIC: &[u8] = b"i3-ipc";
+const MESSAGE_SUBSCRIBE: u32 = 2;
+const EVENT_WINDOW: u32 = 0x80000003;
+
+/// Tracks the active window of sway, i3 and other compositors that implement the i3 IPC protocol.
+pub struct SwayBackend {
+    stream: UnixStream,
+}
+
+/// Returns the IPC socket of the running compositor, sway takes precedence over i3.
+pub fn socket_path_from_env() -> Option<PathBuf> {
+    env::var_os("SWAYSOCK")
+        .or_else(|| env::var_os("I3SOCK"))
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+impl SwayBackend {
+    pub async fn connect(socket_path: &Path) -> Result<Self> {
+        let mut stream = UnixStream::connect(socket_path).await
+            .map_err(|err| anyhow!("failed to connect to the IPC socket '{}': {}", socket_path.display(), err))?;
+
+        write_message(&mut stream, MESSAGE_SUBSCRIBE, br#"["window"]"#).await?;
+        let (_, reply) = read_message(&mut stream).await?;
+        let reply: Value = serde_json::from_slice(&reply)?;
+        if reply["success"] != Value::Bool(true) {
+            return Err(anyhow!("failed to subscribe to window events: {}", reply));
+        }
+
+        Ok(SwayBackend { stream })
+    }
+}
+
+impl WindowInfoBackend for SwayBackend {
+    fn next_event(&mut self) -> BoxFuture<'_, Result<WindowEvent>> {
+        Box::pin(async move {
+            loop {
+                let (message_type, payload) = read_message(&mut self.stream).await?;
+                if message_type != EVENT_WINDOW { continue; }
+
+                if let Some(event) = parse_window_event(&payload)? { return Ok(event); }
+            }
+        })
+    }
+}
+
+async fn write_message(stream: &mut (impl AsyncWrite + Unpin), message_type: u32, payload: &[u8]) -> Result<()> {
+    let mut message = MAGIC.to_vec();
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&message_type.to_ne_bytes());
+    message.extend_from_slice(payload);
+    stream.write_all(&message).await?;
+    Ok(())
+}
+
+async fn read_message(stream: &mut (impl AsyncRead + Unpin)) -> Result<(u32, Vec<u8>)> {
+    let mut header = [0u8; 14];
+    stream.read_exact(&mut header).await?;
+    if &header[..6] != MAGIC { return Err(anyhow!("invalid IPC message header")); }
+
+    let length = u32::from_ne_bytes(header[6..10].try_into().unwrap());
+    let message_type = u32::from_ne_bytes(header[10..14].try_into().unwrap());
+
+    let mut payload = vec![0u8; length as usize];
+    stream.read_exact(&mut payload).await?;
+    Ok((message_type, payload))
+}
+
+/// Turns the payload of a window event into a focus or title change, other changes are ignored.
+fn parse_window_event(payload: &[u8]) -> Result<Option<WindowEvent>> {
+    let event: Value = serde_json::from_slice(payload)?;
+    let container = &event["container"];
+
+    let string = |value: &Value| value.as_str().unwrap_or("").to_string();
+    // X11 windows running through XWayland have window properties, native wayland windows only have an app id
+    let properties = &container["window_properties"];
+    let app_id = string(&container["app_id"]);
+    let info = ActiveWindowInfo {
+        class: properties["class"].as_str().map(String::from).unwrap_or_else(|| app_id.clone()),
+        instance: properties["instance"].as_str().map(String::from).unwrap_or(app_id),
+        name: string(&container["name"]),
+        pid: container["pid"].as_u64().map(|pid| pid as u32),
+        process: None,
+        geometry: parse_rect(&container["rect"]),
+    };
+
+    match event["change"].as_str() {
+        Some("focus") => Ok(Some(WindowEvent::Focus(info))),
+        Some("title") if container["focused"] == Value::Bool(true) => Ok(Some(WindowEvent::TitleChange(info))),
+        _ => Ok(None),
+    }
+}
+
+fn parse_rect(rect: &Value) -> Option<WindowGeometry> {
+    Some(WindowGeometry {
+        x: rect["x"].as_i64()? as i32,
+        y: rect["y"].as_i64()? as i32,
+        width: rect["width"].as_u64()? as u32,
+        height: rect["height"].as_u64()? as u32,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fake_ipc_socket() {
+        let socket_path = env::temp_dir().join(format!("map2-test-ipc-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket_path);
+        let listener = tokio::net::UnixListener::bind(&socket_path).unwrap();
+
+        let compositor = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+
+            let (message_type, payload) = read_message(&mut stream).await.unwrap();
+            assert_eq!(message_type, MESSAGE_SUBSCRIBE);
+            assert_eq!(payload, br#"["window"]"#.to_vec());
+            write_message(&mut stream, MESSAGE_SUBSCRIBE, br#"{"success":true}"#).await.unwrap();
+
+            // workspace event
+            write_message(&mut stream, 0x80000000, br#"{"change":"focus"}"#).await.unwrap();
+            write_message(&mut stream, EVENT_WINDOW, br#"{"change":"focus","container":{
+                "name":"GitHub - Mozilla Firefox","focused":true,"app_id":null,"pid":4242,
+                "rect":{"x":1920,"y":28,"width":1920,"height":1052},
+                "window_properties":{"class":"firefox","instance":"Navigator"}}}"#).await.unwrap();
+            write_message(&mut stream, EVENT_WINDOW, br#"{"change":"new","container":{
+                "name":"foot","focused":false,"app_id":"foot"}}"#).await.unwrap();
+            write_message(&mut stream, EVENT_WINDOW, br#"{"change":"title","container":{
+                "name":"~/projects","focused":false,"app_id":"foot"}}"#).await.unwrap();
+            write_message(&mut stream, EVENT_WINDOW, br#"{"change":"focus","container":{
+                "name":"foot","focused":true,"app_id":"foot"}}"#).await.unwrap();
+            write_message(&mut stream, EVENT_WINDOW, br#"{"change":"title","container":{
+                "name":"vim","focused":true,"app_id":"foot"}}"#).await.unwrap();
+        });
+
+        let mut backend = SwayBackend::connect(&socket_path).await.unwrap();
+        assert_eq!(backend.next_event().await.unwrap(), WindowEvent::Focus(ActiveWindowInfo {
+            class: "firefox".to_string(),
+            instance: "Navigator".to_string(),
+            name: "GitHub - Mozilla Firefox".to_string(),
+            pid: Some(4242),
+            process: None,
+            geometry: Some(WindowGeometry { x: 1920, y: 28, width: 1920, height: 1052 }),
+        }));
+        assert_eq!(backend.next_event().await.unwrap(), WindowEvent::Focus(ActiveWindowInfo {
+            class: "foot".to_string(),
+            instance: "foot".to_string(),
+            name: "foot".to_string(),
+            ..Default::default()
+        }));
+        assert_eq!(backend.next_event().await.unwrap(), WindowEvent::TitleChange(ActiveWindowInfo {
+            class: "foot".to_string(),
+            instance: "foot".to_string(),
+            name: "vim".to_string(),
+            ..Default::default()
+        }));
+
+        compositor.await.unwrap();
+        // the compositor closed the connection
+        assert!(backend.next_event().await.is_err());
+        std::fs::remove_file(&socket_path).unwrap();
+    }
+}
@@ -0,0 +1,216 @@
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::Arc;
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+use tokio::io::unix::AsyncFd;
+use tokio::task;
+use x11rb::connection::Connection;
+use x11rb::protocol::Event;
+use x11rb::protocol::Event::PropertyNotify;
+use x11rb::protocol::xproto::{Atom, AtomEnum, ChangeWindowAttributesAux, ConnectionExt, EventMask, GetPropertyReply, intern_atom, Screen, Window};
+use x11rb::rust_connection::RustConnection;
+use x11rb::x11_utils::TryParse;
+
+use super::*;
+
+/// The socket of the X server connection, the connection itself keeps ownership of it.
+struct ConnectionFd(RawFd);
+
+impl AsRawFd for ConnectionFd {
+    fn as_raw_fd(&self) -> RawFd { self.0 }
+}
+
+#[allow(non_snake_case)]
+#[derive(Clone, Copy)]
+struct Atoms {
+    NET_ACTIVE_WINDOW: Atom,
+    NET_WM_NAME: Atom,
+    NET_WM_PID: Atom,
+    UTF8_STRING: Atom,
+}
+
+pub struct X11State {
+    /// shared with the blocking tasks that wait for replies
+    con: Arc<RustConnection>,
+    /// notifies about incoming data on the connection
+    fd: AsyncFd<ConnectionFd>,
+    root: Window,
+    atoms: Atoms,
+    /// the window that is watched for title changes
+    focused: Option<Window>,
+}
+
+pub async fn x11_initialize() -> Result<X11State> {
+    // connecting waits for the X server, a slow X server must not stall the runtime
+    let (con, root, atoms) = task::spawn_blocking(x11_connect).await??;
+    let fd = AsyncFd::new(ConnectionFd(con.stream().as_raw_fd()))?;
+
+    Ok(X11State {
+        con: Arc::new(con),
+        fd,
+        root,
+        atoms,
+        focused: None,
+    })
+}
+
+fn x11_connect() -> Result<(RustConnection, Window, Atoms)> {
+    let (con, screen_id) = RustConnection::connect(None)?;
+    let screen: &Screen = &con.setup().roots[screen_id];
+    let root: Window = screen.root;
+
+    con.change_window_attributes(root, &ChangeWindowAttributesAux::new()
+        .event_mask(Some(EventMask::SubstructureNotify | EventMask::PropertyChange)))?;
+    con.flush()?;
+
+    let intern = |name: &[u8]| -> Result<Atom> { Ok(intern_atom(&con, false, name)?.reply()?.atom) };
+    let atoms = Atoms {
+        NET_ACTIVE_WINDOW: intern(b"_NET_ACTIVE_WINDOW")?,
+        NET_WM_NAME: intern(b"_NET_WM_NAME")?,
+        NET_WM_PID: intern(b"_NET_WM_PID")?,
+        UTF8_STRING: intern(b"UTF8_STRING")?,
+    };
+
+    Ok((con, root, atoms))
+}
+
+impl WindowInfoBackend for X11State {
+    fn next_event(&mut self) -> BoxFuture<'_, Result<WindowEvent>> {
+        Box::pin(get_window_info_x11(self))
+    }
+}
+
+pub async fn get_window_info_x11(state: &mut X11State) -> Result<WindowEvent> {
+    loop {
+        let event = wait_for_event(state).await?;
+
+        if let PropertyNotify(ev) = event {
+            if ev.window == state.root && ev.atom == state.atoms.NET_ACTIVE_WINDOW {
+                let (window, res) = query_active_window(state).await?;
+                watch_title_changes(state, window)?;
+                return Ok(WindowEvent::Focus(res));
+            }
+
+            if ev.atom == state.atoms.NET_WM_NAME && state.focused == Some(ev.window) {
+                let (_, res) = query_active_window(state).await?;
+                return Ok(WindowEvent::TitleChange(res));
+            }
+        }
+    }
+}
+
+async fn wait_for_event(state: &X11State) -> Result<Event> {
+    loop {
+        // events might have been read already while waiting for a reply
+        if let Some(event) = state.con.poll_for_event()? { return Ok(event); }
+
+        let mut guard = state.fd.readable().await?;
+        guard.clear_ready();
+    }
+}
+
+/// Listens to property changes of the focused window, the previously focused window is not watched anymore.
+fn watch_title_changes(state: &mut X11State, window: Window) -> Result<()> {
+    if state.focused == Some(window) { return Ok(()); }
+
+    if let Some(previous) = state.focused.take() {
+        // the window might be gone already, the resulting error event is ignored
+        state.con.change_window_attributes(previous, &ChangeWindowAttributesAux::new()
+            .event_mask(Some(u32::from(EventMask::NoEvent))))?;
+    }
+    if window != state.root {
+        state.con.change_window_attributes(window, &ChangeWindowAttributesAux::new()
+            .event_mask(Some(u32::from(EventMask::PropertyChange))))?;
+        state.focused = Some(window);
+    }
+    state.con.flush()?;
+    Ok(())
+}
+
+async fn query_active_window(state: &X11State) -> Result<(Window, ActiveWindowInfo)> {
+    let (con, root, atoms) = (state.con.clone(), state.root, state.atoms);
+    // waiting for the replies blocks
+    task::spawn_blocking(move || x11_get_active_window(&con, root, &atoms)).await?
+}
+
+fn x11_get_active_window(con: &RustConnection, root: Window, atoms: &Atoms) -> Result<(Window, ActiveWindowInfo)> {
+    let focus = find_active_window(con, root, atoms.NET_ACTIVE_WINDOW)?;
+
+    let (wm_class, string, cardinal): (Atom, Atom, Atom) =
+        (AtomEnum::WM_CLASS.into(), AtomEnum::STRING.into(), AtomEnum::CARDINAL.into());
+
+    // send all requests before waiting for the first reply
+    let name = con.get_property(false, focus, atoms.NET_WM_NAME, atoms.UTF8_STRING, 0, u32::max_value())?;
+    let class = con.get_property(false, focus, wm_class, string, 0, u32::max_value())?;
+    let pid = con.get_property(false, focus, atoms.NET_WM_PID, cardinal, 0, 1)?;
+    let geometry = con.get_geometry(focus)?;
+    let position = con.translate_coordinates(focus, root, 0, 0)?;
+
+    let (name, class, pid) = (name.reply()?, class.reply()?, pid.reply()?);
+    let (instance, class) = parse_wm_class(&class);
+
+    // the root window has no geometry when nothing is focused
+    let geometry = match (geometry.reply(), position.reply()) {
+        (Ok(geometry), Ok(position)) => Some(WindowGeometry {
+            x: position.dst_x as i32,
+            y: position.dst_y as i32,
+            width: geometry.width as u32,
+            height: geometry.height as u32,
+        }),
+        _ => None,
+    };
+
+    Ok((focus, ActiveWindowInfo {
+        class: class.to_string(),
+        instance: instance.to_string(),
+        name: parse_string_property(&name).to_string(),
+        pid: parse_cardinal_property(&pid),
+        process: None,
+        geometry,
+    }))
+}
+
+fn find_active_window(conn: &impl Connection, root: Window, net_active_window: Atom) -> Result<Window> {
+    let window: Atom = AtomEnum::WINDOW.into();
+    let active_window = conn.get_property(false, root, net_active_window, window, 0, 1)?.reply()?;
+    if active_window.format == 32 && active_window.length == 1 {
+        // Things will be so much easier with the next release:
+        // This does active_window.value32().next().unwrap()
+        Ok(u32::try_parse(&active_window.value)?.0)
+    } else {
+        // Query the input focus
+        Ok(conn.get_input_focus()?.reply()?.focus)
+    }
+}
+
+fn parse_string_property(property: &GetPropertyReply) -> &str {
+    std::str::from_utf8(&property.value).unwrap_or("Invalid utf8")
+}
+
+fn parse_cardinal_property(property: &GetPropertyReply) -> Option<u32> {
+    if property.format != 32 { return None; }
+    u32::try_parse(&property.value).ok().map(|(value, _)| value)
+}
+
+fn parse_wm_class(property: &GetPropertyReply) -> (&str, &str) {
+    if property.format != 8 {
+        return ("Malformed property: wrong format", "Malformed property: wrong format");
+    }
+    let value = &property.value;
+    // The property should contain two null-terminated strings. Find them.
+    if let Some(middle) = value.iter().position(|&b| b == 0) {
+        let (instance, class) = value.split_at(middle);
+        // Skip the null byte at the beginning
+        let mut class = &class[1..];
+        // Remove the last null byte from the class, if it is there.
+        if class.last() == Some(&0) {
+            class = &class[..class.len() - 1];
+        }
+        let instance = std::str::from_utf8(instance);
+        let class = std::str::from_utf8(class);
+        (instance.unwrap_or("Invalid utf8"), class.unwrap_or("Invalid utf8"))
+    } else {
+        ("Missing null byte", "Missing null byte")
+    }
+}